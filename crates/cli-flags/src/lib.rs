@@ -159,11 +159,13 @@ wasmtime_option_group! {
         pub max_wasm_stack: Option<usize>,
         /// Allow unknown exports when running commands.
         pub unknown_exports_allow: Option<bool>,
-        /// Allow the main module to import unknown functions, using an
-        /// implementation that immediately traps, when running commands.
+        /// Allow the main module or component to import unknown functions,
+        /// using an implementation that immediately traps, when running
+        /// commands.
         pub unknown_imports_trap: Option<bool>,
-        /// Allow the main module to import unknown functions, using an
-        /// implementation that returns default values, when running commands.
+        /// Allow the main module or component to import unknown functions,
+        /// using an implementation that returns default values, when running
+        /// commands.
         pub unknown_imports_default: Option<bool>,
         /// Enables memory error checking. (see wmemcheck.md for more info)
         pub wmemcheck: Option<bool>,
@@ -242,6 +244,18 @@ wasmtime_option_group! {
         pub http: Option<bool>,
         /// Enable support for WASI Cloud Core (experimental)
         pub cloud_core: Option<bool>,
+        /// Path to a TOML or JSON file selecting the WASI Cloud Core capability
+        /// providers and their settings
+        pub cloud_core_config: Option<String>,
+        /// Refuse WASI Cloud Core components without valid, unexpired signed
+        /// capability claims, requires `cloud-core-trusted-issuer`
+        pub cloud_core_strict_claims: Option<bool>,
+        /// Public key of an account trusted to issue WASI Cloud Core
+        /// capability claims, may be specified multiple times
+        pub cloud_core_trusted_issuer: Vec<String>,
+        /// Socket address to serve WASI Cloud Core capability call metrics on
+        /// in the Prometheus text format at `/metrics`
+        pub cloud_core_metrics_addr: Option<String>,
         /// Inherit environment variables and file descriptors following the
         /// systemd listen fd specification (UNIX only)
         pub listenfd: Option<bool>,
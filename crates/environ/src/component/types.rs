@@ -518,18 +518,26 @@ impl ComponentTypesBuilder {
         id: types::ComponentFuncTypeId,
     ) -> Result<TypeFuncIndex> {
         let ty = &types[id];
+        let param_names = ty.params.iter().map(|(name, _)| name.to_string()).collect();
         let params = ty
             .params
             .iter()
             .map(|(_name, ty)| self.valtype(types, ty))
             .collect::<Result<_>>()?;
+        let result_names = ty
+            .results
+            .iter()
+            .filter_map(|(name, _)| name.as_ref().map(|name| name.to_string()))
+            .collect();
         let results = ty
             .results
             .iter()
             .map(|(_name, ty)| self.valtype(types, ty))
             .collect::<Result<_>>()?;
         let ty = TypeFunc {
+            param_names,
             params: self.new_tuple_type(params),
+            result_names,
             results: self.new_tuple_type(results),
         };
         Ok(self.add_func_type(ty))
@@ -1065,8 +1073,12 @@ pub struct TypeComponentInstance {
 /// A component function type in the component model.
 #[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Debug)]
 pub struct TypeFunc {
+    /// Names of the parameters.
+    pub param_names: Vec<String>,
     /// Parameters to the function represented as a tuple.
     pub params: TypeTupleIndex,
+    /// Names of the results, which is empty if the results are unnamed.
+    pub result_names: Vec<String>,
     /// Results of the function represented as a tuple.
     pub results: TypeTupleIndex,
 }
@@ -127,6 +127,33 @@ impl MemoryImage {
         Ok(None)
     }
 
+    /// Creates an image of a linear memory whose current contents are `data`,
+    /// for use with [`Memory::restore_image`](crate::Memory::restore_image).
+    ///
+    /// Leading and trailing pages of zeros are left out of the image. Returns
+    /// `None` if an image can't be created from in-memory data on this
+    /// platform.
+    pub fn from_contents(data: &[u8]) -> Result<Option<MemoryImage>> {
+        let page_size = crate::page_size();
+        if data.len() % page_size != 0 {
+            return Ok(None);
+        }
+        let is_zero = |page: &[u8]| page.iter().all(|b| *b == 0);
+        let start = data.chunks(page_size).take_while(|p| is_zero(p)).count() * page_size;
+        let end = data.len()
+            - data[start..]
+                .rchunks(page_size)
+                .take_while(|p| is_zero(p))
+                .count()
+                * page_size;
+        MemoryImage::new(
+            u32::try_from(page_size).unwrap(),
+            u64::try_from(start).unwrap(),
+            &data[start..end],
+            None,
+        )
+    }
+
     unsafe fn map_at(&self, base: *mut u8) -> Result<()> {
         self.source.map_at(
             base.add(self.linear_memory_offset),
@@ -447,14 +474,47 @@ impl MemoryImageSlot {
 
     pub(crate) fn remove_image(&mut self) -> Result<()> {
         if let Some(image) = &self.image {
-            unsafe {
-                image.remap_as_zeros_at(self.base.as_ptr())?;
+            if image.len > 0 {
+                unsafe {
+                    image.remap_as_zeros_at(self.base.as_ptr())?;
+                }
             }
             self.image = None;
         }
         Ok(())
     }
 
+    /// Replaces the contents of all accessible memory in this slot with
+    /// `image`, leaving zeros everywhere else.
+    ///
+    /// Unlike `instantiate` this may be called regardless of whether the slot
+    /// is dirty, since all memory is reset first. The slot is dirty
+    /// afterwards.
+    pub(crate) fn replace_image(&mut self, image: &Arc<MemoryImage>) -> Result<()> {
+        assert!(image.linear_memory_offset + image.len <= self.accessible);
+
+        // Resetting memory may, on some platforms, also make the whole slot
+        // inaccessible, so record how much should be accessible afterwards.
+        let accessible = self.accessible;
+        unsafe {
+            self.reset_all_memory_contents(0)?;
+        }
+        self.remove_image()?;
+        if self.accessible < accessible {
+            self.set_protection(self.accessible..accessible, true)?;
+            self.accessible = accessible;
+        }
+
+        if image.len > 0 {
+            unsafe {
+                image.map_at(self.base.as_ptr())?;
+            }
+        }
+        self.image = Some(image.clone());
+        self.dirty = true;
+        Ok(())
+    }
+
     /// Resets this linear memory slot back to a "pristine state".
     ///
     /// This will reset the memory back to its original contents on Linux or
@@ -152,6 +152,16 @@ pub trait RuntimeLinearMemory: Send + Sync {
     /// `RuntimeMemoryCreator::new_memory()`.
     fn needs_init(&self) -> bool;
 
+    /// Replaces the entire contents of this memory with `image` by mapping it
+    /// copy-on-write.
+    ///
+    /// Returns `false` if this memory doesn't support images, in which case
+    /// its contents are left unchanged.
+    fn restore_image(&mut self, image: &Arc<MemoryImage>) -> Result<bool> {
+        let _ = image;
+        Ok(false)
+    }
+
     /// Used for optional dynamic downcasting.
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 
@@ -350,6 +360,25 @@ impl RuntimeLinearMemory for MmapMemory {
         self.memory_image.is_none()
     }
 
+    fn restore_image(&mut self, image: &Arc<MemoryImage>) -> Result<bool> {
+        // Memories created without an image don't have a slot yet, so create
+        // one over the current mapping. The slot resets all of its accessible
+        // memory before mapping the image, so it's fine that the contents
+        // here aren't zero.
+        let slot = match &mut self.memory_image {
+            Some(slot) => slot,
+            None => {
+                let base = unsafe { self.mmap.as_mut_ptr().add(self.pre_guard_size) };
+                let static_size = self.mmap.len() - self.pre_guard_size - self.offset_guard_size;
+                let mut slot = MemoryImageSlot::create(base.cast(), self.accessible, static_size);
+                slot.no_clear_on_drop();
+                self.memory_image.insert(slot)
+            }
+        };
+        slot.replace_image(image)?;
+        Ok(true)
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -449,6 +478,11 @@ impl RuntimeLinearMemory for StaticMemory {
         !self.memory_image.has_image()
     }
 
+    fn restore_image(&mut self, image: &Arc<MemoryImage>) -> Result<bool> {
+        self.memory_image.replace_image(image)?;
+        Ok(true)
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -877,6 +911,15 @@ impl Memory {
         self.0.vmmemory()
     }
 
+    /// Replaces the entire contents of this memory with `image`, which must not
+    /// extend past the current size of this memory.
+    ///
+    /// Returns `false`, leaving the memory unchanged, if this memory doesn't
+    /// support copy-on-write images.
+    pub fn restore_image(&mut self, image: &Arc<MemoryImage>) -> Result<bool> {
+        self.0.restore_image(image)
+    }
+
     /// Consume the memory, returning its [`MemoryImageSlot`] if any is present.
     /// The image should only be present for a subset of memories created with
     /// [`Memory::new_static()`].
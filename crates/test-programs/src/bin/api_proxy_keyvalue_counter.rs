@@ -0,0 +1,51 @@
+pub mod bindings {
+    use super::T;
+
+    wit_bindgen::generate!({
+        inline: "
+            package wasmtime:test;
+
+            world keyvalue-counter {
+                import wasi:keyvalue/atomic@0.1.0;
+                export wasi:http/incoming-handler@0.2.0;
+            }
+        ",
+        path: "../wasi-cloud-core/wit",
+        world: "wasmtime:test/keyvalue-counter",
+        exports: {
+            "wasi:http/incoming-handler": T,
+        },
+    });
+}
+
+use bindings::wasi::http::types::{
+    Fields, IncomingRequest, OutgoingBody, OutgoingResponse, ResponseOutparam,
+};
+use bindings::wasi::keyvalue::{atomic, types::Bucket};
+
+struct T;
+
+impl bindings::exports::wasi::http::incoming_handler::Guest for T {
+    fn handle(_request: IncomingRequest, outparam: ResponseOutparam) {
+        let bucket = Bucket::open_bucket("counters").expect("failed to open bucket");
+        let n = atomic::increment(&bucket, &"requests".to_string(), 1)
+            .expect("failed to increment counter");
+
+        let hdrs = Fields::new();
+        let resp = OutgoingResponse::new(hdrs);
+        let body = resp.body().expect("outgoing response");
+
+        ResponseOutparam::set(outparam, Ok(resp));
+
+        let out = body.write().expect("outgoing stream");
+        out.blocking_write_and_flush(n.to_string().as_bytes())
+            .expect("writing response");
+
+        drop(out);
+        OutgoingBody::finish(body, None).expect("outgoing-body.finish");
+    }
+}
+
+// Technically this should not be here for a proxy, but given the current
+// framework for tests it's required since this file is built as a `bin`
+fn main() {}
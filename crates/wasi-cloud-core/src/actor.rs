@@ -0,0 +1,351 @@
+//! Component-to-component calls through custom interfaces
+//!
+//! An [Actor] is a component exporting [`TargetInterface::Custom`]
+//! interfaces. [Actors] resolves custom interface imports of a calling
+//! component against the exports of registered actors and dispatches each
+//! call dynamically to a fresh instance of the callee in its own
+//! [Store](wasmtime::Store) with its own [StoreLimits](wasmtime::StoreLimits),
+//! so multi-actor applications can be built without static composition.
+//!
+//! Values are passed between the stores by value, hence functions taking or
+//! returning resources cannot be called across actors.
+
+use crate::capability::{ActorIdentifier, TargetInterface};
+use crate::component::Host;
+use crate::trigger::NewStore;
+
+use core::fmt::Debug;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context, Result};
+use tracing::{instrument, trace};
+use wasmtime::component::types::{ComponentFunc, ComponentItem};
+use wasmtime::component::{Component, InstancePre, Linker, Val};
+use wasmtime::Engine;
+
+/// Identifier [Actors] are keyed by
+fn actor_key(id: &ActorIdentifier) -> String {
+    match id {
+        ActorIdentifier::Alias(alias) => alias.clone(),
+        ActorIdentifier::Key(key) => key.public_key(),
+    }
+}
+
+/// Custom interface exported by an [Actor]
+#[derive(Clone, Debug)]
+struct Export {
+    /// Interface implemented by the exported instance
+    interface: TargetInterface,
+    /// Types of the functions exported by the instance
+    funcs: HashMap<String, ComponentFunc>,
+}
+
+/// Component, which other components can call through the custom interfaces
+/// it exports
+#[derive(Clone)]
+pub struct Actor {
+    engine: Engine,
+    pre: InstancePre<Host>,
+    new_store: NewStore,
+    /// Exported custom interfaces keyed by the full instance name, including
+    /// the version, if any
+    exports: BTreeMap<String, Export>,
+}
+
+impl Debug for Actor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Actor")
+            .field("exports", &self.exports)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Actor {
+    /// Construct a new [Actor] from `component` with imports satisfied by
+    /// `linker`, which is instantiated in a store returned by `new_store` for
+    /// each call
+    pub fn new(
+        engine: &Engine,
+        linker: &Linker<Host>,
+        component: &Component,
+        new_store: NewStore,
+    ) -> Result<Self> {
+        let ty = linker
+            .substituted_component_type(component)
+            .context("failed to type component")?;
+        let mut exports = BTreeMap::new();
+        for (name, item) in ty.exports(engine) {
+            let ComponentItem::ComponentInstance(instance) = item else {
+                continue;
+            };
+            let Ok(interface @ TargetInterface::Custom { .. }) = name.parse() else {
+                continue;
+            };
+            let funcs = instance
+                .exports(engine)
+                .filter_map(|(name, item)| match item {
+                    ComponentItem::ComponentFunc(ty) => Some((name.into(), ty)),
+                    _ => None,
+                })
+                .collect();
+            exports.insert(name.into(), Export { interface, funcs });
+        }
+        let pre = linker
+            .instantiate_pre(component)
+            .context("failed to pre-instantiate component")?;
+        Ok(Self {
+            engine: engine.clone(),
+            pre,
+            new_store,
+            exports,
+        })
+    }
+
+    /// Custom interfaces exported by the actor
+    pub fn interfaces(&self) -> impl Iterator<Item = &TargetInterface> {
+        self.exports.values().map(|export| &export.interface)
+    }
+
+    /// Call function `name` of the custom interface `instance`, for example,
+    /// `acme:math/ops@0.1.0`, exported by the actor in a fresh instance
+    #[instrument(level = "trace", skip(self, params, results))]
+    pub async fn call(
+        &self,
+        instance: &str,
+        name: &str,
+        params: &[Val],
+        results: &mut [Val],
+    ) -> Result<()> {
+        ensure!(
+            self.exports.contains_key(instance),
+            "actor does not export `{instance}`"
+        );
+        let mut store = (self.new_store)(&self.engine).context("failed to construct store")?;
+        store.limiter(|host| &mut host.limits);
+        let callee = self
+            .pre
+            .instantiate_async(&mut store)
+            .await
+            .context("failed to instantiate component")?;
+        let func = {
+            let mut exports = callee.exports(&mut store);
+            exports
+                .instance(instance)
+                .and_then(|mut instance| instance.func(name))
+        }
+        .with_context(|| format!("actor does not export `{instance}#{name}`"))?;
+        func.call_async(&mut store, params, results)
+            .await
+            .with_context(|| format!("failed to call `{instance}#{name}`"))?;
+        func.post_return_async(&mut store)
+            .await
+            .with_context(|| format!("failed to call post-return of `{instance}#{name}`"))
+    }
+}
+
+/// Set of [Actor]s, which components can call
+#[derive(Clone, Debug, Default)]
+pub struct Actors {
+    actors: HashMap<String, Arc<Actor>>,
+    targets: HashMap<TargetInterface, String>,
+}
+
+impl Actors {
+    /// Register `actor` as `id`, returning the actor previously registered
+    /// as `id`, if any
+    pub fn insert(&mut self, id: &ActorIdentifier, actor: Actor) -> Option<Arc<Actor>> {
+        self.actors.insert(actor_key(id), Arc::new(actor))
+    }
+
+    /// Route calls to `interface` to the actor registered as `id`
+    ///
+    /// This is only required if multiple registered actors export `interface`
+    pub fn set_target(&mut self, interface: TargetInterface, id: &ActorIdentifier) {
+        self.targets.insert(interface, actor_key(id));
+    }
+
+    /// Actor handling calls to the custom interface `instance`, for example,
+    /// `acme:math/ops@0.1.0`, if any
+    ///
+    /// Only actors exporting an instance of the same name, including the
+    /// version, handle the calls.
+    pub fn resolve(&self, instance: &str) -> Result<Option<&Arc<Actor>>> {
+        let interface: TargetInterface = instance.parse()?;
+        if let Some(id) = self.targets.get(&interface) {
+            let actor = self
+                .actors
+                .get(id)
+                .with_context(|| format!("target actor `{id}` of `{interface}` not found"))?;
+            if !actor.exports.contains_key(instance) {
+                bail!("target actor `{id}` does not export `{instance}`")
+            }
+            return Ok(Some(actor));
+        }
+        let mut exporters = self
+            .actors
+            .iter()
+            .filter(|(_, actor)| actor.exports.contains_key(instance));
+        match (exporters.next(), exporters.next()) {
+            (None, _) => Ok(None),
+            (Some((_, actor)), None) => Ok(Some(actor)),
+            (Some(_), Some(_)) => {
+                bail!("multiple actors export `{instance}`, a target actor must be set")
+            }
+        }
+    }
+
+    /// Define the custom interfaces imported by `component` in `linker`,
+    /// dispatching calls to the actors exporting them
+    ///
+    /// Custom interfaces not exported by any actor are left undefined. It is
+    /// an error if the actor exporting an interface is missing an imported
+    /// function or if a function's type differs from the import's.
+    pub fn add_to_linker<T: Send + 'static>(
+        &self,
+        linker: &mut Linker<T>,
+        component: &Component,
+    ) -> Result<()> {
+        let engine = linker.engine().clone();
+        let ty = component.component_type();
+        for (name, item) in ty.imports(&engine) {
+            let ComponentItem::ComponentInstance(import) = item else {
+                continue;
+            };
+            let Ok(TargetInterface::Custom { .. }) = name.parse() else {
+                continue;
+            };
+            let Some(actor) = self.resolve(name)? else {
+                trace!(name, "no actor exports custom interface");
+                continue;
+            };
+            let export = &actor.exports[name];
+            let mut instance = linker
+                .instance(name)
+                .with_context(|| format!("failed to define `{name}`"))?;
+            for (func, item) in import.exports(&engine) {
+                let ComponentItem::ComponentFunc(import_ty) = item else {
+                    continue;
+                };
+                let export_ty = export
+                    .funcs
+                    .get(func)
+                    .with_context(|| format!("actor does not export `{name}#{func}`"))?;
+                ensure!(
+                    import_ty.params().eq(export_ty.params())
+                        && import_ty.results().eq(export_ty.results()),
+                    "type of `{name}#{func}` exported by actor does not match the import"
+                );
+                let actor = Arc::clone(actor);
+                let instance_name = name.to_string();
+                let func_name = func.to_string();
+                instance
+                    .func_new_async(component, func, move |_, params, results| {
+                        let actor = Arc::clone(&actor);
+                        let instance_name = instance_name.clone();
+                        let func_name = func_name.clone();
+                        Box::new(async move {
+                            actor
+                                .call(&instance_name, &func_name, params, results)
+                                .await
+                        })
+                    })
+                    .with_context(|| format!("failed to define `{name}#{func}`"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::Handler;
+
+    use wasmtime::Store;
+
+    const CALLEE: &str = r#"
+        (component
+            (core module $m
+                (func (export "add") (param i32 i32) (result i32)
+                    local.get 0
+                    local.get 1
+                    i32.add))
+            (core instance $i (instantiate $m))
+            (func $add (param "a" u32) (param "b" u32) (result u32)
+                (canon lift (core func $i "add")))
+            (instance $ops (export "add" (func $add)))
+            (export "acme:math/ops@0.1.0" (instance $ops)))
+    "#;
+
+    const CALLER: &str = r#"
+        (component
+            (import "acme:math/ops@0.1.0" (instance $ops
+                (export "add" (func (param "a" u32) (param "b" u32) (result u32)))))
+            (core func $add (canon lower (func $ops "add")))
+            (core module $m
+                (import "ops" "add" (func $add (param i32 i32) (result i32)))
+                (func (export "run") (result i32)
+                    i32.const 2
+                    i32.const 3
+                    call $add))
+            (core instance $i (instantiate $m
+                (with "ops" (instance (export "add" (func $add))))))
+            (func (export "run") (result u32)
+                (canon lift (core func $i "run"))))
+    "#;
+
+    #[tokio::test]
+    async fn call_actor() -> Result<()> {
+        let mut config = wasmtime::Config::new();
+        config.async_support(true).wasm_component_model(true);
+        let engine = Engine::new(&config)?;
+        let new_store: NewStore =
+            Arc::new(|engine| Ok(Store::new(engine, Host::new(Handler::default()))));
+
+        let callee = Component::new(&engine, wat::parse_str(CALLEE)?)?;
+        let actor = Actor::new(&engine, &Linker::new(&engine), &callee, new_store)?;
+        let interface: TargetInterface = "acme:math/ops".parse()?;
+        assert_eq!(actor.interfaces().collect::<Vec<_>>(), [&interface]);
+
+        let mut actors = Actors::default();
+        actors.insert(&"math".into(), actor.clone());
+
+        // Imports are resolved from the component type, so precompiled
+        // components are supported
+        let caller = Component::new(&engine, wat::parse_str(CALLER)?)?;
+        let caller = unsafe { Component::deserialize(&engine, caller.serialize()?)? };
+        let mut linker = Linker::new(&engine);
+        actors.add_to_linker(&mut linker, &caller)?;
+        let mut store = Store::new(&engine, Host::new(Handler::default()));
+        let instance = linker.instantiate_async(&mut store, &caller).await?;
+        let run = instance.get_typed_func::<(), (u32,)>(&mut store, "run")?;
+        assert_eq!(run.call_async(&mut store, ()).await?, (5,));
+
+        // Other versions of the interface are left undefined
+        let other = Component::new(&engine, wat::parse_str(CALLER.replace("@0.1.0", "@0.2.0"))?)?;
+        let mut linker = Linker::<Host>::new(&engine);
+        actors.add_to_linker(&mut linker, &other)?;
+        assert!(linker.instantiate_pre(&other).is_err());
+
+        // Functions must have the same type
+        let other = Component::new(
+            &engine,
+            wat::parse_str(CALLER.replace("(result u32)))))", "(result s32)))))"))?,
+        )?;
+        assert!(actors
+            .add_to_linker(&mut Linker::<Host>::new(&engine), &other)
+            .is_err());
+
+        // Ambiguous exports require an explicit target
+        actors.insert(&"other".into(), actor);
+        assert!(actors
+            .add_to_linker(&mut Linker::<Host>::new(&engine), &caller)
+            .is_err());
+        actors.set_target(interface, &"math".into());
+        actors.add_to_linker(&mut Linker::<Host>::new(&engine), &caller)?;
+        Ok(())
+    }
+}
@@ -1,39 +1,65 @@
-use super::{blobstore, format_opt, messaging};
+use super::metrics::{CallLabels, Direction, MeteredReader, Metrics};
+use super::{blobstore, format_opt, logging, messaging};
 
 use core::convert::Infallible;
-use core::fmt::Debug;
+use core::fmt::{self, Debug, Display};
+use core::future::Future;
 use core::str::FromStr;
 use core::time::Duration;
 
+use std::collections::HashMap;
 use std::ops::RangeInclusive;
 use std::sync::Arc;
+use std::time::Instant;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use futures::{Stream, TryStreamExt};
 use nkeys::{KeyPair, KeyPairType};
 use tokio::io::AsyncRead;
 use tracing::{instrument, trace};
 
+/// Name of the link used for calls to an interface, unless another one is selected
+pub const DEFAULT_LINK_NAME: &str = "default";
+
+/// Providers of a single interface keyed by link name
+type Links<T> = HashMap<String, Arc<T>>;
+
+/// Capability handler dispatching guest calls to the configured providers
+///
+/// Each interface can have multiple providers, which are registered under
+/// distinct link names. Calls are routed to the provider linked under the
+/// name currently selected for the interface, see [`Handler::set_link_name`].
+///
+/// If a [Metrics] sink is set, every call is recorded to it, see
+/// [`Handler::replace_metrics`].
 #[derive(Clone, Default)]
 pub struct Handler {
-    blobstore: Option<Arc<dyn Blobstore + Sync + Send>>,
+    blobstore: Links<dyn Blobstore + Sync + Send>,
     incoming_http: Option<Arc<dyn IncomingHttp + Sync + Send>>,
-    outgoing_http: Option<Arc<dyn OutgoingHttp + Sync + Send>>,
-    keyvalue_atomic: Option<Arc<dyn KeyValueAtomic + Sync + Send>>,
-    keyvalue_eventual: Option<Arc<dyn KeyValueEventual + Sync + Send>>,
-    messaging: Option<Arc<dyn Messaging + Sync + Send>>,
+    outgoing_http: Links<dyn OutgoingHttp + Sync + Send>,
+    keyvalue_atomic: Links<dyn KeyValueAtomic + Sync + Send>,
+    keyvalue_eventual: Links<dyn KeyValueEventual + Sync + Send>,
+    logging: Links<dyn Logging + Sync + Send>,
+    messaging: Links<dyn Messaging + Sync + Send>,
+    link_names: HashMap<TargetInterface, String>,
+    metrics: Option<Arc<dyn Metrics + Sync + Send>>,
+    component_id: Option<String>,
 }
 
 impl Debug for Handler {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Handler")
-            .field("blobstore", &format_opt(&self.blobstore))
+            .field("blobstore", &self.blobstore.keys())
             .field("incoming_http", &format_opt(&self.incoming_http))
-            .field("keyvalue_atomic", &format_opt(&self.keyvalue_atomic))
-            .field("keyvalue_eventual", &format_opt(&self.keyvalue_eventual))
-            .field("messaging", &format_opt(&self.messaging))
-            .field("outgoing_http", &format_opt(&self.outgoing_http))
+            .field("keyvalue_atomic", &self.keyvalue_atomic.keys())
+            .field("keyvalue_eventual", &self.keyvalue_eventual.keys())
+            .field("logging", &self.logging.keys())
+            .field("messaging", &self.messaging.keys())
+            .field("outgoing_http", &self.outgoing_http.keys())
+            .field("link_names", &self.link_names)
+            .field("metrics", &format_opt(&self.metrics))
+            .field("component_id", &self.component_id)
             .finish()
     }
 }
@@ -49,35 +75,151 @@ fn proxy<'a, T: ?Sized>(
         .with_context(|| format!("cannot handle `{method}`"))
 }
 
+fn proxy_link<'a, T: ?Sized>(
+    links: &'a Links<T>,
+    link_name: &str,
+    interface: &str,
+    method: &str,
+) -> anyhow::Result<&'a Arc<T>> {
+    trace!(link_name, "call `{interface}` handler");
+    links
+        .get(link_name)
+        .with_context(|| format!("cannot handle `{method}` on link `{link_name}`"))
+}
+
 impl Handler {
+    /// Returns the link name selected for calls to `interface`
+    pub fn link_name(&self, interface: &TargetInterface) -> &str {
+        self.link_names
+            .get(interface)
+            .map_or(DEFAULT_LINK_NAME, String::as_str)
+    }
+
+    /// Select the link name used for calls to `interfaces`
+    pub fn set_link_name(
+        &mut self,
+        name: impl Into<String>,
+        interfaces: impl IntoIterator<Item = TargetInterface>,
+    ) {
+        let name = name.into();
+        for interface in interfaces {
+            self.link_names.insert(interface, name.clone());
+        }
+    }
+
+    /// Set the identifier of the component calling this handler, which is
+    /// used to label recorded [Metrics] and logged messages
+    pub fn set_component_id(&mut self, component_id: impl Into<String>) {
+        self.component_id = Some(component_id.into());
+    }
+
+    /// Identifier of the component calling this handler, if set
+    pub fn component_id(&self) -> Option<&str> {
+        self.component_id.as_deref()
+    }
+
+    fn call_labels(&self, interface: TargetInterface, method: &str) -> CallLabels {
+        CallLabels {
+            component: self.component_id.clone(),
+            link_name: self.link_name(&interface).into(),
+            interface,
+            method: method.into(),
+        }
+    }
+
+    /// Record the duration and outcome of the call returned by `f` to
+    /// [Metrics], if set
+    async fn observe<T, F: Future<Output = anyhow::Result<T>>>(
+        &self,
+        interface: TargetInterface,
+        method: &'static str,
+        f: impl FnOnce(&'static str) -> F,
+    ) -> anyhow::Result<T> {
+        let Some(metrics) = &self.metrics else {
+            return f(method).await;
+        };
+        let labels = self.call_labels(interface, method);
+        let start = Instant::now();
+        let res = f(method).await;
+        metrics.record_call(&labels, start.elapsed(), res.is_ok());
+        res
+    }
+
+    async fn observe_blobstore<T, F: Future<Output = anyhow::Result<T>>>(
+        &self,
+        method: &'static str,
+        f: impl FnOnce(&'static str) -> F,
+    ) -> anyhow::Result<T> {
+        self.observe(TargetInterface::WasiBlobstoreBlobstore, method, f)
+            .await
+    }
+
+    async fn observe_messaging<T, F: Future<Output = anyhow::Result<T>>>(
+        &self,
+        method: &'static str,
+        f: impl FnOnce(&'static str) -> F,
+    ) -> anyhow::Result<T> {
+        self.observe(TargetInterface::WasmcloudMessagingConsumer, method, f)
+            .await
+    }
+
+    /// Record the bytes of `value` transferred in `direction` to [Metrics],
+    /// if set
+    fn meter(
+        &self,
+        interface: TargetInterface,
+        method: &str,
+        direction: Direction,
+        value: Box<dyn AsyncRead + Sync + Send + Unpin>,
+    ) -> Box<dyn AsyncRead + Sync + Send + Unpin> {
+        let Some(metrics) = &self.metrics else {
+            return value;
+        };
+        Box::new(MeteredReader {
+            inner: value,
+            metrics: Arc::clone(metrics),
+            labels: self.call_labels(interface, method),
+            direction,
+        })
+    }
+
     fn proxy_blobstore(&self, method: &str) -> anyhow::Result<&Arc<dyn Blobstore + Sync + Send>> {
-        proxy(&self.blobstore, "Blobstore", method)
+        let link_name = self.link_name(&TargetInterface::WasiBlobstoreBlobstore);
+        proxy_link(&self.blobstore, link_name, "Blobstore", method)
     }
 
     fn proxy_keyvalue_atomic(
         &self,
         method: &str,
     ) -> anyhow::Result<&Arc<dyn KeyValueAtomic + Sync + Send>> {
-        proxy(&self.keyvalue_atomic, "KeyvalueAtomic", method)
+        let link_name = self.link_name(&TargetInterface::WasiKeyvalueAtomic);
+        proxy_link(&self.keyvalue_atomic, link_name, "KeyvalueAtomic", method)
     }
 
     fn proxy_keyvalue_eventual(
         &self,
         method: &str,
     ) -> anyhow::Result<&Arc<dyn KeyValueEventual + Sync + Send>> {
-        proxy(&self.keyvalue_eventual, "KeyvalueEventual", method)
+        let link_name = self.link_name(&TargetInterface::WasiKeyvalueEventual);
+        proxy_link(
+            &self.keyvalue_eventual,
+            link_name,
+            "KeyvalueEventual",
+            method,
+        )
     }
 
     fn proxy_messaging(&self, method: &str) -> anyhow::Result<&Arc<dyn Messaging + Sync + Send>> {
-        proxy(&self.messaging, "Messaging", method)
+        let link_name = self.link_name(&TargetInterface::WasmcloudMessagingConsumer);
+        proxy_link(&self.messaging, link_name, "Messaging", method)
     }
 
-    /// Replace [`Blobstore`] handler returning the old one, if such was set
-    pub fn replace_blobstore(
+    /// Replace [`Metrics`] sink returning the old one, if such was set
+    pub fn replace_metrics(
         &mut self,
-        blobstore: Arc<dyn Blobstore + Send + Sync>,
-    ) -> Option<Arc<dyn Blobstore + Send + Sync>> {
-        self.blobstore.replace(blobstore)
+        metrics: Arc<dyn Metrics + Send + Sync>,
+    ) -> Option<Arc<dyn Metrics + Send + Sync>> {
+        self.metrics.replace(metrics)
     }
 
     /// Replace [`IncomingHttp`] handler returning the old one, if such was set
@@ -88,36 +230,107 @@ impl Handler {
         self.incoming_http.replace(incoming_http)
     }
 
-    /// Replace [`KeyValueAtomic`] handler returning the old one, if such was set
+    /// Replace [`Blobstore`] handler of the default link returning the old one, if such was set
+    pub fn replace_blobstore(
+        &mut self,
+        blobstore: Arc<dyn Blobstore + Send + Sync>,
+    ) -> Option<Arc<dyn Blobstore + Send + Sync>> {
+        self.replace_blobstore_link(DEFAULT_LINK_NAME, blobstore)
+    }
+
+    /// Replace [`Blobstore`] handler of link `name` returning the old one, if such was set
+    pub fn replace_blobstore_link(
+        &mut self,
+        name: impl Into<String>,
+        blobstore: Arc<dyn Blobstore + Send + Sync>,
+    ) -> Option<Arc<dyn Blobstore + Send + Sync>> {
+        self.blobstore.insert(name.into(), blobstore)
+    }
+
+    /// Replace [`KeyValueAtomic`] handler of the default link returning the old one, if such was set
     pub fn replace_keyvalue_atomic(
         &mut self,
         keyvalue_atomic: Arc<dyn KeyValueAtomic + Send + Sync>,
     ) -> Option<Arc<dyn KeyValueAtomic + Send + Sync>> {
-        self.keyvalue_atomic.replace(keyvalue_atomic)
+        self.replace_keyvalue_atomic_link(DEFAULT_LINK_NAME, keyvalue_atomic)
+    }
+
+    /// Replace [`KeyValueAtomic`] handler of link `name` returning the old one, if such was set
+    pub fn replace_keyvalue_atomic_link(
+        &mut self,
+        name: impl Into<String>,
+        keyvalue_atomic: Arc<dyn KeyValueAtomic + Send + Sync>,
+    ) -> Option<Arc<dyn KeyValueAtomic + Send + Sync>> {
+        self.keyvalue_atomic.insert(name.into(), keyvalue_atomic)
     }
 
-    /// Replace [`KeyValueEventual`] handler returning the old one, if such was set
+    /// Replace [`KeyValueEventual`] handler of the default link returning the old one, if such was set
     pub fn replace_keyvalue_eventual(
         &mut self,
         keyvalue_eventual: Arc<dyn KeyValueEventual + Send + Sync>,
     ) -> Option<Arc<dyn KeyValueEventual + Send + Sync>> {
-        self.keyvalue_eventual.replace(keyvalue_eventual)
+        self.replace_keyvalue_eventual_link(DEFAULT_LINK_NAME, keyvalue_eventual)
+    }
+
+    /// Replace [`KeyValueEventual`] handler of link `name` returning the old one, if such was set
+    pub fn replace_keyvalue_eventual_link(
+        &mut self,
+        name: impl Into<String>,
+        keyvalue_eventual: Arc<dyn KeyValueEventual + Send + Sync>,
+    ) -> Option<Arc<dyn KeyValueEventual + Send + Sync>> {
+        self.keyvalue_eventual
+            .insert(name.into(), keyvalue_eventual)
+    }
+
+    /// Replace [`Logging`] handler of the default link returning the old one, if such was set
+    pub fn replace_logging(
+        &mut self,
+        logging: Arc<dyn Logging + Send + Sync>,
+    ) -> Option<Arc<dyn Logging + Send + Sync>> {
+        self.replace_logging_link(DEFAULT_LINK_NAME, logging)
+    }
+
+    /// Replace [`Logging`] handler of link `name` returning the old one, if such was set
+    pub fn replace_logging_link(
+        &mut self,
+        name: impl Into<String>,
+        logging: Arc<dyn Logging + Send + Sync>,
+    ) -> Option<Arc<dyn Logging + Send + Sync>> {
+        self.logging.insert(name.into(), logging)
     }
 
-    /// Replace [`Messaging`] handler returning the old one, if such was set
+    /// Replace [`Messaging`] handler of the default link returning the old one, if such was set
     pub fn replace_messaging(
         &mut self,
         messaging: Arc<dyn Messaging + Send + Sync>,
     ) -> Option<Arc<dyn Messaging + Send + Sync>> {
-        self.messaging.replace(messaging)
+        self.replace_messaging_link(DEFAULT_LINK_NAME, messaging)
     }
 
-    /// Replace [`OutgoingHttp`] handler returning the old one, if such was set
+    /// Replace [`Messaging`] handler of link `name` returning the old one, if such was set
+    pub fn replace_messaging_link(
+        &mut self,
+        name: impl Into<String>,
+        messaging: Arc<dyn Messaging + Send + Sync>,
+    ) -> Option<Arc<dyn Messaging + Send + Sync>> {
+        self.messaging.insert(name.into(), messaging)
+    }
+
+    /// Replace [`OutgoingHttp`] handler of the default link returning the old one, if such was set
     pub fn replace_outgoing_http(
         &mut self,
         outgoing_http: Arc<dyn OutgoingHttp + Send + Sync>,
     ) -> Option<Arc<dyn OutgoingHttp + Send + Sync>> {
-        self.outgoing_http.replace(outgoing_http)
+        self.replace_outgoing_http_link(DEFAULT_LINK_NAME, outgoing_http)
+    }
+
+    /// Replace [`OutgoingHttp`] handler of link `name` returning the old one, if such was set
+    pub fn replace_outgoing_http_link(
+        &mut self,
+        name: impl Into<String>,
+        outgoing_http: Arc<dyn OutgoingHttp + Send + Sync>,
+    ) -> Option<Arc<dyn OutgoingHttp + Send + Sync>> {
+        self.outgoing_http.insert(name.into(), outgoing_http)
     }
 }
 
@@ -170,7 +383,7 @@ pub enum TargetEntity {
     Actor(ActorIdentifier),
 }
 
-#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+#[derive(Debug, Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
 /// Call target identifier
 pub enum TargetInterface {
     /// `wasi:blobstore/blobstore`
@@ -196,6 +409,56 @@ pub enum TargetInterface {
     },
 }
 
+impl FromStr for TargetInterface {
+    type Err = anyhow::Error;
+
+    /// Parse an interface name of the form `namespace:package/interface`,
+    /// optionally followed by a `@version`, which is ignored
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.split_once('@').map_or(s, |(name, _)| name);
+        let (namespace, rest) = name
+            .split_once(':')
+            .with_context(|| format!("interface name `{s}` is missing a namespace"))?;
+        let (package, interface) = rest
+            .split_once('/')
+            .with_context(|| format!("interface name `{s}` is missing an interface"))?;
+        match (namespace, package, interface) {
+            ("wasi", "blobstore", "blobstore") => Ok(Self::WasiBlobstoreBlobstore),
+            ("wasi", "http", "outgoing-handler") => Ok(Self::WasiHttpOutgoingHandler),
+            ("wasi", "keyvalue", "atomic") => Ok(Self::WasiKeyvalueAtomic),
+            ("wasi", "keyvalue", "eventual") => Ok(Self::WasiKeyvalueEventual),
+            ("wasi", "logging", "logging") => Ok(Self::WasiLoggingLogging),
+            ("wasmcloud", "messaging", "consumer") => Ok(Self::WasmcloudMessagingConsumer),
+            _ if namespace.is_empty() || package.is_empty() || interface.is_empty() => {
+                bail!("invalid interface name `{s}`")
+            }
+            _ => Ok(Self::Custom {
+                namespace: namespace.into(),
+                package: package.into(),
+                interface: interface.into(),
+            }),
+        }
+    }
+}
+
+impl Display for TargetInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WasiBlobstoreBlobstore => write!(f, "wasi:blobstore/blobstore"),
+            Self::WasiHttpOutgoingHandler => write!(f, "wasi:http/outgoing-handler"),
+            Self::WasiKeyvalueAtomic => write!(f, "wasi:keyvalue/atomic"),
+            Self::WasiKeyvalueEventual => write!(f, "wasi:keyvalue/eventual"),
+            Self::WasiLoggingLogging => write!(f, "wasi:logging/logging"),
+            Self::WasmcloudMessagingConsumer => write!(f, "wasmcloud:messaging/consumer"),
+            Self::Custom {
+                namespace,
+                package,
+                interface,
+            } => write!(f, "{namespace}:{package}/{interface}"),
+        }
+    }
+}
+
 /// Outgoing HTTP request
 pub struct OutgoingHttpRequest {
     /// Whether to use TLS
@@ -278,6 +541,50 @@ pub trait Blobstore {
             .context("failed to collect object names")?;
         self.delete_objects(container, names).await
     }
+
+    /// Handle `wasi:blobstore/blobstore.copy-object`
+    ///
+    /// The default implementation streams the object data through
+    /// [`Blobstore::get_data`] and [`Blobstore::write_data`]
+    async fn copy_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        if src_container == dest_container && src_name == dest_name {
+            return Ok(());
+        }
+        let (data, _) = self
+            .get_data(src_container, src_name, 0..=u64::MAX)
+            .await
+            .context("failed to get source object data")?;
+        self.write_data(dest_container, dest_name, data)
+            .await
+            .context("failed to write destination object data")
+    }
+
+    /// Handle `wasi:blobstore/blobstore.move-object`
+    ///
+    /// The default implementation copies the object using
+    /// [`Blobstore::copy_object`] and deletes the source afterwards
+    async fn move_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        if src_container == dest_container && src_name == dest_name {
+            return Ok(());
+        }
+        self.copy_object(src_container, src_name.clone(), dest_container, dest_name)
+            .await?;
+        self.delete_objects(src_container, vec![src_name])
+            .await
+            .context("failed to delete source object")
+    }
 }
 
 #[async_trait]
@@ -329,6 +636,44 @@ pub trait KeyValueEventual {
 
     /// Handle `wasi:keyvalue/eventual.exists`
     async fn exists(&self, bucket: &str, key: String) -> anyhow::Result<bool>;
+
+    /// Handle `wasmcloud:host/keyvalue-ext.list-keys` returning a page of keys
+    /// in `bucket` following the key `cursor` and the cursor of the next page,
+    /// if any
+    async fn list_keys(
+        &self,
+        bucket: &str,
+        cursor: Option<String>,
+    ) -> anyhow::Result<(Vec<String>, Option<String>)> {
+        let _ = cursor;
+        bail!("cannot list keys in `{bucket}`, listing keys is not supported")
+    }
+
+    /// Handle `wasmcloud:host/keyvalue-ext.set-with-ttl`, the value expires
+    /// after `ttl`
+    async fn set_with_ttl(
+        &self,
+        bucket: &str,
+        key: String,
+        value: Box<dyn AsyncRead + Sync + Send + Unpin>,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        let _ = (value, ttl);
+        bail!("cannot set `{key}` in `{bucket}` with a TTL, expiry is not supported")
+    }
+}
+
+#[async_trait]
+/// `wasi:logging/logging` implementation, which receives guest log messages
+/// in addition to them being emitted as `tracing` events
+pub trait Logging {
+    /// Handle `wasi:logging/logging.log`
+    async fn log(
+        &self,
+        level: logging::logging::Level,
+        context: String,
+        message: String,
+    ) -> anyhow::Result<()>;
 }
 
 #[async_trait]
@@ -353,6 +698,16 @@ pub trait Messaging {
 
     /// Handle `wasmcloud:messaging/consumer.publish`
     async fn publish(&self, msg: messaging::types::BrokerMessage) -> anyhow::Result<()>;
+
+    /// Subscribe to messages published on subjects matching `subject`, which
+    /// are delivered to components exporting `wasmcloud:messaging/handler`
+    async fn subscribe(
+        &self,
+        subject: String,
+    ) -> anyhow::Result<Box<dyn Stream<Item = messaging::types::BrokerMessage> + Sync + Send + Unpin>>
+    {
+        bail!("cannot subscribe to `{subject}`, subscriptions are not supported")
+    }
 }
 
 #[async_trait]
@@ -369,23 +724,29 @@ pub trait OutgoingHttp {
 impl Blobstore for Handler {
     #[instrument]
     async fn create_container(&self, name: &str) -> anyhow::Result<()> {
-        self.proxy_blobstore("wasi:blobstore/blobstore.create-container")?
-            .create_container(name)
-            .await
+        self.observe_blobstore(
+            "wasi:blobstore/blobstore.create-container",
+            |method| async move { self.proxy_blobstore(method)?.create_container(name).await },
+        )
+        .await
     }
 
     #[instrument]
     async fn container_exists(&self, name: &str) -> anyhow::Result<bool> {
-        self.proxy_blobstore("wasi:blobstore/blobstore.container-exists")?
-            .container_exists(name)
-            .await
+        self.observe_blobstore(
+            "wasi:blobstore/blobstore.container-exists",
+            |method| async move { self.proxy_blobstore(method)?.container_exists(name).await },
+        )
+        .await
     }
 
     #[instrument]
     async fn delete_container(&self, name: &str) -> anyhow::Result<()> {
-        self.proxy_blobstore("wasi:blobstore/blobstore.delete-container")?
-            .delete_container(name)
-            .await
+        self.observe_blobstore(
+            "wasi:blobstore/blobstore.delete-container",
+            |method| async move { self.proxy_blobstore(method)?.delete_container(name).await },
+        )
+        .await
     }
 
     #[instrument]
@@ -393,9 +754,10 @@ impl Blobstore for Handler {
         &self,
         name: &str,
     ) -> anyhow::Result<blobstore::container::ContainerMetadata> {
-        self.proxy_blobstore("wasi:blobstore/container.info")?
-            .container_info(name)
-            .await
+        self.observe_blobstore("wasi:blobstore/container.info", |method| async move {
+            self.proxy_blobstore(method)?.container_info(name).await
+        })
+        .await
     }
 
     #[instrument]
@@ -405,16 +767,30 @@ impl Blobstore for Handler {
         name: String,
         range: RangeInclusive<u64>,
     ) -> anyhow::Result<(Box<dyn AsyncRead + Sync + Send + Unpin>, u64)> {
-        self.proxy_blobstore("wasi:blobstore/container.get-data")?
-            .get_data(container, name, range)
-            .await
+        self.observe_blobstore("wasi:blobstore/container.get-data", |method| async move {
+            let (value, size) = self
+                .proxy_blobstore(method)?
+                .get_data(container, name, range)
+                .await?;
+            let value = self.meter(
+                TargetInterface::WasiBlobstoreBlobstore,
+                method,
+                Direction::Incoming,
+                value,
+            );
+            Ok((value, size))
+        })
+        .await
     }
 
     #[instrument]
     async fn has_object(&self, container: &str, name: String) -> anyhow::Result<bool> {
-        self.proxy_blobstore("wasi:blobstore/container.has-object")?
-            .has_object(container, name)
-            .await
+        self.observe_blobstore("wasi:blobstore/container.has-object", |method| async move {
+            self.proxy_blobstore(method)?
+                .has_object(container, name)
+                .await
+        })
+        .await
     }
 
     #[instrument(skip(value))]
@@ -424,16 +800,31 @@ impl Blobstore for Handler {
         name: String,
         value: Box<dyn AsyncRead + Sync + Send + Unpin>,
     ) -> anyhow::Result<()> {
-        self.proxy_blobstore("wasi:blobstore/container.write-data")?
-            .write_data(container, name, value)
-            .await
+        self.observe_blobstore("wasi:blobstore/container.write-data", |method| async move {
+            let value = self.meter(
+                TargetInterface::WasiBlobstoreBlobstore,
+                method,
+                Direction::Outgoing,
+                value,
+            );
+            self.proxy_blobstore(method)?
+                .write_data(container, name, value)
+                .await
+        })
+        .await
     }
 
     #[instrument]
     async fn delete_objects(&self, container: &str, names: Vec<String>) -> anyhow::Result<()> {
-        self.proxy_blobstore("wasi:blobstore/container.delete-objects")?
-            .delete_objects(container, names)
-            .await
+        self.observe_blobstore(
+            "wasi:blobstore/container.delete-objects",
+            |method| async move {
+                self.proxy_blobstore(method)?
+                    .delete_objects(container, names)
+                    .await
+            },
+        )
+        .await
     }
 
     #[instrument]
@@ -441,9 +832,11 @@ impl Blobstore for Handler {
         &self,
         container: &str,
     ) -> anyhow::Result<Box<dyn Stream<Item = anyhow::Result<String>> + Sync + Send + Unpin>> {
-        self.proxy_blobstore("wasi:blobstore/container.list-objects")?
-            .list_objects(container)
-            .await
+        self.observe_blobstore(
+            "wasi:blobstore/container.list-objects",
+            |method| async move { self.proxy_blobstore(method)?.list_objects(container).await },
+        )
+        .await
     }
 
     #[instrument]
@@ -452,27 +845,83 @@ impl Blobstore for Handler {
         container: &str,
         name: String,
     ) -> anyhow::Result<blobstore::container::ObjectMetadata> {
-        self.proxy_blobstore("wasi:blobstore/container.object-info")?
-            .object_info(container, name)
-            .await
+        self.observe_blobstore(
+            "wasi:blobstore/container.object-info",
+            |method| async move {
+                self.proxy_blobstore(method)?
+                    .object_info(container, name)
+                    .await
+            },
+        )
+        .await
     }
 
     #[instrument]
     async fn clear_container(&self, container: &str) -> anyhow::Result<()> {
-        self.proxy_blobstore("wasi:blobstore/container.clear")?
-            .clear_container(container)
-            .await
+        self.observe_blobstore("wasi:blobstore/container.clear", |method| async move {
+            self.proxy_blobstore(method)?
+                .clear_container(container)
+                .await
+        })
+        .await
+    }
+
+    #[instrument]
+    async fn copy_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        self.observe_blobstore(
+            "wasi:blobstore/blobstore.copy-object",
+            |method| async move {
+                self.proxy_blobstore(method)?
+                    .copy_object(src_container, src_name, dest_container, dest_name)
+                    .await
+            },
+        )
+        .await
+    }
+
+    #[instrument]
+    async fn move_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        self.observe_blobstore(
+            "wasi:blobstore/blobstore.move-object",
+            |method| async move {
+                self.proxy_blobstore(method)?
+                    .move_object(src_container, src_name, dest_container, dest_name)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl KeyValueAtomic for Handler {
+    #[instrument]
     async fn increment(&self, bucket: &str, key: String, delta: u64) -> anyhow::Result<u64> {
-        self.proxy_keyvalue_atomic("wasi:keyvalue/atomic.increment")?
-            .increment(bucket, key, delta)
-            .await
+        self.observe(
+            TargetInterface::WasiKeyvalueAtomic,
+            "wasi:keyvalue/atomic.increment",
+            |method| async move {
+                self.proxy_keyvalue_atomic(method)?
+                    .increment(bucket, key, delta)
+                    .await
+            },
+        )
+        .await
     }
 
+    #[instrument]
     async fn compare_and_swap(
         &self,
         bucket: &str,
@@ -480,9 +929,16 @@ impl KeyValueAtomic for Handler {
         old: u64,
         new: u64,
     ) -> anyhow::Result<bool> {
-        self.proxy_keyvalue_atomic("wasi:keyvalue/atomic.compare-and-swap")?
-            .compare_and_swap(bucket, key, old, new)
-            .await
+        self.observe(
+            TargetInterface::WasiKeyvalueAtomic,
+            "wasi:keyvalue/atomic.compare-and-swap",
+            |method| async move {
+                self.proxy_keyvalue_atomic(method)?
+                    .compare_and_swap(bucket, key, old, new)
+                    .await
+            },
+        )
+        .await
     }
 }
 
@@ -494,9 +950,26 @@ impl KeyValueEventual for Handler {
         bucket: &str,
         key: String,
     ) -> anyhow::Result<Option<(Box<dyn AsyncRead + Sync + Send + Unpin>, u64)>> {
-        self.proxy_keyvalue_eventual("wasi:keyvalue/eventual.get")?
-            .get(bucket, key)
-            .await
+        self.observe(
+            TargetInterface::WasiKeyvalueEventual,
+            "wasi:keyvalue/eventual.get",
+            |method| async move {
+                let value = self
+                    .proxy_keyvalue_eventual(method)?
+                    .get(bucket, key)
+                    .await?;
+                Ok(value.map(|(value, size)| {
+                    let value = self.meter(
+                        TargetInterface::WasiKeyvalueEventual,
+                        method,
+                        Direction::Incoming,
+                        value,
+                    );
+                    (value, size)
+                }))
+            },
+        )
+        .await
     }
 
     #[instrument(skip(value))]
@@ -506,23 +979,94 @@ impl KeyValueEventual for Handler {
         key: String,
         value: Box<dyn AsyncRead + Sync + Send + Unpin>,
     ) -> anyhow::Result<()> {
-        self.proxy_keyvalue_eventual("wasi:keyvalue/eventual.set")?
-            .set(bucket, key, value)
-            .await
+        self.observe(
+            TargetInterface::WasiKeyvalueEventual,
+            "wasi:keyvalue/eventual.set",
+            |method| async move {
+                let value = self.meter(
+                    TargetInterface::WasiKeyvalueEventual,
+                    method,
+                    Direction::Outgoing,
+                    value,
+                );
+                self.proxy_keyvalue_eventual(method)?
+                    .set(bucket, key, value)
+                    .await
+            },
+        )
+        .await
     }
 
     #[instrument]
     async fn delete(&self, bucket: &str, key: String) -> anyhow::Result<()> {
-        self.proxy_keyvalue_eventual("wasi:keyvalue/eventual.delete")?
-            .delete(bucket, key)
-            .await
+        self.observe(
+            TargetInterface::WasiKeyvalueEventual,
+            "wasi:keyvalue/eventual.delete",
+            |method| async move {
+                self.proxy_keyvalue_eventual(method)?
+                    .delete(bucket, key)
+                    .await
+            },
+        )
+        .await
     }
 
     #[instrument]
     async fn exists(&self, bucket: &str, key: String) -> anyhow::Result<bool> {
-        self.proxy_keyvalue_eventual("wasi:keyvalue/eventual.exists")?
-            .exists(bucket, key)
-            .await
+        self.observe(
+            TargetInterface::WasiKeyvalueEventual,
+            "wasi:keyvalue/eventual.exists",
+            |method| async move {
+                self.proxy_keyvalue_eventual(method)?
+                    .exists(bucket, key)
+                    .await
+            },
+        )
+        .await
+    }
+
+    #[instrument]
+    async fn list_keys(
+        &self,
+        bucket: &str,
+        cursor: Option<String>,
+    ) -> anyhow::Result<(Vec<String>, Option<String>)> {
+        self.observe(
+            TargetInterface::WasiKeyvalueEventual,
+            "wasmcloud:host/keyvalue-ext.list-keys",
+            |method| async move {
+                self.proxy_keyvalue_eventual(method)?
+                    .list_keys(bucket, cursor)
+                    .await
+            },
+        )
+        .await
+    }
+
+    #[instrument(skip(value))]
+    async fn set_with_ttl(
+        &self,
+        bucket: &str,
+        key: String,
+        value: Box<dyn AsyncRead + Sync + Send + Unpin>,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        self.observe(
+            TargetInterface::WasiKeyvalueEventual,
+            "wasmcloud:host/keyvalue-ext.set-with-ttl",
+            |method| async move {
+                let value = self.meter(
+                    TargetInterface::WasiKeyvalueEventual,
+                    method,
+                    Direction::Outgoing,
+                    value,
+                );
+                self.proxy_keyvalue_eventual(method)?
+                    .set_with_ttl(bucket, key, value, ttl)
+                    .await
+            },
+        )
+        .await
     }
 }
 
@@ -543,6 +1087,32 @@ impl IncomingHttp for Handler {
     }
 }
 
+#[async_trait]
+impl Logging for Handler {
+    #[instrument(level = "trace", skip(message))]
+    async fn log(
+        &self,
+        level: logging::logging::Level,
+        context: String,
+        message: String,
+    ) -> anyhow::Result<()> {
+        self.observe(
+            TargetInterface::WasiLoggingLogging,
+            "wasi:logging/logging.log",
+            |_| async move {
+                // Logging handler is optional, since messages are always emitted as `tracing` events
+                let link_name = self.link_name(&TargetInterface::WasiLoggingLogging);
+                if let Some(logging) = self.logging.get(link_name) {
+                    logging.log(level, context, message).await
+                } else {
+                    Ok(())
+                }
+            },
+        )
+        .await
+    }
+}
+
 #[async_trait]
 impl Messaging for Handler {
     #[instrument(skip(body))]
@@ -552,9 +1122,15 @@ impl Messaging for Handler {
         body: Option<Vec<u8>>,
         timeout: Duration,
     ) -> anyhow::Result<messaging::types::BrokerMessage> {
-        self.proxy_messaging("wasmcloud:messaging/consumer.request")?
-            .request(subject, body, timeout)
-            .await
+        self.observe_messaging(
+            "wasmcloud:messaging/consumer.request",
+            |method| async move {
+                self.proxy_messaging(method)?
+                    .request(subject, body, timeout)
+                    .await
+            },
+        )
+        .await
     }
 
     #[instrument(skip(body))]
@@ -565,16 +1141,36 @@ impl Messaging for Handler {
         timeout: Duration,
         max_results: u32,
     ) -> anyhow::Result<Vec<messaging::types::BrokerMessage>> {
-        self.proxy_messaging("wasmcloud:messaging/consumer.request-multi")?
-            .request_multi(subject, body, timeout, max_results)
-            .await
+        self.observe_messaging(
+            "wasmcloud:messaging/consumer.request-multi",
+            |method| async move {
+                self.proxy_messaging(method)?
+                    .request_multi(subject, body, timeout, max_results)
+                    .await
+            },
+        )
+        .await
     }
 
     #[instrument(skip(msg))]
     async fn publish(&self, msg: messaging::types::BrokerMessage) -> anyhow::Result<()> {
-        self.proxy_messaging("wasmcloud:messaging/consumer.publish")?
-            .publish(msg)
-            .await
+        self.observe_messaging(
+            "wasmcloud:messaging/consumer.publish",
+            |method| async move { self.proxy_messaging(method)?.publish(msg).await },
+        )
+        .await
+    }
+
+    #[instrument]
+    async fn subscribe(
+        &self,
+        subject: String,
+    ) -> anyhow::Result<Box<dyn Stream<Item = messaging::types::BrokerMessage> + Sync + Send + Unpin>>
+    {
+        self.observe_messaging("wasmcloud:messaging/handler", |method| async move {
+            self.proxy_messaging(method)?.subscribe(subject).await
+        })
+        .await
     }
 }
 
@@ -585,12 +1181,20 @@ impl OutgoingHttp for Handler {
         &self,
         request: OutgoingHttpRequest,
     ) -> anyhow::Result<::http::Response<Box<dyn AsyncRead + Sync + Send + Unpin>>> {
-        proxy(
-            &self.outgoing_http,
-            "OutgoingHttp",
+        self.observe(
+            TargetInterface::WasiHttpOutgoingHandler,
             "wasi:http/outgoing-handler.handle",
-        )?
-        .handle(request)
+            |method| async move {
+                proxy_link(
+                    &self.outgoing_http,
+                    self.link_name(&TargetInterface::WasiHttpOutgoingHandler),
+                    "OutgoingHttp",
+                    method,
+                )?
+                .handle(request)
+                .await
+            },
+        )
         .await
     }
 }
@@ -598,27 +1202,29 @@ impl OutgoingHttp for Handler {
 /// A [Handler] builder used to configure it
 #[derive(Clone, Default)]
 pub(crate) struct HandlerBuilder {
-    /// [`Blobstore`] handler
-    pub blobstore: Option<Arc<dyn Blobstore + Sync + Send>>,
+    /// [`Blobstore`] handlers keyed by link name
+    pub blobstore: Links<dyn Blobstore + Sync + Send>,
     /// [`IncomingHttp`] handler
     pub incoming_http: Option<Arc<dyn IncomingHttp + Sync + Send>>,
-    /// [`KeyValueAtomic`] handler
-    pub keyvalue_atomic: Option<Arc<dyn KeyValueAtomic + Sync + Send>>,
-    /// [`KeyValueEventual`] handler
-    pub keyvalue_eventual: Option<Arc<dyn KeyValueEventual + Sync + Send>>,
-    /// [`Messaging`] handler
-    pub messaging: Option<Arc<dyn Messaging + Sync + Send>>,
-    /// [`OutgoingHttp`] handler
-    pub outgoing_http: Option<Arc<dyn OutgoingHttp + Sync + Send>>,
+    /// [`KeyValueAtomic`] handlers keyed by link name
+    pub keyvalue_atomic: Links<dyn KeyValueAtomic + Sync + Send>,
+    /// [`KeyValueEventual`] handlers keyed by link name
+    pub keyvalue_eventual: Links<dyn KeyValueEventual + Sync + Send>,
+    /// [`Logging`] handlers keyed by link name
+    pub logging: Links<dyn Logging + Sync + Send>,
+    /// [`Messaging`] handlers keyed by link name
+    pub messaging: Links<dyn Messaging + Sync + Send>,
+    /// [`OutgoingHttp`] handlers keyed by link name
+    pub outgoing_http: Links<dyn OutgoingHttp + Sync + Send>,
+    /// [`Metrics`] sink
+    pub metrics: Option<Arc<dyn Metrics + Sync + Send>>,
 }
 
 impl HandlerBuilder {
-    /// Set [`Blobstore`] handler
-    pub fn blobstore(self, blobstore: Arc<impl Blobstore + Sync + Send + 'static>) -> Self {
-        Self {
-            blobstore: Some(blobstore),
-            ..self
-        }
+    /// Set [`Blobstore`] handler of the default link
+    pub fn blobstore(mut self, blobstore: Arc<impl Blobstore + Sync + Send + 'static>) -> Self {
+        self.blobstore.insert(DEFAULT_LINK_NAME.into(), blobstore);
+        self
     }
 
     /// Set [`IncomingHttp`] handler
@@ -632,43 +1238,52 @@ impl HandlerBuilder {
         }
     }
 
-    /// Set [`KeyValueAtomic`] handler
+    /// Set [`KeyValueAtomic`] handler of the default link
     pub fn keyvalue_atomic(
-        self,
+        mut self,
         keyvalue_atomic: Arc<impl KeyValueAtomic + Sync + Send + 'static>,
     ) -> Self {
-        Self {
-            keyvalue_atomic: Some(keyvalue_atomic),
-            ..self
-        }
+        self.keyvalue_atomic
+            .insert(DEFAULT_LINK_NAME.into(), keyvalue_atomic);
+        self
     }
 
-    /// Set [`KeyValueEventual`] handler
+    /// Set [`KeyValueEventual`] handler of the default link
     pub fn keyvalue_eventual(
-        self,
+        mut self,
         keyvalue_eventual: Arc<impl KeyValueEventual + Sync + Send + 'static>,
     ) -> Self {
-        Self {
-            keyvalue_eventual: Some(keyvalue_eventual),
-            ..self
-        }
+        self.keyvalue_eventual
+            .insert(DEFAULT_LINK_NAME.into(), keyvalue_eventual);
+        self
     }
 
-    /// Set [`Messaging`] handler
-    pub fn messaging(self, messaging: Arc<impl Messaging + Sync + Send + 'static>) -> Self {
-        Self {
-            messaging: Some(messaging),
-            ..self
-        }
+    /// Set [`Logging`] handler of the default link
+    pub fn logging(mut self, logging: Arc<impl Logging + Sync + Send + 'static>) -> Self {
+        self.logging.insert(DEFAULT_LINK_NAME.into(), logging);
+        self
+    }
+
+    /// Set [`Messaging`] handler of the default link
+    pub fn messaging(mut self, messaging: Arc<impl Messaging + Sync + Send + 'static>) -> Self {
+        self.messaging.insert(DEFAULT_LINK_NAME.into(), messaging);
+        self
     }
 
-    /// Set [`OutgoingHttp`] handler
+    /// Set [`OutgoingHttp`] handler of the default link
     pub fn outgoing_http(
-        self,
+        mut self,
         outgoing_http: Arc<impl OutgoingHttp + Sync + Send + 'static>,
     ) -> Self {
+        self.outgoing_http
+            .insert(DEFAULT_LINK_NAME.into(), outgoing_http);
+        self
+    }
+
+    /// Set [`Metrics`] sink
+    pub fn metrics(self, metrics: Arc<impl Metrics + Sync + Send + 'static>) -> Self {
         Self {
-            outgoing_http: Some(outgoing_http),
+            metrics: Some(metrics),
             ..self
         }
     }
@@ -677,12 +1292,14 @@ impl HandlerBuilder {
 impl Debug for HandlerBuilder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HandlerBuilder")
-            .field("blobstore", &format_opt(&self.blobstore))
+            .field("blobstore", &self.blobstore.keys())
             .field("incoming_http", &format_opt(&self.incoming_http))
-            .field("keyvalue_atomic", &format_opt(&self.keyvalue_atomic))
-            .field("keyvalue_eventual", &format_opt(&self.keyvalue_eventual))
-            .field("messaging", &format_opt(&self.messaging))
-            .field("outgoing_http", &format_opt(&self.outgoing_http))
+            .field("keyvalue_atomic", &self.keyvalue_atomic.keys())
+            .field("keyvalue_eventual", &self.keyvalue_eventual.keys())
+            .field("logging", &self.logging.keys())
+            .field("messaging", &self.messaging.keys())
+            .field("outgoing_http", &self.outgoing_http.keys())
+            .field("metrics", &format_opt(&self.metrics))
             .finish()
     }
 }
@@ -694,8 +1311,12 @@ impl From<Handler> for HandlerBuilder {
             incoming_http,
             keyvalue_atomic,
             keyvalue_eventual,
+            logging,
             messaging,
             outgoing_http,
+            link_names: _,
+            metrics,
+            component_id: _,
         }: Handler,
     ) -> Self {
         Self {
@@ -703,8 +1324,10 @@ impl From<Handler> for HandlerBuilder {
             incoming_http,
             keyvalue_atomic,
             keyvalue_eventual,
+            logging,
             messaging,
             outgoing_http,
+            metrics,
         }
     }
 }
@@ -716,8 +1339,10 @@ impl From<HandlerBuilder> for Handler {
             incoming_http,
             keyvalue_atomic,
             keyvalue_eventual,
+            logging,
             messaging,
             outgoing_http,
+            metrics,
         }: HandlerBuilder,
     ) -> Self {
         Self {
@@ -726,7 +1351,74 @@ impl From<HandlerBuilder> for Handler {
             outgoing_http,
             keyvalue_atomic,
             keyvalue_eventual,
+            logging,
             messaging,
+            link_names: HashMap::default(),
+            metrics,
+            component_id: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::provider::MemoryKeyValue;
+
+    #[tokio::test]
+    async fn link_routing() -> anyhow::Result<()> {
+        let default = Arc::new(MemoryKeyValue::from(HashMap::from([(
+            "bucket".into(),
+            HashMap::default(),
+        )])));
+        let other = Arc::new(MemoryKeyValue::from(HashMap::from([(
+            "bucket".into(),
+            HashMap::default(),
+        )])));
+        let mut handler = Handler::default();
+        handler.replace_keyvalue_atomic(default.clone());
+        handler.replace_keyvalue_atomic_link("other", other.clone());
+
+        assert_eq!(handler.increment("bucket", "n".into(), 1).await?, 1);
+        handler.set_link_name("other", ["wasi:keyvalue/atomic@0.1.0".parse()?]);
+        assert_eq!(
+            handler.link_name(&TargetInterface::WasiKeyvalueAtomic),
+            "other"
+        );
+        assert_eq!(handler.increment("bucket", "n".into(), 5).await?, 5);
+        assert_eq!(default.increment("bucket", "n".into(), 0).await?, 1);
+
+        handler.set_link_name("missing", [TargetInterface::WasiKeyvalueAtomic]);
+        assert!(handler.increment("bucket", "n".into(), 1).await.is_err());
+        handler.set_link_name(DEFAULT_LINK_NAME, [TargetInterface::WasiKeyvalueAtomic]);
+        assert_eq!(handler.increment("bucket", "n".into(), 1).await?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_target_interface() -> anyhow::Result<()> {
+        assert_eq!(
+            "wasi:blobstore/blobstore".parse::<TargetInterface>()?,
+            TargetInterface::WasiBlobstoreBlobstore
+        );
+        assert_eq!(
+            "wasmcloud:messaging/consumer".parse::<TargetInterface>()?,
+            TargetInterface::WasmcloudMessagingConsumer
+        );
+        assert_eq!(
+            "acme:greeter/greet@1.0.0".parse::<TargetInterface>()?,
+            TargetInterface::Custom {
+                namespace: "acme".into(),
+                package: "greeter".into(),
+                interface: "greet".into(),
+            }
+        );
+        assert!("wasi:keyvalue".parse::<TargetInterface>().is_err());
+        assert!(":foo/bar".parse::<TargetInterface>().is_err());
+        for name in ["wasi:logging/logging", "acme:greeter/greet"] {
+            assert_eq!(name.parse::<TargetInterface>()?.to_string(), name);
         }
+        Ok(())
     }
 }
@@ -0,0 +1,421 @@
+//! Metrics of capability calls handled by [Handler](super::Handler)
+
+use super::TargetInterface;
+
+use core::convert::Infallible;
+use core::fmt::{Debug, Write as _};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use anyhow::Context as _;
+use bytes::Bytes;
+use http::{header, Method, Request, Response, StatusCode};
+use http_body_util::Full;
+use hyper::rt::{Sleep, Timer};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tracing::{debug, error, instrument};
+use wasmtime_wasi_http::io::TokioIo;
+
+/// Labels identifying a capability call
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CallLabels {
+    /// Identifier of the calling component, if known
+    pub component: Option<String>,
+    /// Interface called
+    pub interface: TargetInterface,
+    /// Link the call was routed to
+    pub link_name: String,
+    /// Fully-qualified name of the method called, for example,
+    /// `wasi:blobstore/container.get-data`
+    pub method: String,
+}
+
+/// Direction of a value body transfer relative to the guest
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    /// Value body read by the guest, for example, `wasi:keyvalue/eventual.get`
+    Incoming,
+    /// Value body written by the guest, for example, `wasi:keyvalue/eventual.set`
+    Outgoing,
+}
+
+/// Sink for metrics of capability calls
+///
+/// Methods are called inline on the call path, so implementations should be
+/// cheap and must not block.
+pub trait Metrics {
+    /// Record a completed call taking `duration`, which failed unless
+    /// `success` is set
+    fn record_call(&self, labels: &CallLabels, duration: Duration, success: bool);
+
+    /// Record `n` bytes of a blobstore or keyvalue value body transferred in
+    /// `direction`
+    fn record_bytes(&self, labels: &CallLabels, direction: Direction, n: u64);
+}
+
+/// [AsyncRead] wrapper recording the bytes read to [Metrics]
+pub(crate) struct MeteredReader<T> {
+    pub inner: T,
+    pub metrics: Arc<dyn Metrics + Sync + Send>,
+    pub labels: CallLabels,
+    pub direction: Direction,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for MeteredReader<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let n = buf.filled().len() - filled;
+        if n > 0 {
+            self.metrics
+                .record_bytes(&self.labels, self.direction, n as u64);
+        }
+        res
+    }
+}
+
+/// Upper bounds in seconds of the call duration histogram buckets
+const DURATION_BUCKETS: [f64; 14] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Time allowed for a client of the metrics endpoint to send request headers
+const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct CallStats {
+    calls: u64,
+    errors: u64,
+    duration_buckets: [u64; DURATION_BUCKETS.len()],
+    duration_sum: f64,
+    incoming_bytes: u64,
+    outgoing_bytes: u64,
+}
+
+/// [Metrics] implementation aggregating calls in memory and exporting them in
+/// the Prometheus text exposition format
+///
+/// The following metrics are exported, each labeled by `component`,
+/// `interface`, `link` and `method`:
+///
+/// - `wasmtime_cloud_core_calls_total` counter of calls
+/// - `wasmtime_cloud_core_call_errors_total` counter of failed calls
+/// - `wasmtime_cloud_core_call_duration_seconds` histogram of call durations
+/// - `wasmtime_cloud_core_bytes_total` counter of value body bytes, further
+///   labeled by `direction`, which is either `incoming` or `outgoing`
+#[derive(Debug, Default)]
+pub struct PrometheusMetrics(Mutex<HashMap<CallLabels, CallStats>>);
+
+impl Metrics for PrometheusMetrics {
+    fn record_call(&self, labels: &CallLabels, duration: Duration, success: bool) {
+        let mut stats = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let stats = stats.entry(labels.clone()).or_default();
+        stats.calls += 1;
+        if !success {
+            stats.errors += 1;
+        }
+        let secs = duration.as_secs_f64();
+        stats.duration_sum += secs;
+        for (count, le) in stats.duration_buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if secs <= le {
+                *count += 1;
+            }
+        }
+    }
+
+    fn record_bytes(&self, labels: &CallLabels, direction: Direction, n: u64) {
+        let mut stats = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let stats = stats.entry(labels.clone()).or_default();
+        match direction {
+            Direction::Incoming => stats.incoming_bytes += n,
+            Direction::Outgoing => stats.outgoing_bytes += n,
+        }
+    }
+}
+
+/// Escape a Prometheus label value
+fn escape(s: &str) -> String {
+    s.replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+impl PrometheusMetrics {
+    /// Render all metrics recorded so far in the Prometheus text exposition
+    /// format
+    pub fn render(&self) -> String {
+        let stats = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stats: Vec<_> = stats.iter().collect();
+        stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let stats: Vec<_> = stats
+            .into_iter()
+            .map(|(labels, stats)| {
+                let labels = format!(
+                    r#"component="{}",interface="{}",link="{}",method="{}""#,
+                    escape(labels.component.as_deref().unwrap_or_default()),
+                    escape(&labels.interface.to_string()),
+                    escape(&labels.link_name),
+                    escape(&labels.method),
+                );
+                (labels, stats)
+            })
+            .collect();
+
+        let mut out = String::new();
+        out.push_str("# HELP wasmtime_cloud_core_calls_total Capability calls handled.\n");
+        out.push_str("# TYPE wasmtime_cloud_core_calls_total counter\n");
+        for (labels, stats) in &stats {
+            let _ = writeln!(
+                out,
+                "wasmtime_cloud_core_calls_total{{{labels}}} {}",
+                stats.calls
+            );
+        }
+        out.push_str("# HELP wasmtime_cloud_core_call_errors_total Capability calls failed.\n");
+        out.push_str("# TYPE wasmtime_cloud_core_call_errors_total counter\n");
+        for (labels, stats) in &stats {
+            let _ = writeln!(
+                out,
+                "wasmtime_cloud_core_call_errors_total{{{labels}}} {}",
+                stats.errors
+            );
+        }
+        out.push_str(
+            "# HELP wasmtime_cloud_core_call_duration_seconds Capability call durations.\n",
+        );
+        out.push_str("# TYPE wasmtime_cloud_core_call_duration_seconds histogram\n");
+        for (labels, stats) in &stats {
+            for (count, le) in stats.duration_buckets.iter().zip(DURATION_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    r#"wasmtime_cloud_core_call_duration_seconds_bucket{{{labels},le="{le}"}} {count}"#
+                );
+            }
+            let _ = writeln!(
+                out,
+                r#"wasmtime_cloud_core_call_duration_seconds_bucket{{{labels},le="+Inf"}} {}"#,
+                stats.calls
+            );
+            let _ = writeln!(
+                out,
+                "wasmtime_cloud_core_call_duration_seconds_sum{{{labels}}} {}",
+                stats.duration_sum
+            );
+            let _ = writeln!(
+                out,
+                "wasmtime_cloud_core_call_duration_seconds_count{{{labels}}} {}",
+                stats.calls
+            );
+        }
+        out.push_str("# HELP wasmtime_cloud_core_bytes_total Value body bytes transferred.\n");
+        out.push_str("# TYPE wasmtime_cloud_core_bytes_total counter\n");
+        for (labels, stats) in &stats {
+            for (direction, n) in [
+                ("incoming", stats.incoming_bytes),
+                ("outgoing", stats.outgoing_bytes),
+            ] {
+                if n > 0 {
+                    let _ = writeln!(
+                        out,
+                        r#"wasmtime_cloud_core_bytes_total{{{labels},direction="{direction}"}} {n}"#
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    /// Serve the rendered metrics over HTTP on `GET /metrics` to connections
+    /// accepted on `listener` until accepting fails
+    #[instrument(skip_all)]
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
+        loop {
+            let (conn, addr) = listener
+                .accept()
+                .await
+                .context("failed to accept connection")?;
+            let metrics = Arc::clone(&self);
+            tokio::spawn(async move {
+                let service = service_fn(move |req| {
+                    let res = metrics.respond(&req);
+                    async move { Ok::<_, Infallible>(res) }
+                });
+                if let Err(err) = http1::Builder::new()
+                    .timer(TokioTimer)
+                    .header_read_timeout(HEADER_READ_TIMEOUT)
+                    .keep_alive(false)
+                    .serve_connection(TokioIo::new(conn), service)
+                    .await
+                {
+                    debug!(?err, ?addr, "failed to serve metrics");
+                }
+            });
+        }
+    }
+
+    /// Bind `addr` and spawn a task serving the rendered metrics on it, see
+    /// [`PrometheusMetrics::serve`], returns the bound address
+    pub async fn spawn_server(
+        self: &Arc<Self>,
+        addr: impl ToSocketAddrs,
+    ) -> anyhow::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .context("failed to bind metrics listener")?;
+        let addr = listener
+            .local_addr()
+            .context("failed to get metrics listener address")?;
+        let metrics = Arc::clone(self);
+        tokio::spawn(async move {
+            if let Err(err) = metrics.serve(listener).await {
+                error!(?err, "failed to serve metrics");
+            }
+        });
+        Ok(addr)
+    }
+
+    /// Respond to a single HTTP request, the body of `HEAD` responses is
+    /// omitted by [hyper]
+    fn respond<T>(&self, req: &Request<T>) -> Response<Full<Bytes>> {
+        let res = Response::builder();
+        let res = if req.uri().path() != "/metrics" {
+            res.status(StatusCode::NOT_FOUND).body(Full::default())
+        } else if req.method() == Method::GET || req.method() == Method::HEAD {
+            res.header(header::CONTENT_TYPE, "text/plain; version=0.0.4")
+                .body(Full::new(self.render().into()))
+        } else {
+            res.status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "GET, HEAD")
+                .body(Full::default())
+        };
+        res.expect("metrics response is valid")
+    }
+}
+
+/// [hyper] timer driven by [tokio]
+struct TokioTimer;
+
+impl Timer for TokioTimer {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Sleep>> {
+        Box::pin(TokioSleep(Box::pin(tokio::time::sleep(duration))))
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Sleep>> {
+        Box::pin(TokioSleep(Box::pin(tokio::time::sleep_until(
+            deadline.into(),
+        ))))
+    }
+}
+
+struct TokioSleep(Pin<Box<tokio::time::Sleep>>);
+
+impl Future for TokioSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl Sleep for TokioSleep {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::provider::MemoryKeyValue;
+    use crate::capability::{Handler, KeyValueEventual as _, DEFAULT_LINK_NAME};
+
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpStream;
+
+    #[tokio::test]
+    async fn prometheus() -> anyhow::Result<()> {
+        let metrics = Arc::new(PrometheusMetrics::default());
+        let mut handler = Handler::default();
+        handler.replace_keyvalue_eventual(Arc::new(MemoryKeyValue::from(HashMap::from([(
+            "bucket".into(),
+            HashMap::default(),
+        )]))));
+        handler.replace_metrics(metrics.clone());
+        handler.set_component_id("test");
+
+        handler
+            .set("bucket", "foo".into(), Box::new(&b"bar"[..]))
+            .await?;
+        let (mut value, _) = handler
+            .get("bucket", "foo".into())
+            .await?
+            .context("value missing")?;
+        let mut buf = vec![];
+        value.read_to_end(&mut buf).await?;
+        assert!(handler.get("missing", "foo".into()).await.is_err());
+
+        let out = metrics.render();
+        let labels = format!(
+            r#"component="test",interface="wasi:keyvalue/eventual",link="{DEFAULT_LINK_NAME}""#
+        );
+        for line in [
+            format!(
+                r#"wasmtime_cloud_core_calls_total{{{labels},method="wasi:keyvalue/eventual.get"}} 2"#
+            ),
+            format!(
+                r#"wasmtime_cloud_core_call_errors_total{{{labels},method="wasi:keyvalue/eventual.get"}} 1"#
+            ),
+            format!(
+                r#"wasmtime_cloud_core_call_duration_seconds_count{{{labels},method="wasi:keyvalue/eventual.set"}} 1"#
+            ),
+            format!(
+                r#"wasmtime_cloud_core_bytes_total{{{labels},method="wasi:keyvalue/eventual.get",direction="incoming"}} 3"#
+            ),
+            format!(
+                r#"wasmtime_cloud_core_bytes_total{{{labels},method="wasi:keyvalue/eventual.set",direction="outgoing"}} 3"#
+            ),
+        ] {
+            assert!(
+                out.lines().any(|l| l == line),
+                "`{line}` missing in:\n{out}"
+            );
+        }
+
+        let addr = metrics.spawn_server("127.0.0.1:0").await?;
+        let request = |req: &'static str| async move {
+            let mut conn = TcpStream::connect(addr).await?;
+            conn.write_all(format!("{req} HTTP/1.1\r\nhost: localhost\r\n\r\n").as_bytes())
+                .await?;
+            let mut res = String::new();
+            conn.read_to_string(&mut res).await?;
+            anyhow::Ok(res)
+        };
+        for req in ["GET /metrics", "GET /metrics?name=foo"] {
+            let res = request(req).await?;
+            assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{res}");
+            assert!(res.ends_with(&out));
+        }
+        let res = request("HEAD /metrics").await?;
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{res}");
+        assert!(res.ends_with("\r\n\r\n"));
+        let res = request("GET /metricsfoo").await?;
+        assert!(res.starts_with("HTTP/1.1 404 Not Found\r\n"), "{res}");
+        let res = request("POST /metrics").await?;
+        assert!(
+            res.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+            "{res}"
+        );
+        Ok(())
+    }
+}
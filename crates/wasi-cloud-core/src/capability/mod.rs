@@ -1,12 +1,15 @@
 pub(crate) mod builtin;
+pub mod metrics;
 
 /// Provider implementations
 pub mod provider;
 
 pub use builtin::{
-    ActorIdentifier, Blobstore, IncomingHttp, KeyValueAtomic, KeyValueEventual,
-    Messaging, OutgoingHttp, OutgoingHttpRequest, TargetEntity, TargetInterface,
+    ActorIdentifier, Blobstore, Handler, IncomingHttp, KeyValueAtomic, KeyValueEventual,
+    Logging, Messaging, OutgoingHttp, OutgoingHttpRequest, TargetEntity, TargetInterface,
+    DEFAULT_LINK_NAME,
 };
+pub use metrics::{Metrics, PrometheusMetrics};
 
 #[allow(clippy::doc_markdown)]
 #[allow(missing_docs)]
@@ -23,7 +26,7 @@ mod bindgen {
     mod blobstore {
         pub type Container = std::sync::Arc<String>;
         pub type IncomingValue = (Box<dyn tokio::io::AsyncRead + Send + Sync + Unpin>, u64);
-        pub type OutgoingValue = crate::io::AsyncVec;
+        pub type OutgoingValue = crate::io::SpooledVec;
         pub type StreamObjectNames =
             Box<dyn futures::Stream<Item = anyhow::Result<String>> + Sync + Send + Unpin>;
     }
@@ -61,11 +64,69 @@ mod bindgen {
     });
 }
 
-pub use bindgen::wasi::{blobstore, keyvalue};
+#[allow(clippy::doc_markdown)]
+#[allow(missing_docs)]
+mod guest {
+    wasmtime::component::bindgen!({
+        world: "messaging-handler",
+        async: true,
+        with: {
+           "wasmcloud:messaging/types": crate::capability::messaging::types,
+        },
+    });
+}
+
+pub use bindgen::wasi::{blobstore, keyvalue, logging};
+pub use bindgen::wasmcloud::host::{keyvalue_ext, lattice};
 pub use bindgen::wasmcloud::messaging;
 pub use bindgen::Interfaces;
+pub use guest::MessagingHandler;
 pub use wasmtime_wasi_http::bindings::http;
 
+/// Add the imports of the `wasmcloud:host/interfaces` world providing the
+/// capability `interfaces` to `linker`, using `get` to access the
+/// [`Host`](crate::component::Host) in the store data
+///
+/// Unlike [`Interfaces::add_to_linker`], interfaces not listed in
+/// `interfaces` are left undefined, so components importing them fail to
+/// instantiate. `wasmcloud:host/keyvalue-ext` is added along with
+/// `wasi:keyvalue/eventual` and `wasmcloud:host/lattice` is always added, while
+/// `wasi:http/outgoing-handler` is provided by `wasmtime-wasi-http` and has
+/// to be added separately, same as for [`Interfaces::add_to_linker`].
+pub fn add_interfaces_to_linker<T: Send + 'static>(
+    linker: &mut wasmtime::component::Linker<T>,
+    get: fn(&mut T) -> &mut crate::component::Host,
+    interfaces: &[TargetInterface],
+) -> anyhow::Result<()> {
+    lattice::add_to_linker(linker, get)?;
+    if interfaces.contains(&TargetInterface::WasiBlobstoreBlobstore) {
+        blobstore::types::add_to_linker(linker, get)?;
+        blobstore::container::add_to_linker(linker, get)?;
+        blobstore::blobstore::add_to_linker(linker, get)?;
+    }
+    let atomic = interfaces.contains(&TargetInterface::WasiKeyvalueAtomic);
+    let eventual = interfaces.contains(&TargetInterface::WasiKeyvalueEventual);
+    if atomic || eventual {
+        keyvalue::wasi_keyvalue_error::add_to_linker(linker, get)?;
+        keyvalue::types::add_to_linker(linker, get)?;
+    }
+    if atomic {
+        keyvalue::atomic::add_to_linker(linker, get)?;
+    }
+    if eventual {
+        keyvalue::eventual::add_to_linker(linker, get)?;
+        keyvalue_ext::add_to_linker(linker, get)?;
+    }
+    if interfaces.contains(&TargetInterface::WasiLoggingLogging) {
+        logging::logging::add_to_linker(linker, get)?;
+    }
+    if interfaces.contains(&TargetInterface::WasmcloudMessagingConsumer) {
+        messaging::types::add_to_linker(linker, get)?;
+        messaging::consumer::add_to_linker(linker, get)?;
+    }
+    Ok(())
+}
+
 fn format_opt<T>(opt: &Option<T>) -> &'static str {
     if opt.is_some() {
         "set"
@@ -1,3 +1,6 @@
 mod s3;
 
-pub use s3::S3Blobstore;
+pub use s3::{
+    S3Blobstore, S3BlobstoreConfig, S3Credentials, S3UploadProgress, DEFAULT_MULTIPART_PART_SIZE,
+    DEFAULT_MULTIPART_THRESHOLD, MIN_MULTIPART_PART_SIZE,
+};
@@ -1,31 +1,253 @@
 use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use async_trait::async_trait;
 use aws_config::BehaviorVersion;
 use aws_sdk_s3::{
-    client::Client, operation::get_object::GetObjectError, primitives::ByteStream, types::{builders::DeleteBuilder, ObjectIdentifier}
+    client::Client, config::{Credentials, Region}, operation::get_object::GetObjectError, primitives::ByteStream, types::{builders::DeleteBuilder, CompletedMultipartUpload, CompletedPart, ObjectIdentifier}
 };
 use futures::{stream, Stream};
-use tokio::{io::{AsyncRead, AsyncReadExt}, runtime::Handle};
-use tracing::instrument;
+use serde_derive::Deserialize;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{instrument, warn};
 
 use crate::capability::{self, blobstore};
 
+/// Default size above which [S3Blobstore] uploads objects using multipart
+/// upload
+pub const DEFAULT_MULTIPART_THRESHOLD: u64 = 8 << 20;
+
+/// Default size of parts uploaded by [S3Blobstore]
+pub const DEFAULT_MULTIPART_PART_SIZE: u64 = 8 << 20;
+
+/// Minimum size of all but the last part of a multipart upload accepted by S3
+pub const MIN_MULTIPART_PART_SIZE: u64 = 5 << 20;
+
+/// Maximum number of parts of a multipart upload accepted by S3
+const MAX_MULTIPART_PARTS: i32 = 10_000;
+
+/// Credentials used by [S3Blobstore] to authenticate against the S3 service
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum S3Credentials {
+    /// Static access key credentials
+    #[serde(rename_all = "kebab-case")]
+    Static {
+        /// Access key ID
+        access_key_id: String,
+        /// Secret access key
+        secret_access_key: String,
+        /// Optional session token
+        session_token: Option<String>,
+    },
+    /// Named profile from the shared AWS config and credentials files
+    Profile(String),
+}
+
+/// [S3Blobstore] configuration
+///
+/// Any unset value falls back to the standard AWS environment, profile and
+/// IMDS resolution performed by [`aws_config::defaults`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct S3BlobstoreConfig {
+    /// Custom endpoint URL, e.g. `http://localhost:9000` for MinIO or
+    /// `http://localhost:4566` for LocalStack
+    pub endpoint_url: Option<String>,
+    /// AWS region
+    pub region: Option<String>,
+    /// Credentials to use instead of the default provider chain
+    pub credentials: Option<S3Credentials>,
+    /// Whether to use path-style addressing (`endpoint/bucket/key`) instead
+    /// of virtual-hosted-style addressing (`bucket.endpoint/key`), which
+    /// most S3-compatible stand-ins require
+    pub force_path_style: bool,
+    /// Prefix prepended to every container name to derive the bucket name
+    pub bucket_prefix: Option<String>,
+    /// Objects larger than this many bytes are uploaded using multipart
+    /// upload, defaults to [DEFAULT_MULTIPART_THRESHOLD]
+    pub multipart_threshold: Option<u64>,
+    /// Size of individual parts of multipart uploads in bytes, which bounds
+    /// the memory used per upload, defaults to [DEFAULT_MULTIPART_PART_SIZE]
+    pub multipart_part_size: Option<u64>,
+}
+
+/// Snapshot of [S3Blobstore] upload progress counters
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct S3UploadProgress {
+    /// Total number of bytes uploaded
+    pub bytes_uploaded: u64,
+    /// Total number of multipart upload parts uploaded
+    pub parts_uploaded: u64,
+    /// Number of multipart uploads started
+    pub multipart_started: u64,
+    /// Number of multipart uploads completed
+    pub multipart_completed: u64,
+    /// Number of multipart uploads aborted due to an error
+    pub multipart_aborted: u64,
+}
+
+#[derive(Debug, Default)]
+struct UploadCounters {
+    bytes_uploaded: AtomicU64,
+    parts_uploaded: AtomicU64,
+    multipart_started: AtomicU64,
+    multipart_completed: AtomicU64,
+    multipart_aborted: AtomicU64,
+}
+
+/// [`Blobstore`](crate::capability::Blobstore) implementation backed by S3
+/// or any S3-compatible service
 #[derive(Debug)]
 pub struct S3Blobstore {
     client: Arc<Client>,
+    bucket_prefix: String,
+    multipart_threshold: u64,
+    multipart_part_size: usize,
+    counters: UploadCounters,
 }
 
 impl S3Blobstore {
+    /// Construct a new [S3Blobstore] using the default AWS configuration
     pub async fn new() -> Result<Self> {
-        let config = aws_config::defaults(BehaviorVersion::v2023_11_09()).load().await;
-        let client = Arc::new(Client::new(&config));
-        Ok(Self { client })
+        Self::with_config(S3BlobstoreConfig::default()).await
+    }
+
+    /// Construct a new [S3Blobstore] using the given [S3BlobstoreConfig]
+    pub async fn with_config(
+        S3BlobstoreConfig {
+            endpoint_url,
+            region,
+            credentials,
+            force_path_style,
+            bucket_prefix,
+            multipart_threshold,
+            multipart_part_size,
+        }: S3BlobstoreConfig,
+    ) -> Result<Self> {
+        let multipart_threshold = multipart_threshold.unwrap_or(DEFAULT_MULTIPART_THRESHOLD);
+        let multipart_part_size = multipart_part_size.unwrap_or(DEFAULT_MULTIPART_PART_SIZE);
+        ensure!(
+            multipart_part_size >= MIN_MULTIPART_PART_SIZE,
+            "Multipart part size must be at least {MIN_MULTIPART_PART_SIZE} bytes"
+        );
+        let multipart_part_size = usize::try_from(multipart_part_size).context("Multipart part size is too large")?;
+        let mut loader = aws_config::defaults(BehaviorVersion::v2023_11_09());
+        if let Some(endpoint_url) = endpoint_url {
+            loader = loader.endpoint_url(endpoint_url);
+        }
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region));
+        }
+        match credentials {
+            Some(S3Credentials::Static {
+                access_key_id,
+                secret_access_key,
+                session_token,
+            }) => {
+                loader = loader.credentials_provider(Credentials::new(
+                    access_key_id,
+                    secret_access_key,
+                    session_token,
+                    None,
+                    "wasmtime",
+                ));
+            }
+            Some(S3Credentials::Profile(name)) => {
+                loader = loader.profile_name(name);
+            }
+            None => {}
+        }
+        let config = loader.load().await;
+        let config = aws_sdk_s3::config::Builder::from(&config)
+            .force_path_style(force_path_style)
+            .build();
+        let client = Arc::new(Client::from_conf(config));
+        Ok(Self {
+            client,
+            bucket_prefix: bucket_prefix.unwrap_or_default(),
+            multipart_threshold,
+            multipart_part_size,
+            counters: UploadCounters::default(),
+        })
+    }
+
+    /// Returns a snapshot of the upload progress counters
+    pub fn upload_progress(&self) -> S3UploadProgress {
+        let UploadCounters {
+            bytes_uploaded,
+            parts_uploaded,
+            multipart_started,
+            multipart_completed,
+            multipart_aborted,
+        } = &self.counters;
+        S3UploadProgress {
+            bytes_uploaded: bytes_uploaded.load(Ordering::Relaxed),
+            parts_uploaded: parts_uploaded.load(Ordering::Relaxed),
+            multipart_started: multipart_started.load(Ordering::Relaxed),
+            multipart_completed: multipart_completed.load(Ordering::Relaxed),
+            multipart_aborted: multipart_aborted.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Uploads the parts of a started multipart upload, the first one of
+    /// which is `buf`, reading at most one part into memory at a time
+    async fn upload_parts(&self, bucket: &str, key: &str, upload_id: &str, mut buf: Vec<u8>, value: &mut (dyn AsyncRead + Sync + Send + Unpin)) -> Result<Vec<CompletedPart>> {
+        let mut parts = Vec::new();
+        for part_number in 1..=MAX_MULTIPART_PARTS {
+            fill(value, &mut buf, self.multipart_part_size).await?;
+            let rest = if buf.len() > self.multipart_part_size {
+                buf.split_off(self.multipart_part_size)
+            } else {
+                Vec::with_capacity(self.multipart_part_size)
+            };
+            let part = std::mem::replace(&mut buf, rest);
+            let len = part.len() as u64;
+            let resp = self
+                .client
+                .upload_part()
+                .bucket(bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(part))
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload part {part_number}"))?;
+            self.counters.bytes_uploaded.fetch_add(len, Ordering::Relaxed);
+            self.counters.parts_uploaded.fetch_add(1, Ordering::Relaxed);
+            parts.push(
+                CompletedPart::builder()
+                    .set_e_tag(resp.e_tag)
+                    .part_number(part_number)
+                    .build(),
+            );
+            fill(value, &mut buf, 1).await?;
+            if buf.is_empty() {
+                return Ok(parts);
+            }
+        }
+        Err(anyhow!("Value exceeds the maximum of {MAX_MULTIPART_PARTS} parts"))
+    }
+
+    /// Returns the bucket name used for `container`
+    fn bucket(&self, container: &str) -> String {
+        format!("{}{container}", self.bucket_prefix)
     }
 }
 
+/// Reads from `value` into `buf` until it contains at least `n` bytes or
+/// `value` is exhausted
+async fn fill(value: &mut (dyn AsyncRead + Sync + Send + Unpin), buf: &mut Vec<u8>, n: usize) -> Result<()> {
+    let Some(missing) = n.checked_sub(buf.len()) else {
+        return Ok(());
+    };
+    let _ = (&mut *value).take(missing as u64).read_to_end(buf).await.context("Failed to read value")?;
+    Ok(())
+}
+
 #[async_trait]
 impl capability::Blobstore for S3Blobstore {
     #[instrument]
@@ -33,7 +255,7 @@ impl capability::Blobstore for S3Blobstore {
         let _ = self
             .client
             .create_bucket()
-            .bucket(name)
+            .bucket(self.bucket(name))
             .send()
             .await
             .context("Failed to create container")?;
@@ -48,7 +270,8 @@ impl capability::Blobstore for S3Blobstore {
             .send()
             .await
             .context("Failed to list containers")?;
-        Ok(resp.buckets().iter().any(|b| b.name() == Some(name.into())))
+        let bucket = self.bucket(name);
+        Ok(resp.buckets().iter().any(|b| b.name() == Some(bucket.as_str())))
     }
 
     #[instrument]
@@ -56,7 +279,7 @@ impl capability::Blobstore for S3Blobstore {
         let _ = self
             .client
             .delete_bucket()
-            .bucket(name)
+            .bucket(self.bucket(name))
             .send()
             .await
             .context("Failed to delete container")?;
@@ -71,7 +294,8 @@ impl capability::Blobstore for S3Blobstore {
             .send()
             .await
             .context("Failed to get container info")?;
-        let bucket = resp.buckets().iter().find(|b| b.name() == Some(name.into())).ok_or_else(|| anyhow!("Container not found"))?;
+        let bucket = self.bucket(name);
+        let bucket = resp.buckets().iter().find(|b| b.name() == Some(bucket.as_str())).ok_or_else(|| anyhow!("Container not found"))?;
         let created_at = bucket.creation_date().map(|d| d.secs()).unwrap_or(0).unsigned_abs();
         Ok(blobstore::container::ContainerMetadata {
             name: name.into(),
@@ -84,7 +308,7 @@ impl capability::Blobstore for S3Blobstore {
         let req = self
             .client
             .get_object()
-            .bucket(container)
+            .bucket(self.bucket(container))
             .key(name.clone())
             .range(
                 format!("bytes={}-{}", range.start(), range.end())
@@ -108,7 +332,7 @@ impl capability::Blobstore for S3Blobstore {
         let resp = self
             .client
             .get_object()
-            .bucket(container)
+            .bucket(self.bucket(container))
             .key(name)
             .send()
             .await;
@@ -123,17 +347,66 @@ impl capability::Blobstore for S3Blobstore {
 
     #[instrument(skip(value))]
     async fn write_data(&self, container: &str, name: String, mut value: Box<dyn AsyncRead + Sync + Send + Unpin>) -> Result<()> {
+        let bucket = self.bucket(container);
+        // Read one byte past the threshold to determine whether multipart upload is required
+        let threshold = usize::try_from(self.multipart_threshold).unwrap_or(usize::MAX).saturating_add(1);
         let mut data = Vec::new();
-        value.read_to_end(&mut data).await.context("Failed to read value")?;
-        let _ = self
+        fill(&mut *value, &mut data, threshold).await?;
+        if data.len() < threshold {
+            let len = data.len() as u64;
+            let _ = self
+                .client
+                .put_object()
+                .bucket(bucket)
+                .key(name)
+                .body(ByteStream::from(data))
+                .send()
+                .await
+                .context("Failed to write data")?;
+            self.counters.bytes_uploaded.fetch_add(len, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let upload_id = self
             .client
-            .put_object()
-            .bucket(container)
-            .key(name)
-            .body(ByteStream::from(data))
+            .create_multipart_upload()
+            .bucket(&bucket)
+            .key(&name)
             .send()
             .await
-            .context("Failed to write data")?;
+            .context("Failed to start multipart upload")?
+            .upload_id
+            .context("Multipart upload ID missing")?;
+        self.counters.multipart_started.fetch_add(1, Ordering::Relaxed);
+        let res = match self.upload_parts(&bucket, &name, &upload_id, data, &mut *value).await {
+            Ok(parts) => self
+                .client
+                .complete_multipart_upload()
+                .bucket(&bucket)
+                .key(&name)
+                .upload_id(&upload_id)
+                .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+                .send()
+                .await
+                .context("Failed to complete multipart upload"),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            self.counters.multipart_aborted.fetch_add(1, Ordering::Relaxed);
+            if let Err(abort_err) = self
+                .client
+                .abort_multipart_upload()
+                .bucket(&bucket)
+                .key(&name)
+                .upload_id(&upload_id)
+                .send()
+                .await
+            {
+                warn!(?abort_err, upload_id, "Failed to abort multipart upload");
+            }
+            return Err(err);
+        }
+        self.counters.multipart_completed.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
 
@@ -143,7 +416,7 @@ impl capability::Blobstore for S3Blobstore {
         let _ = self
             .client
             .delete_objects()
-            .bucket(container)
+            .bucket(self.bucket(container))
             .delete(
                 DeleteBuilder::default()
                     .set_objects(Some(object_ids))
@@ -160,7 +433,7 @@ impl capability::Blobstore for S3Blobstore {
         let resp = self
             .client
             .list_objects_v2()
-            .bucket(container)
+            .bucket(self.bucket(container))
             .send()
             .await
             .context("Failed to list objects")?;
@@ -173,7 +446,7 @@ impl capability::Blobstore for S3Blobstore {
         let resp = self
             .client
             .get_object()
-            .bucket(container)
+            .bucket(self.bucket(container))
             .key(name.clone())
             .send()
             .await
@@ -187,4 +460,128 @@ impl capability::Blobstore for S3Blobstore {
             created_at,
         })
     }
-}
\ No newline at end of file
+
+    #[instrument]
+    async fn copy_object(&self, src_container: &str, src_name: String, dest_container: &str, dest_name: String) -> Result<()> {
+        // S3 rejects copying an object onto itself unless its metadata changes
+        if src_container == dest_container && src_name == dest_name {
+            return Ok(());
+        }
+        let source = format!("{}/{}", self.bucket(src_container), encode_key(&src_name));
+        let _ = self
+            .client
+            .copy_object()
+            .copy_source(source)
+            .bucket(self.bucket(dest_container))
+            .key(dest_name)
+            .send()
+            .await
+            .context("Failed to copy object")?;
+        Ok(())
+    }
+
+    #[instrument]
+    async fn move_object(&self, src_container: &str, src_name: String, dest_container: &str, dest_name: String) -> Result<()> {
+        if src_container == dest_container && src_name == dest_name {
+            return Ok(());
+        }
+        self.copy_object(src_container, src_name.clone(), dest_container, dest_name).await?;
+        let _ = self
+            .client
+            .delete_object()
+            .bucket(self.bucket(src_container))
+            .key(src_name)
+            .send()
+            .await
+            .context("Failed to delete source object")?;
+        Ok(())
+    }
+}
+
+/// Percent-encodes an object key for use in the `x-amz-copy-source` header
+fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for b in key.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(char::from(b))
+            }
+            _ => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::Blobstore as _;
+
+    use futures::TryStreamExt;
+
+    /// Runs against an S3-compatible service, such as MinIO or LocalStack,
+    /// if `WASMTIME_TEST_S3_ENDPOINT` is set, e.g.:
+    ///
+    /// ```sh
+    /// docker run -p 9000:9000 minio/minio server /data
+    /// WASMTIME_TEST_S3_ENDPOINT=http://localhost:9000 cargo test -p wasmtime-wasi-cloud-core
+    /// ```
+    #[tokio::test]
+    async fn s3_compatible_roundtrip() -> Result<()> {
+        let Ok(endpoint_url) = std::env::var("WASMTIME_TEST_S3_ENDPOINT") else {
+            return Ok(());
+        };
+        let store = S3Blobstore::with_config(S3BlobstoreConfig {
+            endpoint_url: Some(endpoint_url),
+            region: Some("us-east-1".into()),
+            credentials: Some(S3Credentials::Static {
+                access_key_id: std::env::var("WASMTIME_TEST_S3_ACCESS_KEY_ID")
+                    .unwrap_or_else(|_| "minioadmin".into()),
+                secret_access_key: std::env::var("WASMTIME_TEST_S3_SECRET_ACCESS_KEY")
+                    .unwrap_or_else(|_| "minioadmin".into()),
+                session_token: None,
+            }),
+            force_path_style: true,
+            bucket_prefix: Some(format!("wasmtime-test-{}-", std::process::id())),
+            multipart_threshold: Some(MIN_MULTIPART_PART_SIZE),
+            multipart_part_size: Some(MIN_MULTIPART_PART_SIZE),
+        })
+        .await?;
+
+        store.create_container("roundtrip").await?;
+        assert!(store.container_exists("roundtrip").await?);
+        store
+            .write_data("roundtrip", "key".into(), Box::new(&b"hello world"[..]))
+            .await?;
+        let (mut data, _) = store.get_data("roundtrip", "key".into(), 6..=10).await?;
+        let mut buf = vec![];
+        data.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"world");
+
+        let large = vec![0x2a; 2 * MIN_MULTIPART_PART_SIZE as usize + 1];
+        store
+            .write_data("roundtrip", "large".into(), Box::new(std::io::Cursor::new(large.clone())))
+            .await?;
+        let progress = store.upload_progress();
+        assert_eq!(progress.parts_uploaded, 3);
+        assert_eq!(progress.multipart_completed, 1);
+        let (mut data, _) = store.get_data("roundtrip", "large".into(), 0..=large.len() as u64 - 1).await?;
+        let mut buf = vec![];
+        data.read_to_end(&mut buf).await?;
+        assert!(buf == large);
+        store.delete_objects("roundtrip", vec!["large".into()]).await?;
+        store
+            .copy_object("roundtrip", "key".into(), "roundtrip", "a b".into())
+            .await?;
+        store
+            .move_object("roundtrip", "a b".into(), "roundtrip", "moved".into())
+            .await?;
+        let mut names: Vec<_> = store.list_objects("roundtrip").await?.try_collect().await?;
+        names.sort();
+        assert_eq!(names, ["key", "moved"]);
+        store.clear_container("roundtrip").await?;
+        store.delete_container("roundtrip").await?;
+        Ok(())
+    }
+}
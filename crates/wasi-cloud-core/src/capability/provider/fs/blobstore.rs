@@ -0,0 +1,466 @@
+use crate::capability::{self, blobstore};
+
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, ensure, Context};
+use async_trait::async_trait;
+use futures::{stream, Stream};
+use tokio::fs::{self, File};
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::instrument;
+
+/// Name of the directory under the root used for in-flight writes
+const TMP_DIR: &str = ".tmp";
+
+/// Local filesystem [`Blobstore`](crate::capability::Blobstore) implementation.
+///
+/// Containers are directories directly under the root and objects are files
+/// within them. Object names may contain `/`, in which case the object is
+/// stored in a nested directory of the container.
+#[derive(Debug)]
+pub struct Blobstore {
+    root: PathBuf,
+    tmp_id: AtomicU64,
+}
+
+impl Blobstore {
+    /// Construct a new [Blobstore] rooted at `root`, creating the directory
+    /// if it does not exist yet
+    pub async fn new(root: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(root.join(TMP_DIR))
+            .await
+            .with_context(|| format!("failed to create `{}`", root.display()))?;
+        Ok(Self {
+            root,
+            tmp_id: AtomicU64::default(),
+        })
+    }
+
+    /// Root directory of this [Blobstore]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn container_path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        ensure!(!name.is_empty(), "container name must not be empty");
+        ensure!(
+            !name.starts_with('.') && !name.contains(['/', '\\']),
+            "invalid container name `{name}`"
+        );
+        Ok(self.root.join(name))
+    }
+
+    fn object_path(&self, container: &str, name: &str) -> anyhow::Result<PathBuf> {
+        let mut path = self.container_path(container)?;
+        ensure!(!name.is_empty(), "object name must not be empty");
+        for component in Path::new(name).components() {
+            match component {
+                Component::Normal(component) => path.push(component),
+                _ => bail!("invalid object name `{name}`"),
+            }
+        }
+        Ok(path)
+    }
+
+    async fn existing_container_path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        let path = self.container_path(name)?;
+        match fs::metadata(&path).await {
+            Ok(md) if md.is_dir() => Ok(path),
+            Ok(_) => bail!("container not found"),
+            Err(err) if err.kind() == ErrorKind::NotFound => bail!("container not found"),
+            Err(err) => Err(err).context("failed to lookup container"),
+        }
+    }
+
+    /// Returns a unique path for a temporary file on the same filesystem as
+    /// the containers
+    fn tmp_path(&self) -> PathBuf {
+        let id = self.tmp_id.fetch_add(1, Ordering::Relaxed);
+        self.root
+            .join(TMP_DIR)
+            .join(format!("{}-{id}", std::process::id()))
+    }
+
+    /// Returns the path of an existing object and the destination path,
+    /// creating any missing parent directories of the latter
+    async fn transfer_paths(
+        &self,
+        src_container: &str,
+        src_name: &str,
+        dest_container: &str,
+        dest_name: &str,
+    ) -> anyhow::Result<(PathBuf, PathBuf)> {
+        self.existing_container_path(src_container)
+            .await
+            .context("source container not found")?;
+        self.existing_container_path(dest_container)
+            .await
+            .context("destination container not found")?;
+        let src = self.object_path(src_container, src_name)?;
+        match fs::metadata(&src).await {
+            Ok(md) if md.is_file() => {}
+            Ok(_) => bail!("object not found"),
+            Err(err) if err.kind() == ErrorKind::NotFound => bail!("object not found"),
+            Err(err) => return Err(err).context("failed to lookup object"),
+        }
+        let dest = self.object_path(dest_container, dest_name)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("failed to create object directory")?;
+        }
+        Ok((src, dest))
+    }
+
+    /// Removes empty directories between `path` and the container directory
+    async fn prune_parents(&self, container: &Path, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(path) = dir.filter(|dir| *dir != container) {
+            if fs::remove_dir(path).await.is_err() {
+                break;
+            }
+            dir = path.parent();
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> anyhow::Result<u64> {
+    let time = time
+        .duration_since(UNIX_EPOCH)
+        .context("failed to compute duration since Unix epoch")?;
+    Ok(time.as_secs())
+}
+
+fn created_at(md: &std::fs::Metadata) -> anyhow::Result<u64> {
+    let time = md
+        .created()
+        .or_else(|_| md.modified())
+        .context("failed to get file creation time")?;
+    unix_secs(time)
+}
+
+#[async_trait]
+impl capability::Blobstore for Blobstore {
+    #[instrument]
+    async fn create_container(&self, name: &str) -> anyhow::Result<()> {
+        let path = self.container_path(name)?;
+        match fs::create_dir(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                bail!("container already exists")
+            }
+            Err(err) => Err(err).context("failed to create container directory"),
+        }
+    }
+
+    #[instrument]
+    async fn container_exists(&self, name: &str) -> anyhow::Result<bool> {
+        let path = self.container_path(name)?;
+        match fs::metadata(&path).await {
+            Ok(md) => Ok(md.is_dir()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err).context("failed to lookup container"),
+        }
+    }
+
+    #[instrument]
+    async fn delete_container(&self, name: &str) -> anyhow::Result<()> {
+        let path = self.container_path(name)?;
+        match fs::remove_dir_all(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).context("failed to remove container directory"),
+        }
+    }
+
+    #[instrument]
+    async fn container_info(
+        &self,
+        name: &str,
+    ) -> anyhow::Result<blobstore::container::ContainerMetadata> {
+        let path = self.existing_container_path(name).await?;
+        let md = fs::metadata(&path)
+            .await
+            .context("failed to get container metadata")?;
+        Ok(blobstore::container::ContainerMetadata {
+            name: name.into(),
+            created_at: created_at(&md)?,
+        })
+    }
+
+    #[instrument]
+    async fn get_data(
+        &self,
+        container: &str,
+        name: String,
+        range: RangeInclusive<u64>,
+    ) -> anyhow::Result<(Box<dyn AsyncRead + Sync + Send + Unpin>, u64)> {
+        self.existing_container_path(container).await?;
+        let path = self.object_path(container, &name)?;
+        let mut file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => bail!("object not found"),
+            Err(err) => return Err(err).context("failed to open object"),
+        };
+        let len = file
+            .metadata()
+            .await
+            .context("failed to get object metadata")?
+            .len();
+        let start = *range.start();
+        if len == 0 || start >= len || start > *range.end() {
+            return Ok((Box::new(io::empty()), 0));
+        }
+        let end = (*range.end()).min(len - 1);
+        file.seek(SeekFrom::Start(start))
+            .await
+            .context("failed to seek object")?;
+        let n = end - start + 1;
+        Ok((Box::new(file.take(n)), n))
+    }
+
+    #[instrument]
+    async fn has_object(&self, container: &str, name: String) -> anyhow::Result<bool> {
+        self.existing_container_path(container).await?;
+        let path = self.object_path(container, &name)?;
+        match fs::metadata(&path).await {
+            Ok(md) => Ok(md.is_file()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err).context("failed to lookup object"),
+        }
+    }
+
+    #[instrument(skip(value))]
+    async fn write_data(
+        &self,
+        container: &str,
+        name: String,
+        mut value: Box<dyn AsyncRead + Sync + Send + Unpin>,
+    ) -> anyhow::Result<()> {
+        self.existing_container_path(container).await?;
+        let path = self.object_path(container, &name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("failed to create object directory")?;
+        }
+        // Data is first streamed into a temporary file, which is then
+        // atomically moved into place, so that readers never observe partial writes
+        let tmp = self.tmp_path();
+        let res = async {
+            let mut file = File::create(&tmp)
+                .await
+                .context("failed to create temporary file")?;
+            io::copy(&mut value, &mut file)
+                .await
+                .context("failed to write value")?;
+            file.flush().await.context("failed to flush value")?;
+            fs::rename(&tmp, &path)
+                .await
+                .context("failed to move object into place")
+        }
+        .await;
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp).await;
+        }
+        res
+    }
+
+    #[instrument]
+    async fn delete_objects(&self, container: &str, names: Vec<String>) -> anyhow::Result<()> {
+        let root = self.existing_container_path(container).await?;
+        for name in names {
+            let path = self.object_path(container, &name)?;
+            match fs::remove_file(&path).await {
+                Ok(()) => self.prune_parents(&root, &path).await,
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => return Err(err).with_context(|| format!("failed to delete `{name}`")),
+            }
+        }
+        Ok(())
+    }
+
+    #[instrument]
+    async fn list_objects(
+        &self,
+        container: &str,
+    ) -> anyhow::Result<Box<dyn Stream<Item = anyhow::Result<String>> + Sync + Send + Unpin>> {
+        let root = self.existing_container_path(container).await?;
+        let mut names = vec![];
+        let mut dirs = vec![root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = fs::read_dir(&dir)
+                .await
+                .context("failed to read container directory")?;
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .context("failed to read directory entry")?
+            {
+                let path = entry.path();
+                let ty = entry.file_type().await.context("failed to get file type")?;
+                if ty.is_dir() {
+                    dirs.push(path);
+                } else if ty.is_file() {
+                    let name = path
+                        .strip_prefix(&root)
+                        .context("object path outside of container")?
+                        .components()
+                        .map(|c| c.as_os_str().to_str().context("non UTF-8 object name"))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    names.push(name.join("/"));
+                }
+            }
+        }
+        names.sort();
+        Ok(Box::new(stream::iter(names.into_iter().map(Ok))))
+    }
+
+    #[instrument]
+    async fn object_info(
+        &self,
+        container: &str,
+        name: String,
+    ) -> anyhow::Result<blobstore::container::ObjectMetadata> {
+        self.existing_container_path(container).await?;
+        let path = self.object_path(container, &name)?;
+        let md = match fs::metadata(&path).await {
+            Ok(md) if md.is_file() => md,
+            Ok(_) => bail!("object not found"),
+            Err(err) if err.kind() == ErrorKind::NotFound => bail!("object not found"),
+            Err(err) => return Err(err).context("failed to get object metadata"),
+        };
+        Ok(blobstore::container::ObjectMetadata {
+            name,
+            container: container.into(),
+            size: md.len(),
+            created_at: created_at(&md)?,
+        })
+    }
+
+    #[instrument]
+    async fn copy_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        let (src, dest) = self
+            .transfer_paths(src_container, &src_name, dest_container, &dest_name)
+            .await?;
+        if src == dest {
+            return Ok(());
+        }
+        let tmp = self.tmp_path();
+        let res = async {
+            fs::copy(&src, &tmp)
+                .await
+                .context("failed to copy object")?;
+            fs::rename(&tmp, &dest)
+                .await
+                .context("failed to move object into place")
+        }
+        .await;
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp).await;
+        }
+        res
+    }
+
+    #[instrument]
+    async fn move_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        let (src, dest) = self
+            .transfer_paths(src_container, &src_name, dest_container, &dest_name)
+            .await?;
+        if src == dest {
+            return Ok(());
+        }
+        fs::rename(&src, &dest)
+            .await
+            .context("failed to move object")?;
+        let root = self.container_path(src_container)?;
+        self.prune_parents(&root, &src).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::Blobstore as _;
+
+    use futures::TryStreamExt;
+
+    #[tokio::test]
+    async fn roundtrip() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = Blobstore::new(dir.path()).await?;
+
+        store.create_container("test").await?;
+        assert!(store.container_exists("test").await?);
+        assert!(store.create_container("test").await.is_err());
+        assert!(store.create_container("../escape").await.is_err());
+
+        store
+            .write_data("test", "a/b".into(), Box::new(&b"hello world"[..]))
+            .await?;
+        store
+            .write_data("test", "c".into(), Box::new(&b""[..]))
+            .await?;
+        assert!(store.has_object("test", "a/b".into()).await?);
+        assert!(!store.has_object("test", "a".into()).await?);
+        assert!(store
+            .write_data("test", "../x".into(), Box::new(&b""[..]))
+            .await
+            .is_err());
+
+        let (mut data, n) = store.get_data("test", "a/b".into(), 6..=100).await?;
+        let mut buf = vec![];
+        data.read_to_end(&mut buf).await?;
+        assert_eq!(n, 5);
+        assert_eq!(buf, b"world");
+
+        let info = store.object_info("test", "a/b".into()).await?;
+        assert_eq!(info.size, 11);
+
+        let names: Vec<_> = store.list_objects("test").await?.try_collect().await?;
+        assert_eq!(names, ["a/b", "c"]);
+
+        store.create_container("other").await?;
+        store
+            .copy_object("test", "a/b".into(), "other", "d".into())
+            .await?;
+        store
+            .move_object("test", "a/b".into(), "test", "e".into())
+            .await?;
+        let names: Vec<_> = store.list_objects("test").await?.try_collect().await?;
+        assert_eq!(names, ["c", "e"]);
+        assert_eq!(store.object_info("other", "d".into()).await?.size, 11);
+        assert!(store
+            .move_object("test", "a/b".into(), "other", "f".into())
+            .await
+            .is_err());
+
+        store.clear_container("test").await?;
+        let names: Vec<String> = store.list_objects("test").await?.try_collect().await?;
+        assert!(names.is_empty());
+
+        store.delete_container("test").await?;
+        assert!(!store.container_exists("test").await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,558 @@
+use crate::capability::provider::list_keys_page;
+use crate::capability::{KeyValueAtomic, KeyValueEventual};
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context};
+use async_trait::async_trait;
+use serde_derive::{Deserialize, Serialize};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tracing::{instrument, warn};
+
+/// Name of the log file under the root
+const LOG_FILE: &str = "keyvalue.log";
+
+/// Name of the file a compacted log is written to before replacing [LOG_FILE]
+const COMPACT_FILE: &str = "keyvalue.log.compact";
+
+/// Minimum number of obsolete records in the log before it is compacted
+const COMPACT_THRESHOLD: usize = 1024;
+
+/// Size of the record header, consisting of a `u32` payload length and a
+/// `u64` payload checksum
+const HEADER_LEN: usize = 12;
+
+/// Log record
+#[derive(Debug, Deserialize, Serialize)]
+enum Record {
+    /// Set `key` in `bucket` to a byte blob
+    Blob {
+        bucket: String,
+        key: String,
+        value: Vec<u8>,
+    },
+    /// Set `key` in `bucket` to an atomic number
+    Atomic {
+        bucket: String,
+        key: String,
+        value: u64,
+    },
+    /// Delete `key` from `bucket`
+    Delete { bucket: String, key: String },
+    /// Create `bucket`, which is written by compaction, so that buckets
+    /// outlive their entries
+    Bucket { bucket: String },
+}
+
+#[derive(Clone, Debug)]
+enum Entry {
+    Atomic(u64),
+    Blob(Vec<u8>),
+}
+
+type Bucket = BTreeMap<String, Entry>;
+
+/// 64-bit FNV-1a hash used to detect torn and corrupted records
+fn checksum(buf: &[u8]) -> u64 {
+    buf.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn encode(record: &Record) -> anyhow::Result<Vec<u8>> {
+    let payload = rmp_serde::to_vec(record).context("failed to encode record")?;
+    let len = u32::try_from(payload.len()).context("record is too large")?;
+    let mut buf = Vec::with_capacity(HEADER_LEN + payload.len());
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(&checksum(&payload).to_le_bytes());
+    buf.extend_from_slice(&payload);
+    Ok(buf)
+}
+
+/// Decode the record at the start of `buf` returning it along with its
+/// encoded length or `None` if `buf` does not start with a complete record
+fn decode(buf: &[u8]) -> Option<(Record, usize)> {
+    let len = u32::from_le_bytes(buf.get(..4)?.try_into().ok()?);
+    let len = usize::try_from(len).ok()?;
+    let sum = u64::from_le_bytes(buf.get(4..HEADER_LEN)?.try_into().ok()?);
+    let payload = buf.get(HEADER_LEN..)?.get(..len)?;
+    if checksum(payload) != sum {
+        return None;
+    }
+    let record = rmp_serde::from_slice(payload).ok()?;
+    Some((record, HEADER_LEN + len))
+}
+
+#[derive(Debug)]
+struct State {
+    buckets: HashMap<String, Bucket>,
+    log: File,
+    /// Length of the log up to the end of the last complete record
+    len: u64,
+    /// Whether a failed append could not be rolled back, in which case the log
+    /// may end with a torn record and must not be appended to until it is
+    /// compacted
+    poisoned: bool,
+    /// Number of records in the log, which were superseded by later ones
+    obsolete: usize,
+}
+
+impl State {
+    fn apply(&mut self, record: Record) {
+        let replaced = match record {
+            Record::Blob { bucket, key, value } => self
+                .buckets
+                .entry(bucket)
+                .or_default()
+                .insert(key, Entry::Blob(value))
+                .is_some(),
+            Record::Atomic { bucket, key, value } => self
+                .buckets
+                .entry(bucket)
+                .or_default()
+                .insert(key, Entry::Atomic(value))
+                .is_some(),
+            Record::Delete { bucket, key } => {
+                // Both the deleted entry and the deletion record itself are obsolete
+                self.obsolete += 1;
+                self.buckets
+                    .get_mut(&bucket)
+                    .and_then(|bucket| bucket.remove(&key))
+                    .is_some()
+            }
+            Record::Bucket { bucket } => {
+                self.buckets.entry(bucket).or_default();
+                false
+            }
+        };
+        if replaced {
+            self.obsolete += 1;
+        }
+    }
+
+    fn bucket(&self, bucket: &str) -> anyhow::Result<&Bucket> {
+        self.buckets.get(bucket).context("bucket not found")
+    }
+
+    fn live(&self) -> usize {
+        self.buckets.values().map(BTreeMap::len).sum()
+    }
+}
+
+/// Persistent [`KeyValueEventual`] and [`KeyValueAtomic`] implementation
+/// backed by an append-only log stored under a directory.
+///
+/// Every mutation is appended to the log and synced to disk before it is
+/// applied and acknowledged, so an acknowledged `increment` or
+/// `compare-and-swap` survives a crash. Incomplete records at the end of the
+/// log, left behind by a crash mid-write, are discarded on open, while an
+/// invalid record followed by valid ones fails the open, since discarding it
+/// would lose acknowledged mutations. The log is compacted once it is
+/// dominated by obsolete records.
+#[derive(Debug)]
+pub struct KeyValue {
+    root: PathBuf,
+    state: Mutex<State>,
+}
+
+impl KeyValue {
+    /// Open a [KeyValue] stored under `root`, creating the directory if it
+    /// does not exist yet and replaying an existing log
+    #[instrument(skip(root), fields(root = %root.as_ref().display()))]
+    pub async fn new(root: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)
+            .await
+            .with_context(|| format!("failed to create `{}`", root.display()))?;
+        let path = root.join(LOG_FILE);
+        let buf = match fs::read(&path).await {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read `{}`", path.display()))
+            }
+        };
+        let mut state = State {
+            buckets: HashMap::default(),
+            log: OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+                .with_context(|| format!("failed to open `{}`", path.display()))?,
+            len: 0,
+            poisoned: false,
+            obsolete: 0,
+        };
+        let mut pos = 0;
+        while let Some((record, n)) = buf.get(pos..).and_then(decode) {
+            state.apply(record);
+            pos += n;
+        }
+        state.len = pos.try_into().context("log offset does not fit in `u64`")?;
+        if (pos + 1..buf.len()).any(|pos| decode(&buf[pos..]).is_some()) {
+            bail!(
+                "log `{}` is corrupted at offset {pos}, which is followed by valid records",
+                path.display()
+            )
+        }
+        if pos < buf.len() {
+            warn!(
+                offset = pos,
+                "discarding incomplete records at the end of the log"
+            );
+            state
+                .log
+                .set_len(state.len)
+                .await
+                .context("failed to truncate log")?;
+            state.log.sync_all().await.context("failed to sync log")?;
+        }
+        Ok(Self {
+            root,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Root directory of this [KeyValue]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Rewrite the log to contain only the current entries
+    #[instrument(skip(self))]
+    pub async fn compact(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        self.compact_locked(&mut state).await
+    }
+
+    async fn compact_locked(&self, state: &mut State) -> anyhow::Result<()> {
+        let mut buf = vec![];
+        for (bucket, entries) in &state.buckets {
+            buf.extend(encode(&Record::Bucket {
+                bucket: bucket.clone(),
+            })?);
+            for (key, entry) in entries {
+                let (bucket, key) = (bucket.clone(), key.clone());
+                let record = match entry {
+                    Entry::Atomic(value) => Record::Atomic {
+                        bucket,
+                        key,
+                        value: *value,
+                    },
+                    Entry::Blob(value) => Record::Blob {
+                        bucket,
+                        key,
+                        value: value.clone(),
+                    },
+                };
+                buf.extend(encode(&record)?);
+            }
+        }
+        let tmp = self.root.join(COMPACT_FILE);
+        let path = self.root.join(LOG_FILE);
+        let len = buf
+            .len()
+            .try_into()
+            .context("log size does not fit in `u64`")?;
+        let mut file = File::create(&tmp)
+            .await
+            .with_context(|| format!("failed to create `{}`", tmp.display()))?;
+        file.write_all(&buf)
+            .await
+            .context("failed to write compacted log")?;
+        file.sync_all()
+            .await
+            .context("failed to sync compacted log")?;
+        // Open the compacted log for appending before replacing the current
+        // one, so that `state.log` is updated as soon as the rename succeeds
+        let log = OpenOptions::new()
+            .append(true)
+            .open(&tmp)
+            .await
+            .with_context(|| format!("failed to open `{}`", tmp.display()))?;
+        fs::rename(&tmp, &path)
+            .await
+            .context("failed to replace log")?;
+        state.log = log;
+        state.len = len;
+        state.poisoned = false;
+        state.obsolete = 0;
+        // Persist the rename itself
+        File::open(&self.root)
+            .await
+            .context("failed to open root directory")?
+            .sync_all()
+            .await
+            .context("failed to sync root directory")
+    }
+
+    /// Durably append `record` to the log and apply it
+    async fn commit(&self, state: &mut State, record: Record) -> anyhow::Result<()> {
+        ensure!(
+            !state.poisoned,
+            "log is in an inconsistent state after a failed append"
+        );
+        let buf = encode(&record)?;
+        let len = u64::try_from(buf.len()).context("record size does not fit in `u64`")?;
+        if let Err(err) = append(&mut state.log, &buf).await {
+            // Drop the partially written record, so that later records are
+            // not appended after it
+            if let Err(err) = state.log.set_len(state.len).await {
+                warn!(?err, "failed to truncate log");
+                state.poisoned = true;
+            }
+            return Err(err);
+        }
+        state.len += len;
+        state.apply(record);
+        if state.obsolete >= COMPACT_THRESHOLD && state.obsolete > state.live() {
+            if let Err(err) = self.compact_locked(state).await {
+                warn!(?err, "failed to compact log");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Append `buf` to `log` and sync it to disk
+async fn append(log: &mut File, buf: &[u8]) -> anyhow::Result<()> {
+    log.write_all(buf)
+        .await
+        .context("failed to append to log")?;
+    log.sync_data().await.context("failed to sync log")
+}
+
+#[async_trait]
+impl KeyValueAtomic for KeyValue {
+    #[instrument(skip(self))]
+    async fn increment(&self, bucket: &str, key: String, delta: u64) -> anyhow::Result<u64> {
+        let mut state = self.state.lock().await;
+        let value = match state.buckets.get(bucket).and_then(|b| b.get(&key)) {
+            None => delta,
+            Some(Entry::Atomic(value)) => value.wrapping_add(delta),
+            Some(Entry::Blob(_)) => bail!("invalid entry type"),
+        };
+        self.commit(
+            &mut state,
+            Record::Atomic {
+                bucket: bucket.into(),
+                key,
+                value,
+            },
+        )
+        .await?;
+        Ok(value)
+    }
+
+    #[instrument(skip(self))]
+    async fn compare_and_swap(
+        &self,
+        bucket: &str,
+        key: String,
+        old: u64,
+        new: u64,
+    ) -> anyhow::Result<bool> {
+        let mut state = self.state.lock().await;
+        match state.bucket(bucket)?.get(&key).context("key not found")? {
+            Entry::Atomic(value) if *value == old => {}
+            Entry::Atomic(_) => return Ok(false),
+            Entry::Blob(_) => bail!("invalid entry type"),
+        }
+        self.commit(
+            &mut state,
+            Record::Atomic {
+                bucket: bucket.into(),
+                key,
+                value: new,
+            },
+        )
+        .await?;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl KeyValueEventual for KeyValue {
+    #[instrument(skip(self))]
+    async fn get(
+        &self,
+        bucket: &str,
+        key: String,
+    ) -> anyhow::Result<Option<(Box<dyn AsyncRead + Sync + Send + Unpin>, u64)>> {
+        let state = self.state.lock().await;
+        let value = match state.bucket(bucket)?.get(&key) {
+            None => return Ok(None),
+            Some(Entry::Atomic(value)) => value.to_string().into_bytes(),
+            Some(Entry::Blob(value)) => value.clone(),
+        };
+        let size = value
+            .len()
+            .try_into()
+            .context("size does not fit in `u64`")?;
+        Ok(Some((Box::new(Cursor::new(value)), size)))
+    }
+
+    #[instrument(skip(self, value))]
+    async fn set(
+        &self,
+        bucket: &str,
+        key: String,
+        mut value: Box<dyn AsyncRead + Sync + Send + Unpin>,
+    ) -> anyhow::Result<()> {
+        let mut buf = vec![];
+        value
+            .read_to_end(&mut buf)
+            .await
+            .context("failed to read value")?;
+        let mut state = self.state.lock().await;
+        self.commit(
+            &mut state,
+            Record::Blob {
+                bucket: bucket.into(),
+                key,
+                value: buf,
+            },
+        )
+        .await
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, bucket: &str, key: String) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        ensure!(state.bucket(bucket)?.contains_key(&key), "key not found");
+        self.commit(
+            &mut state,
+            Record::Delete {
+                bucket: bucket.into(),
+                key,
+            },
+        )
+        .await
+    }
+
+    #[instrument(skip(self))]
+    async fn exists(&self, bucket: &str, key: String) -> anyhow::Result<bool> {
+        let state = self.state.lock().await;
+        Ok(state.bucket(bucket)?.contains_key(&key))
+    }
+
+    #[instrument(skip(self))]
+    async fn list_keys(
+        &self,
+        bucket: &str,
+        cursor: Option<String>,
+    ) -> anyhow::Result<(Vec<String>, Option<String>)> {
+        let state = self.state.lock().await;
+        Ok(list_keys_page(state.bucket(bucket)?, cursor, |_| false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(kv: &KeyValue, bucket: &str, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some((mut value, _)) = kv.get(bucket, key.into()).await? else {
+            return Ok(None);
+        };
+        let mut buf = vec![];
+        value.read_to_end(&mut buf).await?;
+        Ok(Some(buf))
+    }
+
+    #[tokio::test]
+    async fn persistence() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let kv = KeyValue::new(dir.path()).await?;
+        assert!(kv.exists("bucket", "foo".into()).await.is_err());
+        kv.set("bucket", "foo".into(), Box::new(&b"bar"[..]))
+            .await?;
+        assert_eq!(kv.increment("bucket", "n".into(), 2).await?, 2);
+        assert_eq!(kv.increment("bucket", "n".into(), 3).await?, 5);
+        assert!(!kv.compare_and_swap("bucket", "n".into(), 4, 10).await?);
+        assert!(kv.compare_and_swap("bucket", "n".into(), 5, 10).await?);
+        assert!(kv.increment("bucket", "foo".into(), 1).await.is_err());
+        kv.set("bucket", "tmp".into(), Box::new(&b"tmp"[..]))
+            .await?;
+        kv.delete("bucket", "tmp".into()).await?;
+        drop(kv);
+
+        // Simulate a crash in the middle of appending a record
+        let path = dir.path().join(LOG_FILE);
+        let mut log = OpenOptions::new().append(true).open(&path).await?;
+        let torn = encode(&Record::Atomic {
+            bucket: "bucket".into(),
+            key: "n".into(),
+            value: 42,
+        })?;
+        log.write_all(&torn[..torn.len() - 1]).await?;
+        drop(log);
+
+        let kv = KeyValue::new(dir.path()).await?;
+        assert_eq!(
+            get(&kv, "bucket", "foo").await?.as_deref(),
+            Some(&b"bar"[..])
+        );
+        assert_eq!(get(&kv, "bucket", "n").await?.as_deref(), Some(&b"10"[..]));
+        assert_eq!(get(&kv, "bucket", "tmp").await?, None);
+        assert_eq!(kv.increment("bucket", "n".into(), 1).await?, 11);
+
+        kv.compact().await?;
+        assert_eq!(kv.increment("bucket", "n".into(), 1).await?, 12);
+        drop(kv);
+        let kv = KeyValue::new(dir.path()).await?;
+        assert_eq!(get(&kv, "bucket", "n").await?.as_deref(), Some(&b"12"[..]));
+        assert!(kv.exists("bucket", "foo".into()).await?);
+        assert!(!kv.exists("bucket", "tmp".into()).await?);
+        assert_eq!(
+            kv.list_keys("bucket", None).await?,
+            (vec!["foo".into(), "n".into()], None)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn empty_bucket() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let kv = KeyValue::new(dir.path()).await?;
+        kv.set("empty", "foo".into(), Box::new(&b"bar"[..])).await?;
+        kv.delete("empty", "foo".into()).await?;
+        drop(kv);
+
+        // Emptied buckets survive both a restart and a compaction
+        let kv = KeyValue::new(dir.path()).await?;
+        assert!(!kv.exists("empty", "foo".into()).await?);
+        kv.compact().await?;
+        drop(kv);
+        let kv = KeyValue::new(dir.path()).await?;
+        assert!(!kv.exists("empty", "foo".into()).await?);
+        assert_eq!(get(&kv, "empty", "foo").await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn corruption() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let kv = KeyValue::new(dir.path()).await?;
+        assert_eq!(kv.increment("bucket", "n".into(), 1).await?, 1);
+        assert_eq!(kv.increment("bucket", "n".into(), 1).await?, 2);
+        drop(kv);
+
+        // Corrupt the first record, which is followed by a valid one
+        let path = dir.path().join(LOG_FILE);
+        let mut buf = fs::read(&path).await?;
+        buf[HEADER_LEN] ^= 0xff;
+        fs::write(&path, &buf).await?;
+        assert!(KeyValue::new(dir.path()).await.is_err());
+        assert_eq!(fs::read(&path).await?, buf);
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+mod blobstore;
+mod keyvalue;
+
+pub use blobstore::Blobstore;
+pub use keyvalue::KeyValue;
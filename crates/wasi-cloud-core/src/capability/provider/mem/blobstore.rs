@@ -260,4 +260,59 @@ impl capability::Blobstore for Blobstore {
             created_at: created_at.as_secs(),
         })
     }
+
+    #[instrument]
+    async fn copy_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        let store = self.0.read().await;
+        let src = store
+            .get(src_container)
+            .context("source container not found")?;
+        let dest = store
+            .get(dest_container)
+            .context("destination container not found")?;
+        let data = {
+            let Container { ref objects, .. } = *src.read().await;
+            let Object { data, .. } = objects.get(&src_name).context("object not found")?;
+            data.clone()
+        };
+        let Container {
+            ref mut objects, ..
+        } = *dest.write().await;
+        objects.insert(dest_name, data.into());
+        Ok(())
+    }
+
+    #[instrument]
+    async fn move_object(
+        &self,
+        src_container: &str,
+        src_name: String,
+        dest_container: &str,
+        dest_name: String,
+    ) -> anyhow::Result<()> {
+        let store = self.0.read().await;
+        let src = store
+            .get(src_container)
+            .context("source container not found")?;
+        let dest = store
+            .get(dest_container)
+            .context("destination container not found")?;
+        // Containers cannot be removed while `store` is locked, so the object
+        // can be taken out of the source and inserted into the destination
+        // without holding both container locks at once
+        let object = src
+            .write()
+            .await
+            .objects
+            .remove(&src_name)
+            .context("object not found")?;
+        dest.write().await.objects.insert(dest_name, object);
+        Ok(())
+    }
 }
@@ -1,10 +1,14 @@
+use crate::capability::provider::list_keys_page;
 use crate::capability::{KeyValueAtomic, KeyValueEventual};
 
 use core::sync::atomic::AtomicU64;
+use core::time::Duration;
 
-use std::collections::{hash_map, BTreeMap, HashMap};
+use std::collections::{btree_map, hash_map, BTreeMap, HashMap};
 use std::io::Cursor;
 use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
 
 use anyhow::{bail, Context};
 use async_trait::async_trait;
@@ -23,21 +27,121 @@ pub enum Entry {
 
 type Bucket = HashMap<String, Entry>;
 
+/// Bucket entries ordered by key, so that keys can be listed from a cursor
+type Entries = BTreeMap<String, Entry>;
+
+/// Expiry instants of keys keyed by bucket name
+type Expiries = HashMap<String, HashMap<String, Instant>>;
+
 /// In-memory [`KeyValueEventual`] and [`KeyValueAtomic`] implementation
+///
+/// Keys set with a TTL are expired lazily, i.e. they are removed once they are
+/// accessed after their expiry and are omitted from key listings.
 #[derive(Debug)]
-pub struct KeyValue(RwLock<HashMap<String, RwLock<Bucket>>>);
+pub struct KeyValue {
+    buckets: RwLock<HashMap<String, RwLock<Entries>>>,
+    expiries: Mutex<Expiries>,
+}
+
+impl KeyValue {
+    fn expiries(&self) -> MutexGuard<'_, Expiries> {
+        self.expiries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn is_expired(&self, bucket: &str, key: &str, now: Instant) -> bool {
+        self.expiries()
+            .get(bucket)
+            .and_then(|expiries| expiries.get(key))
+            .is_some_and(|expiry| *expiry <= now)
+    }
+
+    /// Set the expiry of `key`, must be called with the bucket write lock held
+    fn set_expiry(&self, bucket: &str, key: &str, expiry: Option<Instant>) {
+        let mut expiries = self.expiries();
+        if let Some(expiry) = expiry {
+            expiries
+                .entry(bucket.into())
+                .or_default()
+                .insert(key.into(), expiry);
+        } else if let Some(bucket) = expiries.get_mut(bucket) {
+            bucket.remove(key);
+        }
+    }
+
+    /// Remove `key` from `bucket` if it has expired
+    async fn expire(&self, bucket: &str, key: &str) {
+        if !self.is_expired(bucket, key, Instant::now()) {
+            return;
+        }
+        let kv = self.buckets.read().await;
+        let Some(entries) = kv.get(bucket) else {
+            return;
+        };
+        let mut entries = entries.write().await;
+        // The key may have been set again before the lock was acquired
+        if self.is_expired(bucket, key, Instant::now()) {
+            entries.remove(key);
+            self.set_expiry(bucket, key, None);
+        }
+    }
+
+    async fn insert(
+        &self,
+        bucket: &str,
+        key: String,
+        mut value: Box<dyn tokio::io::AsyncRead + Sync + Send + Unpin>,
+        expiry: Option<Instant>,
+    ) -> anyhow::Result<()> {
+        let mut buf = vec![];
+        value
+            .read_to_end(&mut buf)
+            .await
+            .context("failed to read value")?;
+        let mut kv = self.buckets.write().await;
+        let mut entries = kv.entry(bucket.into()).or_default().write().await;
+        self.set_expiry(bucket, &key, expiry);
+        entries.insert(key, Entry::Blob(buf));
+        Ok(())
+    }
+
+    /// Returns the buckets with all expired keys removed
+    fn into_buckets(self) -> impl Iterator<Item = (String, Bucket)> {
+        let now = Instant::now();
+        let mut expiries = self
+            .expiries
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.buckets
+            .into_inner()
+            .into_iter()
+            .map(move |(name, bucket)| {
+                let mut bucket = bucket.into_inner();
+                for (key, expiry) in expiries.remove(&name).unwrap_or_default() {
+                    if expiry <= now {
+                        bucket.remove(&key);
+                    }
+                }
+                (name, bucket.into_iter().collect())
+            })
+    }
+}
 
 impl FromIterator<(String, RwLock<Bucket>)> for KeyValue {
     fn from_iter<T: IntoIterator<Item = (String, RwLock<Bucket>)>>(iter: T) -> Self {
-        Self(RwLock::new(iter.into_iter().collect()))
+        iter.into_iter().map(|(k, v)| (k, v.into_inner())).collect()
     }
 }
 
 impl FromIterator<(String, Bucket)> for KeyValue {
     fn from_iter<T: IntoIterator<Item = (String, Bucket)>>(iter: T) -> Self {
-        Self(RwLock::new(
-            iter.into_iter().map(|(k, v)| (k, RwLock::new(v))).collect(),
-        ))
+        let buckets = iter
+            .into_iter()
+            .map(|(k, v)| (k, RwLock::new(v.into_iter().collect())))
+            .collect();
+        Self {
+            buckets: RwLock::new(buckets),
+            expiries: Mutex::default(),
+        }
     }
 }
 
@@ -55,20 +159,14 @@ impl From<HashMap<String, RwLock<Bucket>>> for KeyValue {
 
 #[allow(clippy::implicit_hasher)]
 impl From<KeyValue> for HashMap<String, Bucket> {
-    fn from(KeyValue(kv): KeyValue) -> Self {
-        kv.into_inner()
-            .into_iter()
-            .map(|(k, v)| (k, v.into_inner()))
-            .collect()
+    fn from(kv: KeyValue) -> Self {
+        kv.into_buckets().collect()
     }
 }
 
 impl From<KeyValue> for BTreeMap<String, Bucket> {
-    fn from(KeyValue(kv): KeyValue) -> Self {
-        kv.into_inner()
-            .into_iter()
-            .map(|(k, v)| (k, v.into_inner()))
-            .collect()
+    fn from(kv: KeyValue) -> Self {
+        kv.into_buckets().collect()
     }
 }
 
@@ -84,7 +182,8 @@ impl IntoIterator for KeyValue {
 #[async_trait]
 impl KeyValueAtomic for KeyValue {
     async fn increment(&self, bucket: &str, key: String, delta: u64) -> anyhow::Result<u64> {
-        let kv = self.0.read().await;
+        self.expire(bucket, &key).await;
+        let kv = self.buckets.read().await;
         let bucket = kv.get(bucket).context("bucket not found")?;
         if let Some(entry) = bucket.read().await.get(&key) {
             match entry {
@@ -98,11 +197,11 @@ impl KeyValueAtomic for KeyValue {
         }
         let mut bucket = bucket.write().await;
         match bucket.entry(key) {
-            hash_map::Entry::Vacant(entry) => {
+            btree_map::Entry::Vacant(entry) => {
                 entry.insert(Entry::Atomic(AtomicU64::new(delta)));
                 Ok(delta)
             }
-            hash_map::Entry::Occupied(entry) => match entry.get() {
+            btree_map::Entry::Occupied(entry) => match entry.get() {
                 Entry::Atomic(value) => Ok(value
                     .fetch_add(delta, Ordering::Relaxed)
                     .wrapping_add(delta)),
@@ -118,7 +217,8 @@ impl KeyValueAtomic for KeyValue {
         old: u64,
         new: u64,
     ) -> anyhow::Result<bool> {
-        let kv = self.0.read().await;
+        self.expire(bucket, &key).await;
+        let kv = self.buckets.read().await;
         let bucket = kv.get(bucket).context("bucket not found")?.read().await;
         match bucket.get(&key).context("key not found")? {
             Entry::Atomic(value) => Ok(value
@@ -138,7 +238,8 @@ impl KeyValueEventual for KeyValue {
         bucket: &str,
         key: String,
     ) -> anyhow::Result<Option<(Box<dyn tokio::io::AsyncRead + Sync + Send + Unpin>, u64)>> {
-        let kv = self.0.read().await;
+        self.expire(bucket, &key).await;
+        let kv = self.buckets.read().await;
         let bucket = kv.get(bucket).context("bucket not found")?.read().await;
         let value = match bucket.get(&key) {
             None => return Ok(None),
@@ -157,31 +258,124 @@ impl KeyValueEventual for KeyValue {
         &self,
         bucket: &str,
         key: String,
-        mut value: Box<dyn tokio::io::AsyncRead + Sync + Send + Unpin>,
+        value: Box<dyn tokio::io::AsyncRead + Sync + Send + Unpin>,
     ) -> anyhow::Result<()> {
-        let mut buf = vec![];
-        value
-            .read_to_end(&mut buf)
-            .await
-            .context("failed to read value")?;
-        let mut kv = self.0.write().await;
-        let mut bucket = kv.entry(bucket.into()).or_default().write().await;
-        bucket.insert(key, Entry::Blob(buf));
-        Ok(())
+        self.insert(bucket, key, value, None).await
     }
 
     #[instrument]
     async fn delete(&self, bucket: &str, key: String) -> anyhow::Result<()> {
-        let kv = self.0.read().await;
-        let bucket = kv.get(bucket).context("bucket not found")?;
-        bucket.write().await.remove(&key).context("key not found")?;
+        self.expire(bucket, &key).await;
+        let kv = self.buckets.read().await;
+        let mut entries = kv.get(bucket).context("bucket not found")?.write().await;
+        entries.remove(&key).context("key not found")?;
+        self.set_expiry(bucket, &key, None);
         Ok(())
     }
 
     #[instrument]
     async fn exists(&self, bucket: &str, key: String) -> anyhow::Result<bool> {
-        let kv = self.0.read().await;
+        self.expire(bucket, &key).await;
+        let kv = self.buckets.read().await;
         let bucket = kv.get(bucket).context("bucket not found")?.read().await;
         Ok(bucket.contains_key(&key))
     }
+
+    #[instrument]
+    async fn list_keys(
+        &self,
+        bucket: &str,
+        cursor: Option<String>,
+    ) -> anyhow::Result<(Vec<String>, Option<String>)> {
+        let kv = self.buckets.read().await;
+        let entries = kv.get(bucket).context("bucket not found")?.read().await;
+        let now = Instant::now();
+        let expiries = self.expiries();
+        let expiries = expiries.get(bucket);
+        Ok(list_keys_page(&entries, cursor, |key| {
+            expiries
+                .and_then(|expiries| expiries.get(key))
+                .is_some_and(|expiry| *expiry <= now)
+        }))
+    }
+
+    #[instrument(skip(value))]
+    async fn set_with_ttl(
+        &self,
+        bucket: &str,
+        key: String,
+        value: Box<dyn tokio::io::AsyncRead + Sync + Send + Unpin>,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        let expiry = Instant::now()
+            .checked_add(ttl)
+            .context("TTL is too large")?;
+        self.insert(bucket, key, value, Some(expiry)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::provider::LIST_KEYS_PAGE_SIZE;
+
+    async fn get(kv: &KeyValue, bucket: &str, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some((mut value, _)) = kv.get(bucket, key.into()).await? else {
+            return Ok(None);
+        };
+        let mut buf = vec![];
+        value.read_to_end(&mut buf).await?;
+        Ok(Some(buf))
+    }
+
+    #[tokio::test]
+    async fn list_keys_and_ttl() -> anyhow::Result<()> {
+        let kv = KeyValue::from(HashMap::from([("bucket".into(), HashMap::default())]));
+        for i in 0..=LIST_KEYS_PAGE_SIZE {
+            kv.set("bucket", format!("{i:04}"), Box::new(&b"value"[..]))
+                .await?;
+        }
+        let (keys, cursor) = kv.list_keys("bucket", None).await?;
+        assert_eq!(keys.len(), LIST_KEYS_PAGE_SIZE);
+        assert_eq!(keys[0], "0000");
+        assert_eq!(cursor.as_deref(), keys.last().map(String::as_str));
+
+        // Keys expiring between pages do not shift the following pages
+        kv.set_with_ttl("bucket", "0000".into(), Box::new(&b"a"[..]), Duration::ZERO)
+            .await?;
+        let (keys, cursor) = kv.list_keys("bucket", cursor).await?;
+        assert_eq!(keys, [format!("{LIST_KEYS_PAGE_SIZE:04}")]);
+        assert_eq!(cursor, None);
+
+        kv.set_with_ttl(
+            "bucket",
+            "0001".into(),
+            Box::new(&b"b"[..]),
+            Duration::from_secs(3600),
+        )
+        .await?;
+        let (keys, _) = kv.list_keys("bucket", None).await?;
+        assert_eq!(keys[0], "0001");
+        assert_eq!(get(&kv, "bucket", "0000").await?, None);
+        assert!(!kv.exists("bucket", "0000".into()).await?);
+        assert_eq!(
+            get(&kv, "bucket", "0001").await?.as_deref(),
+            Some(&b"b"[..])
+        );
+
+        // Setting a value without a TTL clears the expiry
+        kv.set_with_ttl("bucket", "0002".into(), Box::new(&b"c"[..]), Duration::ZERO)
+            .await?;
+        kv.set("bucket", "0002".into(), Box::new(&b"d"[..])).await?;
+        assert_eq!(
+            get(&kv, "bucket", "0002").await?.as_deref(),
+            Some(&b"d"[..])
+        );
+        assert_eq!(kv.increment("bucket", "0000".into(), 1).await?, 1);
+
+        let buckets = HashMap::from(kv);
+        assert_eq!(buckets["bucket"].len(), LIST_KEYS_PAGE_SIZE + 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,103 @@
+use crate::capability::{self, logging};
+
+use std::sync::{Mutex, PoisonError};
+
+use async_trait::async_trait;
+use tracing::instrument;
+
+/// Log message captured by [Logging]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Record {
+    /// Log level
+    pub level: logging::logging::Level,
+    /// Context of the message
+    pub context: String,
+    /// Message text
+    pub message: String,
+}
+
+/// In-memory [`Logging`](crate::capability::Logging) sink capturing all
+/// received messages, for example, to inspect guest logs in tests
+#[derive(Debug, Default)]
+pub struct Logging(Mutex<Vec<Record>>);
+
+impl Logging {
+    /// Return all messages captured so far
+    pub fn records(&self) -> Vec<Record> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Remove and return all messages captured so far
+    pub fn take(&self) -> Vec<Record> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[async_trait]
+impl capability::Logging for Logging {
+    #[instrument(level = "trace", skip(message))]
+    async fn log(
+        &self,
+        level: logging::logging::Level,
+        context: String,
+        message: String,
+    ) -> anyhow::Result<()> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Record {
+                level,
+                context,
+                message,
+            });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::{Handler, Logging as _};
+    use crate::component::Host;
+
+    use std::sync::Arc;
+
+    use logging::logging::{Host as _, Level};
+
+    #[tokio::test]
+    async fn capture() -> anyhow::Result<()> {
+        let sink = Arc::new(Logging::default());
+        let mut handler = Handler::default();
+        handler
+            .log(Level::Info, "ignored".into(), "no sink".into())
+            .await?;
+        handler.replace_logging(sink.clone());
+        handler.set_component_id("test");
+
+        let mut host = Host::new(handler);
+        host.log(Level::Warn, "ctx".into(), "hello".into()).await?;
+        host.log(Level::Critical, String::new(), "bye".into())
+            .await?;
+        assert_eq!(
+            sink.take(),
+            [
+                Record {
+                    level: Level::Warn,
+                    context: "ctx".into(),
+                    message: "hello".into(),
+                },
+                Record {
+                    level: Level::Critical,
+                    context: String::new(),
+                    message: "bye".into(),
+                },
+            ]
+        );
+        assert!(sink.records().is_empty());
+        Ok(())
+    }
+}
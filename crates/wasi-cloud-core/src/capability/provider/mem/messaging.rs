@@ -0,0 +1,317 @@
+use crate::capability::{self, messaging};
+
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::Poll;
+use core::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use async_trait::async_trait;
+use futures::Stream;
+use tokio::sync::{mpsc, RwLock};
+use tokio::time::{timeout_at, Instant};
+use tracing::{instrument, warn};
+
+/// Number of messages buffered per subscription before new messages are dropped
+const SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// Timeout used for requests, which do not specify one
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prefix of subjects used to receive replies to requests
+const INBOX_PREFIX: &str = "_INBOX";
+
+/// Subject pattern token
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    /// Literal token
+    Literal(String),
+    /// `*`, matching exactly one token
+    Any,
+    /// `>`, matching one or more trailing tokens
+    Rest,
+}
+
+/// Parsed subject pattern, which may contain `*` and `>` wildcards
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Pattern(Vec<Token>);
+
+impl Pattern {
+    fn parse(pattern: &str) -> anyhow::Result<Self> {
+        let mut tokens = vec![];
+        let mut parts = pattern.split('.').peekable();
+        while let Some(part) = parts.next() {
+            let token = match part {
+                "" => bail!("subject pattern `{pattern}` contains an empty token"),
+                "*" => Token::Any,
+                ">" => {
+                    ensure!(
+                        parts.peek().is_none(),
+                        "`>` must be the last token of subject pattern `{pattern}`"
+                    );
+                    Token::Rest
+                }
+                part => Token::Literal(part.into()),
+            };
+            tokens.push(token);
+        }
+        Ok(Self(tokens))
+    }
+
+    fn matches(&self, subject: &str) -> bool {
+        let mut parts = subject.split('.');
+        for token in &self.0 {
+            match (token, parts.next()) {
+                (Token::Rest, Some(_)) => return true,
+                (Token::Any, Some(_)) => {}
+                (Token::Literal(lit), Some(part)) if lit == part => {}
+                _ => return false,
+            }
+        }
+        parts.next().is_none()
+    }
+}
+
+/// Validates that `subject` is a valid subject to publish to
+fn validate_subject(subject: &str) -> anyhow::Result<()> {
+    ensure!(
+        !subject
+            .split('.')
+            .any(|part| matches!(part, "" | "*" | ">")),
+        "invalid subject `{subject}`"
+    );
+    Ok(())
+}
+
+/// Subscription to an in-memory [Messaging] broker, which yields all
+/// messages published to matching subjects
+#[derive(Debug)]
+pub struct Subscription(mpsc::Receiver<messaging::types::BrokerMessage>);
+
+impl Subscription {
+    /// Receive the next message, returns `None` once the broker is dropped
+    pub async fn next_message(&mut self) -> Option<messaging::types::BrokerMessage> {
+        self.0.recv().await
+    }
+}
+
+impl Stream for Subscription {
+    type Item = messaging::types::BrokerMessage;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// In-memory [`Messaging`](crate::capability::Messaging) broker with
+/// NATS-style subject matching and request/reply support
+#[derive(Debug, Default)]
+pub struct Messaging {
+    subscriptions: RwLock<Vec<(Pattern, mpsc::Sender<messaging::types::BrokerMessage>)>>,
+    inbox_id: AtomicU64,
+}
+
+impl Messaging {
+    /// Subscribe to all subjects matching `pattern`, where `*` matches a
+    /// single token and `>` matches one or more trailing tokens
+    #[instrument]
+    pub async fn subscribe(&self, pattern: &str) -> anyhow::Result<Subscription> {
+        let pattern = Pattern::parse(pattern)?;
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_CAPACITY);
+        let mut subscriptions = self.subscriptions.write().await;
+        subscriptions.retain(|(_, tx)| !tx.is_closed());
+        subscriptions.push((pattern, tx));
+        Ok(Subscription(rx))
+    }
+
+    /// Deliver `msg` to all matching subscriptions, returning the number of
+    /// subscriptions, which received it
+    async fn deliver(&self, msg: messaging::types::BrokerMessage) -> anyhow::Result<usize> {
+        validate_subject(&msg.subject)?;
+        let subscriptions = self.subscriptions.read().await;
+        let mut n = 0;
+        for (pattern, tx) in subscriptions.iter() {
+            if !pattern.matches(&msg.subject) {
+                continue;
+            }
+            match tx.try_send(msg.clone()) {
+                Ok(()) => n += 1,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!(subject = msg.subject, "subscription full, dropping message")
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {}
+            }
+        }
+        Ok(n)
+    }
+
+    /// Publish a request and return the subscription receiving the replies
+    async fn send_request(
+        &self,
+        subject: String,
+        body: Option<Vec<u8>>,
+    ) -> anyhow::Result<Subscription> {
+        let id = self.inbox_id.fetch_add(1, Ordering::Relaxed);
+        let inbox = format!("{INBOX_PREFIX}.{id}");
+        let replies = self.subscribe(&inbox).await?;
+        let n = self
+            .deliver(messaging::types::BrokerMessage {
+                subject: subject.clone(),
+                body,
+                reply_to: Some(inbox),
+            })
+            .await?;
+        ensure!(n > 0, "no responders available for subject `{subject}`");
+        Ok(replies)
+    }
+}
+
+fn deadline(timeout: Duration) -> Instant {
+    let timeout = if timeout.is_zero() {
+        DEFAULT_TIMEOUT
+    } else {
+        timeout
+    };
+    Instant::now() + timeout
+}
+
+#[async_trait]
+impl capability::Messaging for Messaging {
+    #[instrument(skip(body))]
+    async fn request(
+        &self,
+        subject: String,
+        body: Option<Vec<u8>>,
+        timeout: Duration,
+    ) -> anyhow::Result<messaging::types::BrokerMessage> {
+        let mut replies = self.send_request(subject, body).await?;
+        timeout_at(deadline(timeout), replies.next_message())
+            .await
+            .context("request timed out")?
+            .context("broker closed")
+    }
+
+    #[instrument(skip(body))]
+    async fn request_multi(
+        &self,
+        subject: String,
+        body: Option<Vec<u8>>,
+        timeout: Duration,
+        max_results: u32,
+    ) -> anyhow::Result<Vec<messaging::types::BrokerMessage>> {
+        let mut replies = self.send_request(subject, body).await?;
+        let deadline = deadline(timeout);
+        let max_results = usize::try_from(max_results).unwrap_or(usize::MAX);
+        let mut msgs = vec![];
+        while max_results == 0 || msgs.len() < max_results {
+            match timeout_at(deadline, replies.next_message()).await {
+                Ok(Some(msg)) => msgs.push(msg),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        Ok(msgs)
+    }
+
+    #[instrument(skip(msg))]
+    async fn publish(&self, msg: messaging::types::BrokerMessage) -> anyhow::Result<()> {
+        self.deliver(msg).await?;
+        Ok(())
+    }
+
+    #[instrument]
+    async fn subscribe(
+        &self,
+        subject: String,
+    ) -> anyhow::Result<Box<dyn Stream<Item = messaging::types::BrokerMessage> + Sync + Send + Unpin>>
+    {
+        let sub = Messaging::subscribe(self, &subject).await?;
+        Ok(Box::new(sub))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::Messaging as _;
+
+    use std::sync::Arc;
+
+    use messaging::types::BrokerMessage;
+
+    #[test]
+    fn subject_matching() -> anyhow::Result<()> {
+        let pattern = Pattern::parse("foo.*.baz")?;
+        assert!(pattern.matches("foo.bar.baz"));
+        assert!(!pattern.matches("foo.bar"));
+        assert!(!pattern.matches("foo.bar.baz.qux"));
+
+        let pattern = Pattern::parse("foo.>")?;
+        assert!(pattern.matches("foo.bar"));
+        assert!(pattern.matches("foo.bar.baz"));
+        assert!(!pattern.matches("foo"));
+
+        let pattern = Pattern::parse("foo.bar")?;
+        assert!(pattern.matches("foo.bar"));
+        assert!(!pattern.matches("foo.baz"));
+
+        assert!(Pattern::parse("foo.>.bar").is_err());
+        assert!(Pattern::parse("foo..bar").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn request_reply() -> anyhow::Result<()> {
+        let broker = Arc::new(Messaging::default());
+        assert!(broker
+            .request("echo".into(), None, Duration::from_millis(10))
+            .await
+            .is_err());
+
+        let mut sub = broker.subscribe("echo.*").await?;
+        let responder = {
+            let broker = Arc::clone(&broker);
+            tokio::spawn(async move {
+                while let Some(msg) = sub.next_message().await {
+                    let reply_to = msg.reply_to.expect("missing reply subject");
+                    for _ in 0..2 {
+                        broker
+                            .publish(BrokerMessage {
+                                subject: reply_to.clone(),
+                                body: msg.body.clone(),
+                                reply_to: None,
+                            })
+                            .await
+                            .expect("failed to reply");
+                    }
+                }
+            })
+        };
+
+        let reply = broker
+            .request(
+                "echo.a".into(),
+                Some(b"hi".to_vec()),
+                Duration::from_secs(1),
+            )
+            .await?;
+        assert_eq!(reply.body.as_deref(), Some(&b"hi"[..]));
+
+        let replies = broker
+            .request_multi("echo.b".into(), None, Duration::from_millis(100), 0)
+            .await?;
+        assert_eq!(replies.len(), 2);
+
+        let replies = broker
+            .request_multi("echo.c".into(), None, Duration::from_secs(1), 1)
+            .await?;
+        assert_eq!(replies.len(), 1);
+
+        drop(broker);
+        responder.abort();
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 mod blobstore;
 mod keyvalue;
+mod logging;
+mod messaging;
 
 pub use blobstore::{Blobstore, Container as BlobstoreContainer, Object as BlobstoreObject};
 pub use keyvalue::{Entry as KeyValueEntry, KeyValue};
+pub use logging::{Logging, Record as LoggingRecord};
+pub use messaging::{Messaging, Subscription as MessagingSubscription};
@@ -1,12 +1,50 @@
+use core::ops::Bound;
+
+use std::collections::BTreeMap;
+
 /// In-memory provider implementations
 pub mod mem;
 
 pub use mem::{
     Blobstore as MemoryBlobstore, BlobstoreContainer as MemoryBlobstoreContainer,
     BlobstoreObject as MemoryBlobstoreObject, KeyValue as MemoryKeyValue,
-    KeyValueEntry as MemoryKeyValueEntry,
+    KeyValueEntry as MemoryKeyValueEntry, Logging as MemoryLogging,
+    LoggingRecord as MemoryLoggingRecord, Messaging as MemoryMessaging,
+    MessagingSubscription as MemoryMessagingSubscription,
 };
 
+/// AWS provider implementations
 pub mod aws;
 
-pub use aws::S3Blobstore;
\ No newline at end of file
+/// Local filesystem provider implementations
+pub mod fs;
+
+pub use aws::{S3Blobstore, S3BlobstoreConfig, S3Credentials, S3UploadProgress};
+pub use fs::{Blobstore as FsBlobstore, KeyValue as FsKeyValue};
+
+/// Maximum number of keys returned by a single
+/// [`KeyValueEventual::list_keys`](crate::capability::KeyValueEventual::list_keys)
+/// call of the providers in this module
+pub const LIST_KEYS_PAGE_SIZE: usize = 1000;
+
+/// Returns the page of keys in `entries` following the key `cursor` in
+/// lexicographic order, skipping keys for which `skip` returns `true`, and the
+/// cursor of the next page, if any, which is the last key returned
+fn list_keys_page<V>(
+    entries: &BTreeMap<String, V>,
+    cursor: Option<String>,
+    mut skip: impl FnMut(&str) -> bool,
+) -> (Vec<String>, Option<String>) {
+    let start = cursor.as_deref().map_or(Bound::Unbounded, Bound::Excluded);
+    let mut keys = entries
+        .range::<str, _>((start, Bound::Unbounded))
+        .map(|(key, _)| key)
+        .filter(|key| !skip(key));
+    let page: Vec<_> = keys.by_ref().take(LIST_KEYS_PAGE_SIZE).cloned().collect();
+    let cursor = if keys.next().is_some() {
+        page.last().cloned()
+    } else {
+        None
+    };
+    (page, cursor)
+}
@@ -0,0 +1,377 @@
+//! Signed capability claims embedded in components
+//!
+//! Claims are a JWT signed by an account key stored in the `jwt` custom
+//! section of a component. They identify the component by its module public
+//! key, bind to the component contents by hash and list the capability
+//! interfaces the component may import, for example:
+//!
+//! ```json
+//! {
+//!   "iss": "ACZ...",
+//!   "sub": "MBX...",
+//!   "iat": 1700000000,
+//!   "exp": 1800000000,
+//!   "wascap": {
+//!     "hash": "5f2b...",
+//!     "caps": ["wasi:keyvalue/atomic", "wasi:logging/logging"]
+//!   }
+//! }
+//! ```
+
+use crate::capability::TargetInterface;
+
+use core::ops::Range;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use nkeys::{KeyPair, KeyPairType};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use wasmparser::{Chunk, Parser, Payload};
+
+/// Name of the custom section containing the claims
+pub const SECTION_NAME: &str = "jwt";
+
+/// JWT header of signed claims
+const HEADER: &str = r#"{"typ":"jwt","alg":"Ed25519"}"#;
+
+/// Capability claims of a component
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Claims {
+    /// Public key of the account, which issued the claims
+    #[serde(rename = "iss")]
+    pub issuer: String,
+    /// Module public key identifying the component
+    #[serde(rename = "sub")]
+    pub subject: String,
+    /// Seconds since UNIX epoch at which the claims were issued
+    #[serde(rename = "iat", default)]
+    pub issued_at: u64,
+    /// Seconds since UNIX epoch at which the claims expire
+    #[serde(rename = "exp", default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    /// Seconds since UNIX epoch before which the claims are not valid
+    #[serde(rename = "nbf", default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<u64>,
+    /// Component-specific claims
+    #[serde(rename = "wascap")]
+    pub component: ComponentClaims,
+}
+
+/// Component-specific part of [Claims]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ComponentClaims {
+    /// Human-readable name of the component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Hex-encoded SHA-256 hash of the component without the claims section
+    #[serde(default)]
+    pub hash: String,
+    /// Capability interfaces, which the component is allowed to import,
+    /// for example, `wasi:blobstore/blobstore`
+    #[serde(default)]
+    pub caps: Vec<String>,
+}
+
+/// Claims enforcement policy
+#[derive(Clone, Debug, Default)]
+pub struct Policy {
+    /// Refuse components without claims or with expired claims instead of
+    /// granting unsigned components all interfaces
+    ///
+    /// Strict enforcement requires at least one trusted issuer, since anyone
+    /// can sign claims granting themselves all interfaces otherwise.
+    pub strict: bool,
+    /// Public keys of accounts trusted to issue claims, if empty, claims
+    /// issued by any account are accepted unless the policy is strict
+    pub trusted_issuers: Vec<String>,
+}
+
+impl Claims {
+    /// Capability interfaces granted by these claims
+    pub fn interfaces(&self) -> Result<Vec<TargetInterface>> {
+        self.component.caps.iter().map(|cap| cap.parse()).collect()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Locate the top-level claims section of `wasm` returning the byte range
+/// of the whole section, including its header, and its contents
+fn find_section(wasm: &[u8]) -> Result<Option<(Range<usize>, &[u8])>> {
+    let mut found = None;
+    let mut parser = Parser::new(0);
+    let mut offset = 0;
+    loop {
+        let Chunk::Parsed { consumed, payload } = parser
+            .parse(&wasm[offset..], true)
+            .context("failed to parse component")?
+        else {
+            bail!("failed to parse component: unexpected end of input");
+        };
+        let start = offset;
+        offset += consumed;
+        match payload {
+            // Nested modules and components are skipped, only the
+            // top-level claims apply
+            Payload::ModuleSection { range, .. } | Payload::ComponentSection { range, .. } => {
+                offset = range.end;
+            }
+            Payload::CustomSection(section) if section.name() == SECTION_NAME => {
+                ensure!(
+                    found.is_none(),
+                    "component contains multiple claims sections"
+                );
+                found = Some((start..offset, section.data()));
+            }
+            Payload::End(_) => return Ok(found),
+            _ => {}
+        }
+    }
+}
+
+/// Hex-encoded SHA-256 hash of `wasm` excluding `section`
+fn hash(wasm: &[u8], section: Option<&Range<usize>>) -> String {
+    let mut hasher = Sha256::new();
+    if let Some(section) = section {
+        hasher.update(&wasm[..section.start]);
+        hasher.update(&wasm[section.end..]);
+    } else {
+        hasher.update(wasm);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Sign `claims` with the `issuer` account key and embed them in `wasm`,
+/// replacing existing claims, if any
+///
+/// The issuer and hash of `claims` are set to match `issuer` and `wasm`.
+pub fn embed(wasm: &[u8], claims: &Claims, issuer: &KeyPair) -> Result<Vec<u8>> {
+    ensure!(
+        issuer.key_pair_type() == KeyPairType::Account,
+        "claims must be issued by an account key"
+    );
+    let mut wasm = wasm.to_vec();
+    if let Some((section, _)) = find_section(&wasm)? {
+        wasm.drain(section);
+    }
+    let claims = Claims {
+        issuer: issuer.public_key(),
+        component: ComponentClaims {
+            hash: hash(&wasm, None),
+            ..claims.component.clone()
+        },
+        ..claims.clone()
+    };
+    let payload = serde_json::to_vec(&claims).context("failed to encode claims")?;
+    let input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(HEADER),
+        URL_SAFE_NO_PAD.encode(payload)
+    );
+    let sig = issuer
+        .sign(input.as_bytes())
+        .context("failed to sign claims")?;
+    let token = format!("{input}.{}", URL_SAFE_NO_PAD.encode(sig));
+    wasm_encoder::Section::append_to(
+        &wasm_encoder::CustomSection {
+            name: SECTION_NAME.into(),
+            data: token.as_bytes().into(),
+        },
+        &mut wasm,
+    );
+    Ok(wasm)
+}
+
+/// Decode and verify the signature and hash of claims embedded in `wasm`
+/// without checking their validity period, returns `None` if `wasm` does not
+/// contain claims
+pub fn extract(wasm: &[u8]) -> Result<Option<Claims>> {
+    let Some((section, token)) = find_section(wasm)? else {
+        return Ok(None);
+    };
+    let token = std::str::from_utf8(token).context("claims are not valid UTF-8")?;
+    let (input, sig) = token.rsplit_once('.').context("claims are not a JWT")?;
+    let (header, payload) = input.split_once('.').context("claims are not a JWT")?;
+    let header = URL_SAFE_NO_PAD
+        .decode(header)
+        .context("failed to decode claims header")?;
+    let header: serde_json::Value =
+        serde_json::from_slice(&header).context("failed to parse claims header")?;
+    ensure!(
+        header["alg"] == "Ed25519",
+        "unsupported claims signature algorithm `{}`",
+        header["alg"]
+    );
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .context("failed to decode claims")?;
+    let claims: Claims = serde_json::from_slice(&payload).context("failed to parse claims")?;
+    let sig = URL_SAFE_NO_PAD
+        .decode(sig)
+        .context("failed to decode claims signature")?;
+
+    let issuer = KeyPair::from_public_key(&claims.issuer).context("invalid claims issuer")?;
+    ensure!(
+        issuer.key_pair_type() == KeyPairType::Account,
+        "claims issuer `{}` is not an account key",
+        claims.issuer
+    );
+    let subject = KeyPair::from_public_key(&claims.subject).context("invalid claims subject")?;
+    ensure!(
+        subject.key_pair_type() == KeyPairType::Module,
+        "claims subject `{}` is not a module key",
+        claims.subject
+    );
+    issuer
+        .verify(input.as_bytes(), &sig)
+        .context("invalid claims signature")?;
+    ensure!(
+        claims.component.hash == hash(wasm, Some(&section)),
+        "claims do not match the component contents"
+    );
+    Ok(Some(claims))
+}
+
+/// Verify claims embedded in `wasm` according to `policy`
+///
+/// Returns `None` if `wasm` does not contain claims and the policy is not
+/// strict, in which case the component should be granted all interfaces.
+/// Fails if the policy is strict without any trusted issuers.
+pub fn verify(wasm: &[u8], policy: &Policy) -> Result<Option<Claims>> {
+    ensure!(
+        !policy.strict || !policy.trusted_issuers.is_empty(),
+        "strict claims enforcement requires at least one trusted issuer"
+    );
+    let Some(claims) = extract(wasm)? else {
+        ensure!(!policy.strict, "component does not contain signed claims");
+        return Ok(None);
+    };
+    ensure!(
+        policy.trusted_issuers.is_empty() || policy.trusted_issuers.contains(&claims.issuer),
+        "claims issuer `{}` is not trusted",
+        claims.issuer
+    );
+    let now = now();
+    if let Some(not_before) = claims.not_before {
+        ensure!(now >= not_before, "claims are not valid yet");
+    }
+    match claims.expires {
+        Some(expires) if now >= expires && policy.strict => bail!("claims have expired"),
+        Some(expires) if now >= expires => {
+            warn!(subject = claims.subject, "claims have expired");
+        }
+        _ => {}
+    }
+    Ok(Some(claims))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smallest valid component
+    const COMPONENT: &[u8] = b"\0asm\x0d\0\x01\0";
+
+    #[test]
+    fn roundtrip() -> Result<()> {
+        let account = KeyPair::new_account();
+        let module = KeyPair::new_module();
+        let claims = Claims {
+            subject: module.public_key(),
+            issued_at: now(),
+            component: ComponentClaims {
+                caps: vec!["wasi:keyvalue/atomic".into()],
+                ..ComponentClaims::default()
+            },
+            ..Claims::default()
+        };
+        let wasm = embed(COMPONENT, &claims, &account)?;
+        let strict = Policy {
+            strict: true,
+            trusted_issuers: vec![account.public_key()],
+        };
+        let verified = verify(&wasm, &strict)?.context("claims missing")?;
+        assert_eq!(verified.issuer, account.public_key());
+        assert_eq!(
+            verified.interfaces()?,
+            [TargetInterface::WasiKeyvalueAtomic]
+        );
+
+        // Re-signing replaces the existing claims
+        let wasm = embed(&wasm, &claims, &account)?;
+        assert!(verify(&wasm, &strict)?.is_some());
+
+        // Section sizes are not necessarily minimally encoded
+        let (_, token) = find_section(&wasm)?.context("claims missing")?;
+        let size = u32::try_from(token.len() + 4)?;
+        let mut padded = COMPONENT.to_vec();
+        padded.push(0);
+        padded.extend((0..5).map(|i| {
+            let byte = (size >> (7 * i)) as u8 & 0x7f;
+            if i < 4 {
+                byte | 0x80
+            } else {
+                byte
+            }
+        }));
+        padded.push(3);
+        padded.extend(SECTION_NAME.as_bytes());
+        padded.extend(token);
+        assert!(verify(&padded, &strict)?.is_some());
+        assert!(verify(&embed(&padded, &claims, &account)?, &strict)?.is_some());
+
+        assert!(verify(COMPONENT, &strict).is_err());
+        assert!(verify(COMPONENT, &Policy::default())?.is_none());
+
+        let untrusted = Policy {
+            trusted_issuers: vec![KeyPair::new_account().public_key()],
+            ..strict.clone()
+        };
+        assert!(verify(&wasm, &untrusted).is_err());
+
+        // Strict enforcement does not accept self-signed claims of any issuer
+        let any_issuer = Policy {
+            trusted_issuers: vec![],
+            ..strict.clone()
+        };
+        assert!(verify(&wasm, &any_issuer).is_err());
+
+        let mut tampered = wasm.clone();
+        wasm_encoder::Section::append_to(
+            &wasm_encoder::CustomSection {
+                name: "foo".into(),
+                data: b"bar".as_slice().into(),
+            },
+            &mut tampered,
+        );
+        assert!(extract(&tampered).is_err());
+
+        let expired = embed(
+            COMPONENT,
+            &Claims {
+                expires: Some(1),
+                ..claims.clone()
+            },
+            &account,
+        )?;
+        assert!(verify(&expired, &strict).is_err());
+        assert!(verify(&expired, &Policy::default())?.is_some());
+
+        assert!(embed(COMPONENT, &claims, &module).is_err());
+        Ok(())
+    }
+}
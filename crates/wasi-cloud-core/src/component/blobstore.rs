@@ -7,11 +7,11 @@ use crate::capability::blobstore::types::{
 };
 use crate::capability::blobstore::{blobstore, container, types};
 use crate::capability::Blobstore;
-use crate::io::AsyncVec;
+use crate::io::SpooledVec;
 
 use std::sync::Arc;
 
-use anyhow::{bail, ensure, Context as _};
+use anyhow::{ensure, Context as _};
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
@@ -90,7 +90,7 @@ impl container::HostContainer for Host {
     ) -> anyhow::Result<Result<()>> {
         let (table, handler) = self.table_and_handler();
         let mut stream = table
-            .get::<AsyncVec>(&data)
+            .get::<SpooledVec>(&data)
             .context("failed to get outgoing value")?
             .clone();
         stream.rewind().await.context("failed to rewind stream")?;
@@ -263,7 +263,7 @@ impl types::HostOutgoingValue for Host {
     #[instrument]
     async fn new_outgoing_value(&mut self) -> anyhow::Result<Resource<types::OutgoingValue>> {
         self.table()
-            .push(AsyncVec::default())
+            .push(SpooledVec::default())
             .context("failed to push outgoing value")
     }
 
@@ -274,7 +274,7 @@ impl types::HostOutgoingValue for Host {
     ) -> anyhow::Result<Result<Resource<Box<dyn HostOutputStream>>, ()>> {
         let stream = self
             .table()
-            .get::<AsyncVec>(&outgoing_value)
+            .get::<SpooledVec>(&outgoing_value)
             .context("failed to get outgoing value")?
             .clone();
         let stream: Box<dyn HostOutputStream> = Box::new(AsyncWriteStream::new(1 << 16, stream));
@@ -404,16 +404,28 @@ impl blobstore::Host for Host {
         }
     }
 
-    #[allow(unused)] // TODO: Implement and remove
     #[instrument]
     async fn copy_object(&mut self, src: ObjectId, dest: ObjectId) -> anyhow::Result<Result<()>> {
-        bail!("not supported yet")
+        match self
+            .handler
+            .copy_object(&src.container, src.object, &dest.container, dest.object)
+            .await
+        {
+            Ok(()) => Ok(Ok(())),
+            Err(err) => Ok(Err(format!("{err:#}"))),
+        }
     }
 
-    #[allow(unused)] // TODO: Implement and remove
     #[instrument]
     async fn move_object(&mut self, src: ObjectId, dest: ObjectId) -> anyhow::Result<Result<()>> {
-        bail!("not supported yet")
+        match self
+            .handler
+            .move_object(&src.container, src.object, &dest.container, dest.object)
+            .await
+        {
+            Ok(()) => Ok(Ok(())),
+            Err(err) => Ok(Err(format!("{err:#}"))),
+        }
     }
 }
 
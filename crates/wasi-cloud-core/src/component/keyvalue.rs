@@ -1,9 +1,12 @@
 use super::Host;
 
 use crate::capability::keyvalue::{atomic, eventual, types, wasi_keyvalue_error};
+use crate::capability::keyvalue_ext;
 use crate::capability::{KeyValueAtomic, KeyValueEventual};
 use crate::io::AsyncVec;
 
+use core::time::Duration;
+
 use std::sync::Arc;
 
 use anyhow::{anyhow, ensure, Context};
@@ -149,6 +152,53 @@ impl eventual::Host for Host {
     }
 }
 
+#[async_trait]
+impl keyvalue_ext::Host for Host {
+    #[instrument]
+    async fn list_keys(
+        &mut self,
+        bucket: Resource<types::Bucket>,
+        cursor: Option<String>,
+    ) -> anyhow::Result<Result<keyvalue_ext::KeyResponse>> {
+        let (table, handler) = self.table_and_handler();
+        let bucket = table.get(&bucket).context("failed to get bucket")?;
+        match handler.list_keys(bucket, cursor).await {
+            Ok((keys, cursor)) => Ok(Ok(keyvalue_ext::KeyResponse { keys, cursor })),
+            Err(err) => {
+                let err = self.table().push(err).context("failed to push error")?;
+                Ok(Err(err))
+            }
+        }
+    }
+
+    #[instrument]
+    async fn set_with_ttl(
+        &mut self,
+        bucket: Resource<types::Bucket>,
+        key: types::Key,
+        outgoing_value: Resource<types::OutgoingValue>,
+        ttl_ms: u64,
+    ) -> anyhow::Result<Result<()>> {
+        let (table, handler) = self.table_and_handler();
+        let mut stream = table
+            .get::<AsyncVec>(&outgoing_value)
+            .context("failed to get outgoing value")?
+            .clone();
+        stream.rewind().await.context("failed to rewind stream")?;
+        let bucket = table.get(&bucket).context("failed to get bucket")?;
+        match handler
+            .set_with_ttl(bucket, key, Box::new(stream), Duration::from_millis(ttl_ms))
+            .await
+        {
+            Ok(()) => Ok(Ok(())),
+            Err(err) => {
+                let err = self.table().push(err).context("failed to push error")?;
+                Ok(Err(err))
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl types::HostBucket for Host {
     #[instrument]
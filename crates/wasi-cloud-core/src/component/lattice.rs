@@ -0,0 +1,28 @@
+use super::Host;
+
+use crate::capability::lattice;
+use crate::capability::TargetInterface;
+
+use async_trait::async_trait;
+use tracing::instrument;
+
+#[async_trait]
+impl lattice::Host for Host {
+    #[instrument]
+    async fn set_link_name(
+        &mut self,
+        name: String,
+        interfaces: Vec<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        let interfaces = match interfaces
+            .iter()
+            .map(|interface| interface.parse())
+            .collect::<anyhow::Result<Vec<TargetInterface>>>()
+        {
+            Ok(interfaces) => interfaces,
+            Err(err) => return Ok(Err(format!("{err:#}"))),
+        };
+        self.handler.set_link_name(name, interfaces);
+        Ok(Ok(()))
+    }
+}
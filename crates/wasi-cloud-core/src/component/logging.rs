@@ -0,0 +1,35 @@
+use super::Host;
+
+use crate::capability::logging::logging;
+use crate::capability::Logging;
+
+use async_trait::async_trait;
+use tracing::{debug, error, info, trace, warn};
+
+#[async_trait]
+impl logging::Host for Host {
+    async fn log(
+        &mut self,
+        level: logging::Level,
+        context: String,
+        message: String,
+    ) -> anyhow::Result<()> {
+        let component = self.handler.component_id().unwrap_or_default();
+        match level {
+            logging::Level::Trace => trace!(component, context, message),
+            logging::Level::Debug => debug!(component, context, message),
+            logging::Level::Info => info!(component, context, message),
+            logging::Level::Warn => warn!(component, context, message),
+            logging::Level::Error => error!(component, context, message),
+            logging::Level::Critical => error!(component, context, message, critical = true),
+        }
+        if let Err(err) = self.handler.log(level, context, message).await {
+            warn!(
+                component,
+                ?err,
+                "failed to forward log message to logging handler"
+            );
+        }
+        Ok(())
+    }
+}
@@ -28,6 +28,8 @@ use wasmtime_wasi_threads::WasiThreadsCtx;
 mod blobstore;
 mod http;
 mod keyvalue;
+mod lattice;
+mod logging;
 mod messaging;
 
 type TableResult<T> = Result<T, ResourceTableError>;
@@ -176,31 +178,46 @@ impl StdoutStream for StdioStream<Box<dyn HostOutputStream>> {
     }
 }
 
-#[derive(Clone)]
+/// Constructor of a preview2 [WasiCtx], called once for a [Host] and once for
+/// each of its clones
+pub type NewWasiCtx = Arc<dyn Fn() -> anyhow::Result<WasiCtx> + Send + Sync>;
+
+/// Store state of a component
+///
+/// Cloning a [Host], for example, when `wasi-threads` spawns a thread, shares
+/// the capability providers of the [Handler] and the `wasi_common` context,
+/// while the clone gets its own empty [ResourceTable], preview1 adapter state
+/// and preview2 [WasiCtx], which is constructed by
+/// [`Host::new_preview2_ctx`] on first use. Hence, resources, like buckets and
+/// containers, cannot be passed between threads.
+///
+/// For the same reason, a known limitation of `wasi-threads` modules using the
+/// preview2-based preview1 implementation is that every thread has its own file
+/// descriptor table, so descriptors opened by one thread are not visible to
+/// the others. `wasi_common`, which `wasi-threads` uses by default, shares the
+/// descriptors between threads.
 pub struct Host {
     pub handler: builtin::Handler,
     pub stdin: StdioStream<Box<dyn HostInputStream>>,
     pub stdout: StdioStream<Box<dyn HostOutputStream>>,
     pub stderr: StdioStream<Box<dyn HostOutputStream>>,
     pub preview1_ctx: Option<wasi_common::WasiCtx>,
-    pub preview2_ctx: Option<Arc<std::sync::Mutex<wasmtime_wasi::WasiCtx>>>,
-    pub preview2_table: Arc<std::sync::Mutex<wasmtime::component::ResourceTable>>,
-    pub preview2_adapter: Arc<wasmtime_wasi::preview1::WasiPreview1Adapter>,
+    /// Preview2 [WasiCtx] of this host, see [`Host::set_preview2_ctx`]
+    pub preview2_ctx: Option<WasiCtx>,
+    /// Constructor of the preview2 [WasiCtx] of clones of this host
+    pub new_preview2_ctx: Option<NewWasiCtx>,
+    pub preview2_table: ResourceTable,
+    pub preview2_adapter: wasmtime_wasi::preview1::WasiPreview1Adapter,
     pub wasi_nn: Option<Arc<WasiNnCtx>>,
     pub wasi_threads: Option<Arc<WasiThreadsCtx<Host>>>,
-    pub wasi_http: Option<Arc<WasiHttpCtx>>,
+    pub wasi_http: Option<WasiHttpCtx>,
     pub limits: StoreLimits,
     pub guest_profiler: Option<Arc<wasmtime::GuestProfiler>>,
 }
 
 impl Host {
     fn table_and_handler(&mut self) -> (&mut ResourceTable, &mut builtin::Handler) {
-        let table = Arc::get_mut(&mut self.preview2_table)
-            .expect("wasmtime_wasi is not compatible with threads")
-            .get_mut()
-            .unwrap();
-        let handler = &mut self.handler;
-        (table, handler)
+        (&mut self.preview2_table, &mut self.handler)
     }
 
     pub async fn default() -> Self {
@@ -214,15 +231,21 @@ impl Host {
             .blobstore(blobstore)
             .keyvalue_eventual(keyvalue);
 
+        Self::new(Handler::from(bulider))
+    }
+
+    /// Construct a new [Host] using the given capability [Handler]
+    pub fn new(handler: Handler) -> Self {
         Self {
-            handler: Handler::from(bulider),
+            handler,
             stdin: StdioStream::default(),
             stdout: StdioStream::default(),
             stderr: StdioStream::default(),
             preview1_ctx: None,
             preview2_ctx: None,
-            preview2_table: Arc::new(std::sync::Mutex::new(ResourceTable::default())),
-            preview2_adapter: Arc::new(wasmtime_wasi::preview1::WasiPreview1Adapter::default()),
+            new_preview2_ctx: None,
+            preview2_table: ResourceTable::default(),
+            preview2_adapter: wasmtime_wasi::preview1::WasiPreview1Adapter::default(),
             wasi_nn: None,
             wasi_threads: None,
             wasi_http: None,
@@ -232,6 +255,37 @@ impl Host {
     }
 }
 
+impl Host {
+    /// Set the preview2 [WasiCtx] of this host to one constructed by
+    /// `new_ctx`, which also constructs the contexts of its clones
+    pub fn set_preview2_ctx(&mut self, new_ctx: NewWasiCtx) -> anyhow::Result<()> {
+        self.preview2_ctx = Some(new_ctx()?);
+        self.new_preview2_ctx = Some(new_ctx);
+        Ok(())
+    }
+}
+
+impl Clone for Host {
+    fn clone(&self) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            stdin: self.stdin.clone(),
+            stdout: self.stdout.clone(),
+            stderr: self.stderr.clone(),
+            preview1_ctx: self.preview1_ctx.clone(),
+            preview2_ctx: None,
+            new_preview2_ctx: self.new_preview2_ctx.clone(),
+            preview2_table: ResourceTable::default(),
+            preview2_adapter: wasmtime_wasi::preview1::WasiPreview1Adapter::default(),
+            wasi_nn: self.wasi_nn.clone(),
+            wasi_threads: self.wasi_threads.clone(),
+            wasi_http: self.wasi_http.as_ref().map(|_| WasiHttpCtx),
+            limits: self.limits.clone(),
+            guest_profiler: self.guest_profiler.clone(),
+        }
+    }
+}
+
 impl Debug for Host {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Ctx").field("runtime", &"wasmtime").finish()
@@ -244,7 +298,72 @@ impl wasmtime_wasi::preview1::WasiPreview1View for Host {
     }
 
     fn adapter_mut(&mut self) -> &mut wasmtime_wasi::preview1::WasiPreview1Adapter {
-        Arc::get_mut(&mut self.preview2_adapter)
-            .expect("wasmtime_wasi is not compatible with threads")
+        &mut self.preview2_adapter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::keyvalue::atomic::Host as _;
+    use crate::capability::keyvalue::types::HostBucket as _;
+    use crate::capability::provider::MemoryKeyValue;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use wasmtime_wasi::WasiView;
+    use wasmtime_wasi_http::types::WasiHttpView;
+
+    #[tokio::test]
+    async fn clone_per_thread() -> anyhow::Result<()> {
+        let kv = Arc::new(MemoryKeyValue::from(HashMap::from([(
+            "bucket".into(),
+            HashMap::default(),
+        )])));
+        let mut handler = Handler::default();
+        handler.replace_keyvalue_atomic(kv);
+        let mut host = Host::new(handler);
+        let ctxs = Arc::new(AtomicUsize::new(0));
+        host.set_preview2_ctx({
+            let ctxs = Arc::clone(&ctxs);
+            Arc::new(move || {
+                ctxs.fetch_add(1, Ordering::Relaxed);
+                Ok(wasmtime_wasi::WasiCtxBuilder::new().build())
+            })
+        })?;
+        host.wasi_http = Some(WasiHttpCtx);
+        let bucket = host
+            .open_bucket("bucket".into())
+            .await?
+            .expect("failed to open bucket");
+
+        // Clones get their own resource table and WASI contexts, but share the
+        // providers
+        let mut clone = host.clone();
+        tokio::spawn(async move {
+            WasiView::ctx(&mut clone);
+            WasiHttpView::ctx(&mut clone);
+            let bucket = clone
+                .open_bucket("bucket".into())
+                .await?
+                .expect("failed to open bucket");
+            let n = clone
+                .increment(bucket, "n".into(), 2)
+                .await?
+                .expect("failed to increment");
+            assert_eq!(n, 2);
+            anyhow::Ok(())
+        })
+        .await??;
+        let n = host
+            .increment(bucket, "n".into(), 1)
+            .await?
+            .expect("failed to increment");
+        assert_eq!(n, 3);
+        WasiView::ctx(&mut host);
+        WasiHttpView::ctx(&mut host);
+        assert_eq!(ctxs.load(Ordering::Relaxed), 2);
+        Ok(())
     }
 }
@@ -0,0 +1,285 @@
+//! Declarative provider configuration
+//!
+//! A [Config] selects a provider and its settings for each capability
+//! interface and can be loaded from a TOML or JSON file, for example:
+//!
+//! ```toml
+//! [blobstore]
+//! provider = "fs"
+//! root = "/var/lib/wasmtime/blobstore"
+//!
+//! [keyvalue]
+//! provider = "memory"
+//! buckets = { "" = { foo = "bar" } }
+//!
+//! [links.archive.blobstore]
+//! provider = "s3"
+//! region = "us-east-1"
+//! ```
+
+use crate::capability::builtin::{Handler, HandlerBuilder};
+use crate::capability::provider::{
+    FsBlobstore, FsKeyValue, MemoryBlobstore, MemoryKeyValue, MemoryKeyValueEntry, MemoryMessaging,
+    S3Blobstore, S3BlobstoreConfig,
+};
+use crate::capability::{KeyValueAtomic, KeyValueEventual, Messaging, DEFAULT_LINK_NAME};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{ensure, Context, Result};
+use serde_derive::Deserialize;
+
+/// Provider configuration for all capability interfaces
+///
+/// Interfaces without a configured provider are left unset in the resulting
+/// [Handler], so guest calls to them fail.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// `wasi:blobstore/blobstore` provider
+    pub blobstore: Option<BlobstoreConfig>,
+    /// Provider shared by `wasi:keyvalue/atomic` and `wasi:keyvalue/eventual`,
+    /// unless overridden by [`Config::keyvalue_atomic`] or
+    /// [`Config::keyvalue_eventual`]
+    pub keyvalue: Option<KeyValueConfig>,
+    /// `wasi:keyvalue/atomic` provider
+    pub keyvalue_atomic: Option<KeyValueConfig>,
+    /// `wasi:keyvalue/eventual` provider
+    pub keyvalue_eventual: Option<KeyValueConfig>,
+    /// `wasmcloud:messaging/consumer` provider
+    pub messaging: Option<MessagingConfig>,
+    /// Providers of additional named links, which guests can select using
+    /// `wasmcloud:host/lattice.set-link-name`
+    pub links: HashMap<String, Config>,
+}
+
+/// `wasi:blobstore/blobstore` provider configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "provider", deny_unknown_fields, rename_all = "kebab-case")]
+pub enum BlobstoreConfig {
+    /// [MemoryBlobstore]
+    Memory,
+    /// [FsBlobstore]
+    Fs {
+        /// Root directory containing the containers
+        root: PathBuf,
+    },
+    /// [S3Blobstore]
+    S3(S3BlobstoreConfig),
+}
+
+/// `wasi:keyvalue` provider configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "provider", deny_unknown_fields, rename_all = "kebab-case")]
+pub enum KeyValueConfig {
+    /// [MemoryKeyValue]
+    Memory {
+        /// Initial bucket contents
+        #[serde(default)]
+        buckets: HashMap<String, HashMap<String, String>>,
+    },
+    /// [FsKeyValue]
+    Fs {
+        /// Directory containing the persistent log
+        root: PathBuf,
+    },
+}
+
+/// `wasmcloud:messaging` provider configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "provider", deny_unknown_fields, rename_all = "kebab-case")]
+pub enum MessagingConfig {
+    /// [MemoryMessaging]
+    Memory,
+}
+
+impl Config {
+    /// Load [Config] from a file, which is parsed as JSON if it has a `.json`
+    /// extension and as TOML otherwise
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let buf = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&buf)
+                .with_context(|| format!("failed to parse `{}` as JSON", path.display()))
+        } else {
+            toml::from_str(&buf)
+                .with_context(|| format!("failed to parse `{}` as TOML", path.display()))
+        }
+    }
+
+    /// Construct a [Handler] with the configured providers
+    pub async fn handler(&self) -> Result<Handler> {
+        let mut builder = HandlerBuilder::default();
+        self.configure(&mut builder, DEFAULT_LINK_NAME).await?;
+        for (name, link) in &self.links {
+            ensure!(
+                link.links.is_empty(),
+                "link `{name}` must not contain nested links"
+            );
+            link.configure(&mut builder, name)
+                .await
+                .with_context(|| format!("failed to configure link `{name}`"))?;
+        }
+        Ok(builder.into())
+    }
+
+    /// Register the configured providers in `builder` under link `name`
+    async fn configure(&self, builder: &mut HandlerBuilder, name: &str) -> Result<()> {
+        if let Some(config) = &self.blobstore {
+            let blobstore = config
+                .provider()
+                .await
+                .context("failed to construct blobstore provider")?;
+            builder.blobstore.insert(name.into(), blobstore);
+        }
+        if let Some(config) = &self.keyvalue {
+            let (atomic, eventual) = config
+                .provider()
+                .await
+                .context("failed to construct keyvalue provider")?;
+            builder.keyvalue_atomic.insert(name.into(), atomic);
+            builder.keyvalue_eventual.insert(name.into(), eventual);
+        }
+        if let Some(config) = &self.keyvalue_atomic {
+            let (atomic, _) = config
+                .provider()
+                .await
+                .context("failed to construct keyvalue atomic provider")?;
+            builder.keyvalue_atomic.insert(name.into(), atomic);
+        }
+        if let Some(config) = &self.keyvalue_eventual {
+            let (_, eventual) = config
+                .provider()
+                .await
+                .context("failed to construct keyvalue eventual provider")?;
+            builder.keyvalue_eventual.insert(name.into(), eventual);
+        }
+        if let Some(config) = &self.messaging {
+            builder.messaging.insert(name.into(), config.provider());
+        }
+        Ok(())
+    }
+}
+
+impl BlobstoreConfig {
+    async fn provider(&self) -> Result<Arc<dyn crate::capability::Blobstore + Send + Sync>> {
+        match self {
+            Self::Memory => Ok(Arc::new(MemoryBlobstore::default())),
+            Self::Fs { root } => Ok(Arc::new(FsBlobstore::new(root).await?)),
+            Self::S3(config) => Ok(Arc::new(S3Blobstore::with_config(config.clone()).await?)),
+        }
+    }
+}
+
+impl KeyValueConfig {
+    async fn provider(
+        &self,
+    ) -> Result<(
+        Arc<dyn KeyValueAtomic + Send + Sync>,
+        Arc<dyn KeyValueEventual + Send + Sync>,
+    )> {
+        match self {
+            Self::Memory { buckets } => {
+                let kv: MemoryKeyValue = buckets
+                    .iter()
+                    .map(|(name, entries)| {
+                        let entries: HashMap<_, _> = entries
+                            .iter()
+                            .map(|(k, v)| {
+                                (k.clone(), MemoryKeyValueEntry::Blob(v.as_bytes().to_vec()))
+                            })
+                            .collect();
+                        (name.clone(), entries)
+                    })
+                    .collect();
+                let kv = Arc::new(kv);
+                Ok((kv.clone(), kv))
+            }
+            Self::Fs { root } => {
+                let kv = Arc::new(FsKeyValue::new(root).await?);
+                Ok((kv.clone(), kv))
+            }
+        }
+    }
+}
+
+impl MessagingConfig {
+    fn provider(&self) -> Arc<dyn Messaging + Send + Sync> {
+        match self {
+            Self::Memory => Arc::new(MemoryMessaging::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_toml() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [blobstore]
+            provider = "s3"
+            endpoint-url = "http://localhost:9000"
+            region = "us-east-1"
+            force-path-style = true
+            multipart-threshold = 16777216
+            credentials = { static = { access-key-id = "minioadmin", secret-access-key = "minioadmin" } }
+
+            [keyvalue]
+            provider = "memory"
+            buckets = { "" = { foo = "bar" } }
+
+            [keyvalue-atomic]
+            provider = "fs"
+            root = "/var/lib/wasmtime/keyvalue"
+
+            [messaging]
+            provider = "memory"
+
+            [links.archive.blobstore]
+            provider = "fs"
+            root = "/var/lib/wasmtime/archive"
+            "#,
+        )?;
+        let Some(BlobstoreConfig::S3(s3)) = config.blobstore else {
+            panic!("unexpected blobstore config");
+        };
+        assert_eq!(s3.endpoint_url.as_deref(), Some("http://localhost:9000"));
+        assert!(s3.force_path_style);
+        assert_eq!(s3.multipart_threshold, Some(16 << 20));
+        assert!(matches!(
+            config.keyvalue,
+            Some(KeyValueConfig::Memory { .. })
+        ));
+        assert!(matches!(
+            config.keyvalue_atomic,
+            Some(KeyValueConfig::Fs { .. })
+        ));
+        assert!(config.keyvalue_eventual.is_none());
+        assert!(matches!(config.messaging, Some(MessagingConfig::Memory)));
+        assert!(matches!(
+            config.links["archive"].blobstore,
+            Some(BlobstoreConfig::Fs { .. })
+        ));
+
+        assert!(toml::from_str::<Config>("[blobstore]\nprovider = \"ftp\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_json() -> Result<()> {
+        let config: Config =
+            serde_json::from_str(r#"{ "blobstore": { "provider": "fs", "root": "/tmp/blobs" } }"#)?;
+        let Some(BlobstoreConfig::Fs { root }) = config.blobstore else {
+            panic!("unexpected blobstore config");
+        };
+        assert_eq!(root, Path::new("/tmp/blobs"));
+        Ok(())
+    }
+}
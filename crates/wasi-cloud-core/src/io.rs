@@ -1,10 +1,13 @@
+use core::future::Future;
 use core::pin::Pin;
-use core::task::{Context, Poll};
+use core::task::{ready, Context, Poll};
 
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::sync::{Arc, MutexGuard};
 
+use tokio::fs::File;
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
+use tokio::task::{spawn_blocking, JoinHandle};
 
 /// wasmCloud I/O functionality
 
@@ -82,3 +85,203 @@ impl AsyncSeek for AsyncVec {
         Pin::new(&mut *inner).poll_complete(cx)
     }
 }
+
+/// Default number of bytes [SpooledVec] keeps in memory before spilling to a
+/// temporary file
+pub const DEFAULT_SPOOL_LIMIT: usize = 1 << 20;
+
+#[derive(Debug)]
+enum Spool {
+    Memory(Cursor<Vec<u8>>),
+    File(File),
+}
+
+#[derive(Debug)]
+struct Spooled {
+    limit: usize,
+    spool: Spool,
+    /// Blocking task moving the buffered contents to a temporary file, which
+    /// returns the buffer back if that fails
+    spill: Option<JoinHandle<Result<std::fs::File, (Cursor<Vec<u8>>, std::io::Error)>>>,
+    /// Seek requested while the contents were being spilled
+    seek: Option<SeekFrom>,
+}
+
+impl Spooled {
+    /// Start moving the buffered contents to a temporary file if writing `n`
+    /// more bytes at the current position would exceed the limit
+    fn reserve(&mut self, n: usize) {
+        let Spool::Memory(buf) = &mut self.spool else {
+            return;
+        };
+        if self.spill.is_some() {
+            return;
+        }
+        let end = usize::try_from(buf.position())
+            .unwrap_or(usize::MAX)
+            .saturating_add(n);
+        if end <= self.limit {
+            return;
+        }
+        let buf = std::mem::take(buf);
+        self.spill = Some(spawn_blocking(move || {
+            let spill = || -> std::io::Result<_> {
+                let mut file = tempfile::tempfile()?;
+                file.write_all(buf.get_ref())?;
+                file.seek(SeekFrom::Start(buf.position()))?;
+                Ok(file)
+            };
+            spill().map_err(|e| (buf, e))
+        }));
+    }
+
+    /// Wait for the contents to be moved to a temporary file, if a spill is
+    /// in progress, and apply a seek requested in the meantime
+    fn poll_spill(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if let Some(spill) = &mut self.spill {
+            let res = ready!(Pin::new(spill).poll(cx));
+            self.spill = None;
+            match res.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))? {
+                Ok(file) => self.spool = Spool::File(File::from_std(file)),
+                Err((buf, err)) => {
+                    self.spool = Spool::Memory(buf);
+                    return Poll::Ready(Err(err));
+                }
+            }
+        }
+        if let Some(pos) = self.seek.take() {
+            match &mut self.spool {
+                Spool::Memory(inner) => Pin::new(inner).start_seek(pos)?,
+                Spool::File(inner) => Pin::new(inner).start_seek(pos)?,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Buffer, which keeps up to a limit of bytes in memory and spills to an
+/// anonymous temporary file once the limit is exceeded, so that arbitrarily
+/// large values can be buffered in constant memory
+#[derive(Clone, Debug)]
+pub struct SpooledVec(Arc<std::sync::Mutex<Spooled>>);
+
+impl Default for SpooledVec {
+    fn default() -> Self {
+        Self::new(DEFAULT_SPOOL_LIMIT)
+    }
+}
+
+impl SpooledVec {
+    /// Construct an empty [SpooledVec] keeping at most `limit` bytes in memory
+    pub fn new(limit: usize) -> Self {
+        Self(Arc::new(std::sync::Mutex::new(Spooled {
+            limit,
+            spool: Spool::Memory(Cursor::default()),
+            spill: None,
+            seek: None,
+        })))
+    }
+
+    /// Whether the contents have been spilled to a temporary file
+    pub fn is_spilled(&self) -> bool {
+        self.inner()
+            .map(|inner| inner.spill.is_some() || matches!(inner.spool, Spool::File(..)))
+            .unwrap_or_default()
+    }
+
+    fn inner(&self) -> std::io::Result<MutexGuard<'_, Spooled>> {
+        self.0
+            .lock()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+    }
+}
+
+impl AsyncWrite for SpooledVec {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let mut inner = self.inner()?;
+        inner.reserve(buf.len());
+        ready!(inner.poll_spill(cx))?;
+        match &mut inner.spool {
+            Spool::Memory(inner) => Pin::new(inner).poll_write(cx, buf),
+            Spool::File(inner) => Pin::new(inner).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let mut inner = self.inner()?;
+        ready!(inner.poll_spill(cx))?;
+        match &mut inner.spool {
+            Spool::Memory(inner) => Pin::new(inner).poll_flush(cx),
+            Spool::File(inner) => Pin::new(inner).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncRead for SpooledVec {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let mut inner = self.inner()?;
+        ready!(inner.poll_spill(cx))?;
+        match &mut inner.spool {
+            Spool::Memory(inner) => Pin::new(inner).poll_read(cx, buf),
+            Spool::File(inner) => Pin::new(inner).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncSeek for SpooledVec {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let mut inner = self.inner()?;
+        if inner.spill.is_some() {
+            // Applied once the spill completes in `poll_complete`
+            inner.seek = Some(position);
+            return Ok(());
+        }
+        match &mut inner.spool {
+            Spool::Memory(inner) => Pin::new(inner).start_seek(position),
+            Spool::File(inner) => Pin::new(inner).start_seek(position),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        let mut inner = self.inner()?;
+        ready!(inner.poll_spill(cx))?;
+        match &mut inner.spool {
+            Spool::Memory(inner) => Pin::new(inner).poll_complete(cx),
+            Spool::File(inner) => Pin::new(inner).poll_complete(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpooledVec;
+
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn spool() -> std::io::Result<()> {
+        let mut buf = SpooledVec::new(4);
+        buf.write_all(b"foo").await?;
+        assert!(!buf.is_spilled());
+        buf.write_all(b"bar").await?;
+        assert!(buf.is_spilled());
+        buf.write_all(b"baz").await?;
+        buf.rewind().await?;
+        let mut data = String::new();
+        buf.read_to_string(&mut data).await?;
+        assert_eq!(data, "foobarbaz");
+        Ok(())
+    }
+}
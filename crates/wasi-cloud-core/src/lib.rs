@@ -4,11 +4,19 @@
 #![warn(missing_docs)]
 #![forbid(clippy::unwrap_used)]
 
+pub mod actor;
+
 pub mod component;
 
 /// Capability provider implementations and adaptors
 pub mod capability;
 
+pub mod claims;
+
+pub mod config;
+
+pub mod trigger;
+
 /// wasmCloud I/O functionality
 pub mod io;
 
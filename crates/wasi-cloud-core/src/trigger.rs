@@ -0,0 +1,357 @@
+//! Triggers invoking component exports in response to external events
+
+use crate::capability::messaging::types::BrokerMessage;
+use crate::capability::{Messaging, MessagingHandler};
+use crate::component::Host;
+
+use core::fmt::Debug;
+
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context, Result};
+use futures::{stream, StreamExt};
+use serde_derive::Serialize;
+use tokio::sync::Semaphore;
+use tracing::{error, instrument, trace};
+use wasmtime::component::InstancePre;
+use wasmtime::{Engine, Store};
+
+/// Default maximum number of messages handled concurrently
+pub const DEFAULT_MAX_CONCURRENCY: usize = 100;
+
+/// Constructor of a fresh [Store] for each handled message
+pub type NewStore = Arc<dyn Fn(&Engine) -> Result<Store<Host>> + Send + Sync>;
+
+/// Message published to the dead-letter subject of a [MessagingTrigger] for
+/// each message, which could not be handled
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DeadLetter {
+    /// Subject the message was received on
+    pub subject: String,
+    /// Reply subject of the message
+    pub reply_to: Option<String>,
+    /// Body of the message
+    pub body: Option<Vec<u8>>,
+    /// Reason the message could not be handled
+    pub error: String,
+}
+
+/// Trigger delivering messages received on subscribed subjects to a
+/// component exporting `wasmcloud:messaging/handler`
+///
+/// Each message is handled in a fresh instance created from a shared
+/// [InstancePre], with at most [`MessagingTrigger::max_concurrency`]
+/// messages handled at once. Messages, which fail to be handled, are
+/// reported to the dead-letter subject, if one is configured.
+#[derive(Clone)]
+pub struct MessagingTrigger {
+    engine: Engine,
+    pre: InstancePre<Host>,
+    new_store: NewStore,
+    messaging: Arc<dyn Messaging + Send + Sync>,
+    subjects: Vec<String>,
+    max_concurrency: usize,
+    dead_letter_subject: Option<String>,
+}
+
+impl Debug for MessagingTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessagingTrigger")
+            .field("subjects", &self.subjects)
+            .field("max_concurrency", &self.max_concurrency)
+            .field("dead_letter_subject", &self.dead_letter_subject)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MessagingTrigger {
+    /// Construct a new [MessagingTrigger] instantiating `pre` in stores
+    /// returned by `new_store` and receiving messages from `messaging`
+    pub fn new(
+        engine: Engine,
+        pre: InstancePre<Host>,
+        new_store: NewStore,
+        messaging: Arc<dyn Messaging + Send + Sync>,
+    ) -> Self {
+        Self {
+            engine,
+            pre,
+            new_store,
+            messaging,
+            subjects: Vec::default(),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            dead_letter_subject: None,
+        }
+    }
+
+    /// Subscribe to `subject`, which may contain wildcards supported by the
+    /// [Messaging] provider
+    #[must_use]
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subjects.push(subject.into());
+        self
+    }
+
+    /// Set the maximum number of messages handled concurrently
+    #[must_use]
+    pub fn max_concurrency(self, max_concurrency: usize) -> Self {
+        Self {
+            max_concurrency,
+            ..self
+        }
+    }
+
+    /// Publish a [DeadLetter] encoded as JSON to `subject` for each message,
+    /// which could not be handled
+    #[must_use]
+    pub fn dead_letter_subject(self, subject: impl Into<String>) -> Self {
+        Self {
+            dead_letter_subject: Some(subject.into()),
+            ..self
+        }
+    }
+
+    /// Handle a single message in a fresh instance
+    #[instrument(level = "trace", skip(self, msg), fields(subject = msg.subject))]
+    pub async fn handle_message(&self, msg: &BrokerMessage) -> Result<()> {
+        let mut store = (self.new_store)(&self.engine).context("failed to construct store")?;
+        store.limiter(|host| &mut host.limits);
+        let (handler, _) = MessagingHandler::instantiate_pre(&mut store, &self.pre)
+            .await
+            .context("failed to instantiate component")?;
+        match handler
+            .wasmcloud_messaging_handler()
+            .call_handle_message(&mut store, msg)
+            .await
+            .context("failed to call `wasmcloud:messaging/handler.handle-message`")?
+        {
+            Ok(()) => Ok(()),
+            Err(err) => bail!(err),
+        }
+    }
+
+    /// Report a message, which could not be handled
+    async fn dead_letter(&self, msg: BrokerMessage, err: &anyhow::Error) {
+        error!(
+            subject = msg.subject,
+            error = format!("{err:#}"),
+            "failed to handle message"
+        );
+        let Some(subject) = &self.dead_letter_subject else {
+            return;
+        };
+        let letter = DeadLetter {
+            subject: msg.subject,
+            reply_to: msg.reply_to,
+            body: msg.body,
+            error: format!("{err:#}"),
+        };
+        let body = match serde_json::to_vec(&letter) {
+            Ok(body) => body,
+            Err(err) => {
+                error!(?err, "failed to encode dead letter");
+                return;
+            }
+        };
+        if let Err(err) = self
+            .messaging
+            .publish(BrokerMessage {
+                subject: subject.clone(),
+                body: Some(body),
+                reply_to: None,
+            })
+            .await
+        {
+            error!(error = format!("{err:#}"), "failed to publish dead letter");
+        }
+    }
+
+    /// Subscribe to all configured subjects and handle received messages until
+    /// all subscriptions are closed
+    #[instrument(skip(self))]
+    pub async fn run(self) -> Result<()> {
+        ensure!(!self.subjects.is_empty(), "no subjects to subscribe to");
+        ensure!(
+            self.max_concurrency > 0,
+            "maximum concurrency must be non-zero"
+        );
+        let max_concurrency =
+            u32::try_from(self.max_concurrency).context("maximum concurrency is too large")?;
+
+        let mut subscriptions = Vec::with_capacity(self.subjects.len());
+        for subject in &self.subjects {
+            let sub = self
+                .messaging
+                .subscribe(subject.clone())
+                .await
+                .with_context(|| format!("failed to subscribe to `{subject}`"))?;
+            subscriptions.push(sub);
+        }
+        let mut msgs = stream::select_all(subscriptions);
+
+        let permits = Arc::new(Semaphore::new(self.max_concurrency));
+        let trigger = Arc::new(self);
+        while let Some(msg) = msgs.next().await {
+            let permit = Arc::clone(&permits)
+                .acquire_owned()
+                .await
+                .context("failed to acquire permit")?;
+            let trigger = Arc::clone(&trigger);
+            tokio::spawn(async move {
+                trace!(subject = msg.subject, "handling message");
+                if let Err(err) = trigger.handle_message(&msg).await {
+                    trigger.dead_letter(msg, &err).await;
+                }
+                drop(permit);
+            });
+        }
+        // Wait for in-flight messages to be handled
+        let _ = permits
+            .acquire_many(max_concurrency)
+            .await
+            .context("failed to acquire permits")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::provider::MemoryMessaging;
+    use crate::capability::{add_interfaces_to_linker, Handler, TargetInterface};
+
+    use std::time::Duration;
+
+    use wasmtime::component::{Component, Linker};
+
+    /// Handler replying to each message with its body, failing messages
+    /// without a reply subject
+    const ECHO: &str = r#"
+        (component
+            (import "wasmcloud:messaging/consumer" (instance $consumer
+                (type $msg' (record
+                    (field "subject" string)
+                    (field "body" (option (list u8)))
+                    (field "reply-to" (option string))))
+                (export $msg "broker-message" (type (eq $msg')))
+                (export "publish" (func (param "msg" $msg) (result (result (error string)))))))
+            (alias export $consumer "broker-message" (type $msg))
+
+            (core module $libc
+                (memory (export "memory") 1)
+                (global $heap (mut i32) (i32.const 1024))
+                (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                    (local $ret i32)
+                    (local.set $ret (i32.and
+                        (i32.add (global.get $heap) (i32.sub (local.get 2) (i32.const 1)))
+                        (i32.sub (i32.const 0) (local.get 2))))
+                    (global.set $heap (i32.add (local.get $ret) (local.get 3)))
+                    (local.get $ret)))
+            (core instance $libc (instantiate $libc))
+            (core func $publish (canon lower (func $consumer "publish")
+                (memory $libc "memory") (realloc (func $libc "realloc"))))
+
+            (core module $m
+                (import "libc" "memory" (memory 1))
+                (import "consumer" "publish"
+                    (func $publish (param i32 i32 i32 i32 i32 i32 i32 i32 i32)))
+                (data (i32.const 16) "missing reply subject")
+                (func (export "handle")
+                    (param $subject i32) (param $subject_len i32)
+                    (param $body i32) (param $body_ptr i32) (param $body_len i32)
+                    (param $reply i32) (param $reply_ptr i32) (param $reply_len i32)
+                    (result i32)
+                    (if (i32.eqz (local.get $reply))
+                        (then
+                            (i32.store (i32.const 48) (i32.const 1))
+                            (i32.store (i32.const 52) (i32.const 16))
+                            (i32.store (i32.const 56) (i32.const 21))
+                            (return (i32.const 48))))
+                    (call $publish
+                        (local.get $reply_ptr) (local.get $reply_len)
+                        (local.get $body) (local.get $body_ptr) (local.get $body_len)
+                        (i32.const 0) (i32.const 0) (i32.const 0)
+                        (i32.const 48))
+                    (i32.const 48)))
+            (core instance $i (instantiate $m
+                (with "libc" (instance $libc))
+                (with "consumer" (instance (export "publish" (func $publish))))))
+
+            (func $handle (param "msg" $msg) (result (result (error string)))
+                (canon lift (core func $i "handle")
+                    (memory $libc "memory") (realloc (func $libc "realloc"))))
+            (instance $handler
+                (export "broker-message" (type $msg))
+                (export "handle-message" (func $handle)))
+            (export "wasmcloud:messaging/handler" (instance $handler)))
+    "#;
+
+    #[tokio::test]
+    async fn request_reply() -> Result<()> {
+        let mut config = wasmtime::Config::new();
+        config.async_support(true).wasm_component_model(true);
+        let engine = Engine::new(&config)?;
+        let broker = Arc::new(MemoryMessaging::default());
+
+        let mut linker = Linker::new(&engine);
+        add_interfaces_to_linker(
+            &mut linker,
+            |host| host,
+            &[TargetInterface::WasmcloudMessagingConsumer],
+        )?;
+        let component = Component::new(&engine, wat::parse_str(ECHO)?)?;
+        let pre = linker.instantiate_pre(&component)?;
+        let new_store: NewStore = {
+            let broker = Arc::clone(&broker);
+            Arc::new(move |engine| {
+                let mut handler = Handler::default();
+                handler.replace_messaging(broker.clone());
+                Ok(Store::new(engine, Host::new(handler)))
+            })
+        };
+        let trigger = MessagingTrigger::new(engine, pre, new_store, broker.clone())
+            .subject("echo")
+            .dead_letter_subject("dead");
+        let mut dead = broker.subscribe("dead").await?;
+        let run = tokio::spawn(trigger.run());
+
+        // Requests fail until the trigger has subscribed
+        let mut reply = None;
+        for _ in 0..100 {
+            match broker
+                .request(
+                    "echo".into(),
+                    Some(b"ping".to_vec()),
+                    Duration::from_secs(5),
+                )
+                .await
+            {
+                Ok(msg) => {
+                    reply = Some(msg);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+        let reply = reply.context("no reply received")?;
+        assert_eq!(reply.body.as_deref(), Some(&b"ping"[..]));
+
+        // Messages failing to be handled are published as dead letters
+        broker
+            .publish(BrokerMessage {
+                subject: "echo".into(),
+                body: None,
+                reply_to: None,
+            })
+            .await?;
+        let letter = dead.next_message().await.context("no dead letter")?;
+        let letter: serde_json::Value = serde_json::from_slice(&letter.body.unwrap_or_default())?;
+        assert_eq!(letter["subject"], "echo");
+        assert_eq!(letter["error"], "missing reply subject");
+
+        run.abort();
+        Ok(())
+    }
+}
@@ -122,6 +122,11 @@ fn api_proxy() {}
 #[allow(dead_code)]
 fn api_proxy_streaming() {}
 
+// This is tested in the CLI tests, but need to satisfy the `foreach_api!`
+// macro above.
+#[allow(dead_code)]
+fn api_proxy_keyvalue_counter() {}
+
 wasmtime::component::bindgen!({
     world: "test-reactor",
     async: true,
@@ -233,6 +233,10 @@
 //!   a core dump when a trap happens. This can be configured via
 //!   [`Config::coredump_on_trap`].
 //!
+//! * `preinit` - Enabled by default, this feature adds support for running a
+//!   module's initialization function ahead of time and snapshotting the
+//!   result into a new module with [`Engine::preinitialize`].
+//!
 //! * `addr2line` - Enabled by default, this feature configures whether traps
 //!   will attempt to parse DWARF debug information and convert WebAssembly
 //!   addresses to source filenames and line numbers.
@@ -3,6 +3,7 @@
 #[macro_use]
 pub(crate) mod func;
 
+pub(crate) mod checkpoint;
 pub(crate) mod code;
 pub(crate) mod code_memory;
 pub(crate) mod debug;
@@ -34,6 +35,9 @@ pub(crate) mod stack;
 #[cfg(feature = "coredump")]
 mod coredump;
 
+#[cfg(all(feature = "preinit", any(feature = "cranelift", feature = "winch")))]
+mod preinit;
+
 cfg_if::cfg_if! {
     if #[cfg(miri)] {
         // no extensions on miri
@@ -46,6 +50,7 @@ cfg_if::cfg_if! {
     }
 }
 
+pub use checkpoint::Checkpoint;
 pub use code_memory::CodeMemory;
 pub use externals::*;
 pub use func::*;
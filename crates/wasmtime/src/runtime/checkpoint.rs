@@ -0,0 +1,596 @@
+//! Capturing and restoring the mutable state of a store's instances.
+
+use crate::store::{InstanceId, StoreOpaque};
+use crate::{Memory, Ref, StoreContextMut, Table};
+use anyhow::{bail, ensure, Context, Result};
+use once_cell::sync::OnceCell;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use wasmtime_environ::{
+    EntityRef, FuncIndex, Module, TableIndex, WasmHeapType, WasmRefType, WasmValType,
+    WASM_PAGE_SIZE,
+};
+use wasmtime_runtime::{ExportGlobal, MemoryImage, TableElement, VMFuncRef};
+
+/// Version byte at the start of serialized checkpoints, bumped whenever the
+/// format changes.
+const VERSION: u8 = 0;
+
+/// A snapshot of the mutable state of all instances within a
+/// [`Store`](crate::Store).
+///
+/// Checkpoints are created with [`Store::checkpoint`](crate::Store::checkpoint)
+/// and contain the contents of each instance's defined linear memories and
+/// tables along with the values of its mutable globals. A checkpoint can be
+/// applied to a new store with [`Store::restore`](crate::Store::restore) once
+/// the same modules have been instantiated in it, in the same order, to resume
+/// from the captured state without re-running any initialization.
+///
+/// Restoring memories maps the captured contents copy-on-write where the
+/// platform supports it, so restoring the same `Checkpoint` into many stores
+/// doesn't copy memory contents each time. Checkpoints can also be persisted
+/// with [`Checkpoint::serialize`] and later loaded with
+/// [`Checkpoint::deserialize`].
+///
+/// Only module instances are captured: memories, tables and globals created by
+/// the host, such as with [`Memory::new`], are not part of a checkpoint, and
+/// neither is whether passive data and element segments have been dropped.
+/// Checkpoints can't capture shared memories, non-null `externref` values or
+/// references to functions that aren't defined or imported by one of the
+/// store's instances.
+pub struct Checkpoint {
+    pub(crate) instances: Vec<InstanceState>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct InstanceState {
+    /// The number of functions in the instance's module, used as a sanity
+    /// check that a checkpoint is restored into the same module.
+    functions: u32,
+    pub(crate) memories: Vec<MemoryState>,
+    pub(crate) tables: Vec<Vec<Option<FuncRef>>>,
+    /// The values of the instance's defined globals, which are `None` for
+    /// immutable globals.
+    pub(crate) globals: Vec<Option<Value>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MemoryState {
+    pub(crate) data: Vec<u8>,
+    /// The copy-on-write image of `data`, created on first restore.
+    #[serde(skip)]
+    image: OnceCell<Option<Arc<MemoryImage>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Value {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    V128(u128),
+    Ref(Option<FuncRef>),
+}
+
+/// A function reference, identified by the index of the instance it's
+/// defined or imported in and its index within that instance.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub(crate) struct FuncRef {
+    pub(crate) instance: u32,
+    pub(crate) func: u32,
+}
+
+impl fmt::Debug for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Checkpoint")
+            .field("instances", &self.instances.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MemoryState {
+    /// Returns the copy-on-write image of `data`, if the platform supports
+    /// one, creating it on first use.
+    fn image(&self) -> Result<Option<&Arc<MemoryImage>>> {
+        let image = self.image.get_or_try_init(|| {
+            Ok::<_, anyhow::Error>(MemoryImage::from_contents(&self.data)?.map(Arc::new))
+        })?;
+        Ok(image.as_ref())
+    }
+}
+
+impl Checkpoint {
+    /// Serializes this checkpoint into a list of bytes which can be turned
+    /// back into a `Checkpoint` with [`Checkpoint::deserialize`].
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![VERSION];
+        bincode::serialize_into(&mut bytes, &self.instances)?;
+        Ok(bytes)
+    }
+
+    /// Deserializes a checkpoint previously created with
+    /// [`Checkpoint::serialize`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` isn't a checkpoint serialized by a
+    /// compatible version of Wasmtime.
+    pub fn deserialize(bytes: &[u8]) -> Result<Checkpoint> {
+        let (version, rest) = bytes.split_first().context("checkpoint data is empty")?;
+        ensure!(
+            *version == VERSION,
+            "checkpoint format version {version} is not supported"
+        );
+        let instances = bincode::deserialize(rest).context("failed to deserialize checkpoint")?;
+        Ok(Checkpoint { instances })
+    }
+}
+
+pub(crate) fn checkpoint<T>(store: StoreContextMut<'_, T>) -> Result<Checkpoint> {
+    let ids = instance_ids(store.0);
+    let funcs = func_refs(store.0, &ids);
+
+    let mut instances = Vec::with_capacity(ids.len());
+    for (i, id) in ids.iter().enumerate() {
+        let handle = store.0.instance_mut(*id);
+        let module = handle.module().clone();
+        let memories = handle.defined_memories().collect::<Vec<_>>();
+        let globals = handle.defined_globals().map(|(_, g)| g).collect::<Vec<_>>();
+
+        let memories = memories
+            .into_iter()
+            .enumerate()
+            .map(|(j, export)| {
+                if export.memory.memory.shared {
+                    bail!("cannot checkpoint shared memory {j} of instance {i}");
+                }
+                let memory = unsafe { Memory::from_wasmtime_memory(export, store.0) };
+                Ok(MemoryState {
+                    data: memory.data(&store).to_vec(),
+                    image: OnceCell::new(),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let tables = defined_tables(store.0, *id, &module)
+            .into_iter()
+            .enumerate()
+            .map(|(j, table)| {
+                checkpoint_table(store.0, table, &funcs)
+                    .with_context(|| format!("cannot checkpoint table {j} of instance {i}"))
+            })
+            .collect::<Result<_>>()?;
+
+        let globals = globals
+            .iter()
+            .enumerate()
+            .map(|(j, global)| {
+                checkpoint_global(global, &funcs)
+                    .with_context(|| format!("cannot checkpoint global {j} of instance {i}"))
+            })
+            .collect::<Result<_>>()?;
+
+        instances.push(InstanceState {
+            functions: u32::try_from(module.functions.len()).unwrap(),
+            memories,
+            tables,
+            globals,
+        });
+    }
+
+    Ok(Checkpoint { instances })
+}
+
+pub(crate) fn restore<T>(mut store: StoreContextMut<'_, T>, checkpoint: &Checkpoint) -> Result<()> {
+    let ids = instance_ids(store.0);
+    ensure!(
+        ids.len() == checkpoint.instances.len(),
+        "checkpoint contains {} instances but the store contains {}",
+        checkpoint.instances.len(),
+        ids.len(),
+    );
+
+    // Validate everything up front so that a mismatched checkpoint doesn't
+    // leave the store partially restored.
+    let use_image = store.engine().config().memory_init_cow;
+    let mut instances = Vec::with_capacity(ids.len());
+    for (i, (id, state)) in ids.iter().zip(&checkpoint.instances).enumerate() {
+        let handle = store.0.instance_mut(*id);
+        let module = handle.module().clone();
+        ensure!(
+            state.functions == u32::try_from(module.functions.len()).unwrap()
+                && state.memories.len() == module.memory_plans.len() - module.num_imported_memories
+                && state.tables.len() == module.table_plans.len() - module.num_imported_tables
+                && state.globals.len() == module.globals.len() - module.num_imported_globals,
+            "instance {i} in the store was not created from the same module as in the checkpoint"
+        );
+        let memories = handle.defined_memories().collect::<Vec<_>>();
+        let globals = handle.defined_globals().map(|(_, g)| g).collect::<Vec<_>>();
+
+        let memories = memories
+            .into_iter()
+            .zip(&state.memories)
+            .enumerate()
+            .map(|(j, (export, state))| {
+                if export.memory.memory.shared {
+                    bail!("cannot restore shared memory {j} of instance {i}");
+                }
+                let memory = unsafe { Memory::from_wasmtime_memory(export, store.0) };
+                validate_memory(store.0, memory, state, use_image)
+                    .with_context(|| format!("cannot restore memory {j} of instance {i}"))?;
+                Ok(memory)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let tables = defined_tables(store.0, *id, &module);
+        for (j, (table, elements)) in tables.iter().zip(&state.tables).enumerate() {
+            validate_table(store.0, *table, elements, &ids)
+                .with_context(|| format!("cannot restore table {j} of instance {i}"))?;
+        }
+
+        for (j, (global, value)) in globals.iter().zip(&state.globals).enumerate() {
+            validate_global(store.0, global, value.as_ref(), &ids)
+                .with_context(|| format!("cannot restore global {j} of instance {i}"))?;
+        }
+
+        instances.push((memories, tables, globals));
+    }
+
+    // Growing memories and tables can still fail, for example when the
+    // store's `ResourceLimiter` denies it, so all of them are grown before any
+    // state is written and a failure only leaves some of them larger.
+    for (i, ((memories, tables, _), state)) in
+        instances.iter().zip(&checkpoint.instances).enumerate()
+    {
+        for (j, (memory, state)) in memories.iter().zip(&state.memories).enumerate() {
+            let delta = (state.data.len() - memory.data_size(&store)) / WASM_PAGE_SIZE as usize;
+            if delta > 0 {
+                memory
+                    .grow(&mut store, u64::try_from(delta).unwrap())
+                    .with_context(|| format!("failed to grow memory {j} of instance {i}"))?;
+            }
+        }
+        for (j, (table, elements)) in tables.iter().zip(&state.tables).enumerate() {
+            let delta = u32::try_from(elements.len()).unwrap() - table.size(&store);
+            if delta > 0 {
+                let null = match table.wasmtime_ty(store.0.store_data()).wasm_ty.heap_type {
+                    WasmHeapType::Extern => Ref::Extern(None),
+                    _ => Ref::Func(None),
+                };
+                table
+                    .grow(&mut store, delta, null)
+                    .with_context(|| format!("failed to grow table {j} of instance {i}"))?;
+            }
+        }
+    }
+
+    // Only mapping a memory image, which fails if the OS runs out of
+    // resources, can fail from here on.
+    for (i, ((memories, tables, globals), state)) in
+        instances.iter().zip(&checkpoint.instances).enumerate()
+    {
+        for (j, (memory, state)) in memories.iter().zip(&state.memories).enumerate() {
+            restore_memory(&mut store, *memory, state, use_image)
+                .with_context(|| format!("failed to restore memory {j} of instance {i}"))?;
+        }
+        for (table, elements) in tables.iter().zip(&state.tables) {
+            restore_table(store.0, *table, elements, &ids);
+        }
+        for (global, value) in globals.iter().zip(&state.globals) {
+            restore_global(store.0, global, value.as_ref(), &ids);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the ids of all module instances in `store`, in the order they were
+/// created.
+fn instance_ids(store: &mut StoreOpaque) -> Vec<InstanceId> {
+    let instances = store.all_instances().collect::<Vec<_>>();
+    instances.iter().map(|i| i.id(store)).collect()
+}
+
+fn defined_tables(store: &mut StoreOpaque, id: InstanceId, module: &Module) -> Vec<Table> {
+    (module.num_imported_tables..module.table_plans.len())
+        .map(|i| {
+            let export = store
+                .instance_mut(id)
+                .get_exported_table(TableIndex::new(i));
+            unsafe { Table::from_wasmtime_table(export, store) }
+        })
+        .collect()
+}
+
+/// Maps the address of every function reference that one of `ids` can hand
+/// out to the `FuncRef` which identifies it.
+fn func_refs(store: &mut StoreOpaque, ids: &[InstanceId]) -> HashMap<usize, FuncRef> {
+    let mut funcs = HashMap::new();
+    for (i, id) in ids.iter().enumerate() {
+        let handle = store.instance_mut(*id);
+        let module = handle.module().clone();
+        for (index, func) in module.functions.iter() {
+            if !func.is_escaping() {
+                continue;
+            }
+            let func_ref = handle.get_exported_func(index).func_ref;
+            funcs.insert(
+                func_ref.as_ptr() as usize,
+                FuncRef {
+                    instance: u32::try_from(i).unwrap(),
+                    func: index.as_u32(),
+                },
+            );
+        }
+    }
+    funcs
+}
+
+/// Resolves `func` to the function reference it identifies in the store
+/// containing the instances `ids`.
+fn resolve_func_ref(
+    store: &mut StoreOpaque,
+    func: FuncRef,
+    ids: &[InstanceId],
+) -> Result<*mut VMFuncRef> {
+    let id = usize::try_from(func.instance)
+        .ok()
+        .and_then(|i| ids.get(i))
+        .with_context(|| format!("function reference to unknown instance {}", func.instance))?;
+    let handle = store.instance_mut(*id);
+    let index = FuncIndex::from_u32(func.func);
+    match handle.module().functions.get(index) {
+        Some(f) if f.is_escaping() => {}
+        _ => bail!(
+            "function reference to unknown function {} of instance {}",
+            func.func,
+            func.instance
+        ),
+    }
+    Ok(handle.get_exported_func(index).func_ref.as_ptr())
+}
+
+/// Checks that references of type `ty` can be captured in a checkpoint.
+fn ensure_supported_ref(ty: &WasmRefType) -> Result<()> {
+    match ty.heap_type {
+        WasmHeapType::Func | WasmHeapType::Extern | WasmHeapType::NoFunc if ty.nullable => Ok(()),
+        _ => bail!("typed function references are not supported"),
+    }
+}
+
+fn checkpoint_table(
+    store: &mut StoreOpaque,
+    table: Table,
+    funcs: &HashMap<usize, FuncRef>,
+) -> Result<Vec<Option<FuncRef>>> {
+    ensure_supported_ref(&table.wasmtime_ty(store.store_data()).wasm_ty)?;
+    let size = table.internal_size(store);
+    let table = table.wasmtime_table(store, 0..size);
+    (0..size)
+        .map(|i| match unsafe { (*table).get(i) } {
+            Some(TableElement::FuncRef(f)) => checkpoint_func_ref(f, funcs),
+            Some(TableElement::ExternRef(None)) => Ok(None),
+            Some(TableElement::ExternRef(Some(_))) => {
+                bail!("non-null `externref` values are not supported")
+            }
+            Some(TableElement::UninitFunc) | None => unreachable!(),
+        })
+        .collect()
+}
+
+fn checkpoint_global(
+    global: &ExportGlobal,
+    funcs: &HashMap<usize, FuncRef>,
+) -> Result<Option<Value>> {
+    if !global.global.mutability {
+        return Ok(None);
+    }
+    let definition = unsafe { &*global.definition };
+    let value = unsafe {
+        match global.global.wasm_ty {
+            WasmValType::I32 => Value::I32(*definition.as_i32()),
+            WasmValType::I64 => Value::I64(*definition.as_i64()),
+            WasmValType::F32 => Value::F32(*definition.as_u32()),
+            WasmValType::F64 => Value::F64(*definition.as_u64()),
+            WasmValType::V128 => Value::V128(*definition.as_u128()),
+            WasmValType::Ref(ty) => {
+                ensure_supported_ref(&ty)?;
+                match ty.heap_type {
+                    WasmHeapType::Extern => {
+                        ensure!(
+                            definition.as_externref().is_none(),
+                            "non-null `externref` values are not supported"
+                        );
+                        Value::Ref(None)
+                    }
+                    _ => Value::Ref(checkpoint_func_ref(definition.as_func_ref(), funcs)?),
+                }
+            }
+        }
+    };
+    Ok(Some(value))
+}
+
+fn checkpoint_func_ref(
+    func_ref: *mut VMFuncRef,
+    funcs: &HashMap<usize, FuncRef>,
+) -> Result<Option<FuncRef>> {
+    if func_ref.is_null() {
+        return Ok(None);
+    }
+    match funcs.get(&(func_ref as usize)) {
+        Some(func) => Ok(Some(*func)),
+        None => bail!("references to host-defined functions are not supported"),
+    }
+}
+
+/// Checks that `state` can be restored into `memory`, which can only grow, and
+/// creates its image if `use_image` is set.
+fn validate_memory(
+    store: &StoreOpaque,
+    memory: Memory,
+    state: &MemoryState,
+    use_image: bool,
+) -> Result<()> {
+    let size = memory.internal_data_size(store);
+    let page_size = usize::try_from(WASM_PAGE_SIZE).unwrap();
+    ensure!(
+        state.data.len() % page_size == 0 && size <= state.data.len(),
+        "cannot restore {} bytes into a memory of {size} bytes",
+        state.data.len(),
+    );
+    let pages = u64::try_from(state.data.len() / page_size).unwrap();
+    if let Some(maximum) = memory.wasmtime_ty(store.store_data()).maximum {
+        ensure!(
+            pages <= maximum,
+            "cannot restore {pages} pages into a memory with a maximum of {maximum} pages"
+        );
+    }
+    if use_image {
+        state.image()?;
+    }
+    Ok(())
+}
+
+/// Writes `state` into `memory`, which has already been grown to its size.
+fn restore_memory<T>(
+    store: &mut StoreContextMut<'_, T>,
+    memory: Memory,
+    state: &MemoryState,
+    use_image: bool,
+) -> Result<()> {
+    let image = if use_image { state.image()? } else { None };
+    if let Some(image) = image {
+        let mem = memory.wasmtime_memory(store.0);
+        if unsafe { (*mem).restore_image(image)? } {
+            return Ok(());
+        }
+    }
+
+    memory.data_mut(&mut *store).copy_from_slice(&state.data);
+    Ok(())
+}
+
+/// Checks that `elements` can be restored into `table`, which can only grow.
+fn validate_table(
+    store: &mut StoreOpaque,
+    table: Table,
+    elements: &[Option<FuncRef>],
+    ids: &[InstanceId],
+) -> Result<()> {
+    let ty = *table.wasmtime_ty(store.store_data());
+    ensure_supported_ref(&ty.wasm_ty)?;
+
+    let size = table.internal_size(store);
+    let len = u32::try_from(elements.len()).context("too many table elements")?;
+    ensure!(
+        size <= len,
+        "cannot restore {len} elements into a table of {size} elements"
+    );
+    if let Some(maximum) = ty.maximum {
+        ensure!(
+            len <= maximum,
+            "cannot restore {len} elements into a table with a maximum of {maximum} elements"
+        );
+    }
+    for func in elements.iter().flatten() {
+        match ty.wasm_ty.heap_type {
+            WasmHeapType::Extern | WasmHeapType::NoFunc => {
+                bail!("function reference in a table of type `{}`", ty.wasm_ty)
+            }
+            _ => resolve_func_ref(store, *func, ids)?,
+        };
+    }
+    Ok(())
+}
+
+/// Writes `elements` into `table`, which has been validated with
+/// [`validate_table`] and grown to their number.
+fn restore_table(
+    store: &mut StoreOpaque,
+    table: Table,
+    elements: &[Option<FuncRef>],
+    ids: &[InstanceId],
+) {
+    let heap_type = table.wasmtime_ty(store.store_data()).wasm_ty.heap_type;
+    let raw = table.wasmtime_table(store, std::iter::empty());
+    for (i, element) in (0..).zip(elements) {
+        let element = match (heap_type, element) {
+            (WasmHeapType::Extern, _) => TableElement::ExternRef(None),
+            (_, None) => TableElement::FuncRef(std::ptr::null_mut()),
+            (_, Some(func)) => TableElement::FuncRef(resolve_func_ref(store, *func, ids).unwrap()),
+        };
+        unsafe {
+            (*raw).set(i, element).unwrap();
+        }
+    }
+}
+
+/// Checks that `value` can be restored into `global`.
+fn validate_global(
+    store: &mut StoreOpaque,
+    global: &ExportGlobal,
+    value: Option<&Value>,
+    ids: &[InstanceId],
+) -> Result<()> {
+    let value = match (global.global.mutability, value) {
+        (true, Some(value)) => value,
+        (false, None) => return Ok(()),
+        _ => bail!("global mutability doesn't match the checkpoint"),
+    };
+    let ty = global.global.wasm_ty;
+    match (ty, value) {
+        (WasmValType::I32, Value::I32(_))
+        | (WasmValType::I64, Value::I64(_))
+        | (WasmValType::F32, Value::F32(_))
+        | (WasmValType::F64, Value::F64(_))
+        | (WasmValType::V128, Value::V128(_)) => {}
+        (WasmValType::Ref(ty), Value::Ref(func)) => {
+            ensure_supported_ref(&ty)?;
+            match (ty.heap_type, func) {
+                (WasmHeapType::Extern | WasmHeapType::NoFunc, Some(_)) => {
+                    bail!("function reference in a global of type `{ty}`")
+                }
+                (_, Some(func)) => {
+                    resolve_func_ref(store, *func, ids)?;
+                }
+                (_, None) => {}
+            }
+        }
+        _ => bail!("global of type `{ty}` doesn't match the checkpoint"),
+    }
+    Ok(())
+}
+
+/// Writes `value` into `global`, which has been validated with
+/// [`validate_global`].
+fn restore_global(
+    store: &mut StoreOpaque,
+    global: &ExportGlobal,
+    value: Option<&Value>,
+    ids: &[InstanceId],
+) {
+    let Some(value) = value else {
+        return;
+    };
+    let definition = unsafe { &mut *global.definition };
+    unsafe {
+        match (global.global.wasm_ty, value) {
+            (_, Value::I32(v)) => *definition.as_i32_mut() = *v,
+            (_, Value::I64(v)) => *definition.as_i64_mut() = *v,
+            (_, Value::F32(v)) => *definition.as_u32_mut() = *v,
+            (_, Value::F64(v)) => *definition.as_u64_mut() = *v,
+            (_, Value::V128(v)) => *definition.as_u128_mut() = *v,
+            (WasmValType::Ref(ty), Value::Ref(None)) if ty.heap_type == WasmHeapType::Extern => {
+                drop(mem::take(definition.as_externref_mut()));
+            }
+            (_, Value::Ref(None)) => *definition.as_func_ref_mut() = std::ptr::null_mut(),
+            (_, Value::Ref(Some(func))) => {
+                *definition.as_func_ref_mut() = resolve_func_ref(store, *func, ids).unwrap();
+            }
+        }
+    }
+}
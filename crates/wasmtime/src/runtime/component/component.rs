@@ -1,3 +1,5 @@
+use crate::component::matching::InstanceType;
+use crate::component::types;
 use crate::{
     code::CodeObject, code_memory::CodeMemory, instantiate::MmapVecWrapper,
     type_registry::TypeCollection, Engine, Module, ResourcesRequired,
@@ -284,6 +286,26 @@ impl Component {
         Component::from_parts(engine, code, None)
     }
 
+    /// Returns the type of this component as a [`types::Component`].
+    ///
+    /// Unlike [`Linker::substituted_component_type`] this doesn't require the
+    /// imports of the component to be defined. Resources imported or defined
+    /// by the component are represented by [`ResourceType`]s unique to this
+    /// component, which are distinct from those of its instances.
+    ///
+    /// [`Linker::substituted_component_type`]: crate::component::Linker::substituted_component_type
+    /// [`ResourceType`]: crate::component::ResourceType
+    pub fn component_type(&self) -> types::Component {
+        let resources = Arc::new(PrimaryMap::new());
+        types::Component::from(
+            self.ty(),
+            &InstanceType {
+                types: self.types(),
+                resources: &resources,
+            },
+        )
+    }
+
     /// Final assembly step for a component from its in-memory representation.
     ///
     /// If the `artifacts` are specified as `None` here then they will be
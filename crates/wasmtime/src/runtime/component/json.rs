@@ -0,0 +1,421 @@
+//! JSON encoding of component model values.
+//!
+//! This module converts between [`Val`]s and [`serde_json::Value`]s, directed
+//! by the component model [`Type`] of the value, so that component exports can
+//! be called with JSON payloads. Values are encoded as:
+//!
+//! | Type                    | JSON                                          |
+//! |-------------------------|-----------------------------------------------|
+//! | `bool`                  | `true`, `false`                               |
+//! | integers                | `42`, `-7`                                    |
+//! | `float32`, `float64`    | `3.5`, `"nan"`, `"inf"`, `"-inf"`             |
+//! | `char`, `string`        | `"x"`, `"hello"`                              |
+//! | `list<T>`, `tuple<...>` | `[1, 2, 3]`, `[1, "two"]`                     |
+//! | `record`                | `{"name": "x", "size": 3}`                    |
+//! | `variant`               | `"case"`, `{"case": payload}`                 |
+//! | `enum`                  | `"case"`                                      |
+//! | `option<T>`             | `null`, the value of `T`                      |
+//! | `result<T, E>`          | `{"ok": value}`, `{"err": value}`             |
+//! | `flags`                 | `["read", "write"]`                           |
+//!
+//! Record fields of `option` type may be omitted, in which case they are
+//! `none`, and `ok` and `err` cases without a payload are encoded as `null`.
+//! As `null` is ambiguous for nested options, `some(value)` of an `option`
+//! whose payload is itself an `option` is encoded as the single element array
+//! `[value]`. Resources can't be encoded in JSON.
+//!
+//! Errors refer to the offending part of the JSON value by its path, e.g.
+//! `$.tags[1]`.
+
+use crate::component::types::{self, Type};
+use crate::component::Val;
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+/// Converts `json` to a value of type `ty`.
+pub fn from_json(ty: &Type, json: &Value) -> Result<Val> {
+    Decoder::default().value(ty, json)
+}
+
+/// Converts the elements of the JSON array `json` to values of types `tys`,
+/// for example the arguments of a function call.
+pub fn from_json_args(tys: &[Type], json: &Value) -> Result<Vec<Val>> {
+    let mut decoder = Decoder::default();
+    let Value::Array(values) = json else {
+        return Err(decoder.error(format!("expected an array, found {}", kind(json))));
+    };
+    decoder.sequence(tys, values)
+}
+
+/// Converts `val` to JSON.
+///
+/// Fails if `val` contains a resource.
+pub fn to_json(val: &Val) -> Result<Value> {
+    value(&mut Path::default(), val)
+}
+
+/// Converts the values `vals`, for example the results of a function call, to
+/// a JSON array.
+pub fn to_json_args(vals: &[Val]) -> Result<Value> {
+    sequence(&mut Path::default(), vals.iter())
+}
+
+/// Describes the kind of `json` in error messages.
+fn kind(json: &Value) -> &'static str {
+    match json {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Segment of the path to a part of a JSON value.
+enum Segment {
+    Index(usize),
+    Key(String),
+}
+
+/// Path to the part of a JSON value being converted.
+#[derive(Default)]
+struct Path(Vec<Segment>);
+
+impl AsMut<Path> for Path {
+    fn as_mut(&mut self) -> &mut Path {
+        self
+    }
+}
+
+/// Runs `f` with `segment` appended to the path of `state`.
+fn at<S: AsMut<Path>, T>(state: &mut S, segment: Segment, f: impl FnOnce(&mut S) -> T) -> T {
+    state.as_mut().0.push(segment);
+    let ret = f(state);
+    state.as_mut().0.pop();
+    ret
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for segment in &self.0 {
+            match segment {
+                Segment::Index(i) => write!(f, "[{i}]")?,
+                Segment::Key(key) => write!(f, ".{key}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Type-directed conversion of JSON to [`Val`]s.
+#[derive(Default)]
+struct Decoder {
+    path: Path,
+}
+
+impl AsMut<Path> for Decoder {
+    fn as_mut(&mut self) -> &mut Path {
+        &mut self.path
+    }
+}
+
+impl Decoder {
+    fn error(&self, msg: impl fmt::Display) -> anyhow::Error {
+        anyhow!("{msg} at `{}`", self.path)
+    }
+
+    fn mismatch(&self, expected: &str, json: &Value) -> anyhow::Error {
+        self.error(format!("expected {expected}, found {}", kind(json)))
+    }
+
+    fn sequence(&mut self, tys: &[Type], values: &[Value]) -> Result<Vec<Val>> {
+        if values.len() != tys.len() {
+            return Err(self.error(format!(
+                "expected {} elements, found {}",
+                tys.len(),
+                values.len()
+            )));
+        }
+        tys.iter()
+            .zip(values)
+            .enumerate()
+            .map(|(i, (ty, json))| at(self, Segment::Index(i), |d| d.value(ty, json)))
+            .collect()
+    }
+
+    fn integer<T: TryFrom<i64> + TryFrom<u64>>(&self, ty: &str, json: &Value) -> Result<T> {
+        let Value::Number(n) = json else {
+            return Err(self.mismatch(ty, json));
+        };
+        let n = match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => T::try_from(n).ok(),
+            (None, Some(n)) => T::try_from(n).ok(),
+            (None, None) => None,
+        };
+        n.ok_or_else(|| self.error(format!("invalid {ty} `{json}`")))
+    }
+
+    fn float(&self, ty: &str, json: &Value) -> Result<f64> {
+        match json {
+            Value::Number(n) => Ok(n.as_f64().unwrap()),
+            Value::String(s) if s == "nan" => Ok(f64::NAN),
+            Value::String(s) if s == "inf" => Ok(f64::INFINITY),
+            Value::String(s) if s == "-inf" => Ok(f64::NEG_INFINITY),
+            _ => Err(self.mismatch(ty, json)),
+        }
+    }
+
+    fn str<'a>(&self, json: &'a Value) -> Result<&'a str> {
+        match json {
+            Value::String(s) => Ok(s),
+            _ => Err(self.mismatch("a string", json)),
+        }
+    }
+
+    /// Splits an object `{"name": payload}` with a single entry.
+    fn case<'a>(&self, json: &'a Value) -> Result<(&'a str, &'a Value)> {
+        match json {
+            Value::Object(object) if object.len() == 1 => {
+                let (name, payload) = object.iter().next().unwrap();
+                Ok((name, payload))
+            }
+            Value::Object(_) => Err(self.error("expected an object with a single entry")),
+            _ => Err(self.mismatch("an object", json)),
+        }
+    }
+
+    /// Converts the payload of case `name`, which is `null` if `ty` is unset.
+    fn payload(&mut self, ty: Option<Type>, name: &str, json: &Value) -> Result<Option<Val>> {
+        at(self, Segment::Key(name.into()), |d| match (ty, json) {
+            (Some(ty), json) => d.value(&ty, json).map(Some),
+            (None, Value::Null) => Ok(None),
+            (None, json) => Err(d.mismatch("null", json)),
+        })
+    }
+
+    fn value(&mut self, ty: &Type, json: &Value) -> Result<Val> {
+        let val = match ty {
+            Type::Bool => match json {
+                Value::Bool(b) => Val::Bool(*b),
+                _ => return Err(self.mismatch("a boolean", json)),
+            },
+            Type::S8 => Val::S8(self.integer("s8", json)?),
+            Type::U8 => Val::U8(self.integer("u8", json)?),
+            Type::S16 => Val::S16(self.integer("s16", json)?),
+            Type::U16 => Val::U16(self.integer("u16", json)?),
+            Type::S32 => Val::S32(self.integer("s32", json)?),
+            Type::U32 => Val::U32(self.integer("u32", json)?),
+            Type::S64 => Val::S64(self.integer("s64", json)?),
+            Type::U64 => Val::U64(self.integer("u64", json)?),
+            Type::Float32 => Val::Float32(self.float("float32", json)? as f32),
+            Type::Float64 => Val::Float64(self.float("float64", json)?),
+            Type::Char => {
+                let mut chars = self.str(json)?.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Val::Char(c),
+                    _ => return Err(self.error("expected a single character")),
+                }
+            }
+            Type::String => Val::String(self.str(json)?.into()),
+            Type::List(list) => {
+                let Value::Array(values) = json else {
+                    return Err(self.mismatch("an array", json));
+                };
+                let element = list.ty();
+                let values = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, json)| at(self, Segment::Index(i), |d| d.value(&element, json)))
+                    .collect::<Result<Vec<_>>>()?;
+                list.new_val(values.into())?
+            }
+            Type::Tuple(tuple) => {
+                let Value::Array(values) = json else {
+                    return Err(self.mismatch("an array", json));
+                };
+                let types = tuple.types().collect::<Vec<_>>();
+                tuple.new_val(self.sequence(&types, values)?.into())?
+            }
+            Type::Record(record) => {
+                let Value::Object(object) = json else {
+                    return Err(self.mismatch("an object", json));
+                };
+                self.record(record, object)?
+            }
+            Type::Variant(variant) => {
+                let (name, payload) = match json {
+                    Value::String(name) => (name.as_str(), &Value::Null),
+                    json => self.case(json)?,
+                };
+                let Some(case) = variant.cases().find(|case| case.name == name) else {
+                    return Err(self.error(format!("unknown variant case `{name}`")));
+                };
+                if case.ty.is_some() && json.is_string() {
+                    return Err(self.error(format!("`{name}` requires a payload")));
+                }
+                let payload = self.payload(case.ty, name, payload)?;
+                variant.new_val(name, payload)?
+            }
+            Type::Enum(enum_) => {
+                let name = self.str(json)?;
+                if !enum_.names().any(|case| case == name) {
+                    return Err(self.error(format!("unknown enum case `{name}`")));
+                }
+                enum_.new_val(name)?
+            }
+            Type::Option(option) => {
+                let payload = option.ty();
+                match (json, &payload) {
+                    (Value::Null, _) => option.new_val(None)?,
+                    (Value::Array(values), Type::Option(_)) if values.len() == 1 => {
+                        let val = at(self, Segment::Index(0), |d| d.value(&payload, &values[0]))?;
+                        option.new_val(Some(val))?
+                    }
+                    (json, Type::Option(_)) => {
+                        return Err(self.mismatch("null or a single element array", json))
+                    }
+                    (json, _) => option.new_val(Some(self.value(&payload, json)?))?,
+                }
+            }
+            Type::Result(result) => match self.case(json)? {
+                ("ok", payload) => {
+                    result.new_val(Ok(self.payload(result.ok(), "ok", payload)?))?
+                }
+                ("err", payload) => {
+                    result.new_val(Err(self.payload(result.err(), "err", payload)?))?
+                }
+                _ => return Err(self.error("expected `ok` or `err`")),
+            },
+            Type::Flags(flags) => {
+                let Value::Array(values) = json else {
+                    return Err(self.mismatch("an array", json));
+                };
+                let names = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, json)| {
+                        at(self, Segment::Index(i), |d| {
+                            let name = d.str(json)?;
+                            if !flags.names().any(|flag| flag == name) {
+                                return Err(d.error(format!("unknown flag `{name}`")));
+                            }
+                            Ok(name)
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                flags.new_val(&names)?
+            }
+            Type::Own(_) | Type::Borrow(_) => {
+                return Err(self.error("resources cannot be converted from JSON"))
+            }
+        };
+        Ok(val)
+    }
+
+    fn record(&mut self, record: &types::Record, object: &Map<String, Value>) -> Result<Val> {
+        if let Some(name) = object
+            .keys()
+            .find(|name| !record.fields().any(|field| field.name == *name))
+        {
+            return Err(self.error(format!("unknown field `{name}`")));
+        }
+        let values = record
+            .fields()
+            .map(|field| {
+                let value = match (object.get(field.name), &field.ty) {
+                    (Some(json), ty) => {
+                        at(self, Segment::Key(field.name.into()), |d| d.value(ty, json))?
+                    }
+                    (None, Type::Option(option)) => option.new_val(None)?,
+                    (None, _) => return Err(self.error(format!("missing field `{}`", field.name))),
+                };
+                Ok((field.name, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        record.new_val(values)
+    }
+}
+
+fn sequence<'a>(path: &mut Path, vals: impl Iterator<Item = &'a Val>) -> Result<Value> {
+    vals.enumerate()
+        .map(|(i, val)| at(path, Segment::Index(i), |path| value(path, val)))
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
+}
+
+fn float(f: f64) -> Value {
+    match Number::from_f64(f) {
+        Some(n) => Value::Number(n),
+        None if f.is_nan() => Value::String("nan".into()),
+        None if f > 0.0 => Value::String("inf".into()),
+        None => Value::String("-inf".into()),
+    }
+}
+
+/// Encodes the payload of case `name`, which is `null` if unset.
+fn payload(path: &mut Path, name: &str, val: Option<&Val>) -> Result<Value> {
+    match val {
+        Some(val) => at(path, Segment::Key(name.into()), |path| value(path, val)),
+        None => Ok(Value::Null),
+    }
+}
+
+fn case(name: &str, payload: Value) -> Value {
+    Value::Object([(name.to_string(), payload)].into_iter().collect())
+}
+
+fn value(path: &mut Path, val: &Val) -> Result<Value> {
+    let json = match val {
+        Val::Bool(b) => Value::Bool(*b),
+        Val::S8(n) => Value::from(*n),
+        Val::U8(n) => Value::from(*n),
+        Val::S16(n) => Value::from(*n),
+        Val::U16(n) => Value::from(*n),
+        Val::S32(n) => Value::from(*n),
+        Val::U32(n) => Value::from(*n),
+        Val::S64(n) => Value::from(*n),
+        Val::U64(n) => Value::from(*n),
+        // Widen through the shortest decimal representation so that e.g.
+        // `0.1f32` is encoded as `0.1` rather than `0.10000000149011612`.
+        Val::Float32(f) if f.is_finite() => float(f.to_string().parse().unwrap()),
+        Val::Float32(f) => float(f64::from(*f)),
+        Val::Float64(f) => float(*f),
+        Val::Char(c) => Value::String(c.to_string()),
+        Val::String(s) => Value::String(s.to_string()),
+        Val::List(list) => sequence(path, list.iter())?,
+        Val::Tuple(tuple) => sequence(path, tuple.values().iter())?,
+        Val::Record(record) => record
+            .fields()
+            .map(|(name, val)| {
+                let json = at(path, Segment::Key(name.into()), |path| value(path, val))?;
+                Ok((name.to_string(), json))
+            })
+            .collect::<Result<Map<_, _>>>()
+            .map(Value::Object)?,
+        Val::Variant(variant) => {
+            let name = variant.discriminant();
+            match variant.payload() {
+                Some(val) => case(name, payload(path, name, Some(val))?),
+                None => Value::String(name.into()),
+            }
+        }
+        Val::Enum(enum_) => Value::String(enum_.discriminant().into()),
+        Val::Option(option) => match option.value() {
+            None => Value::Null,
+            Some(val @ Val::Option(_)) => {
+                Value::Array(vec![at(path, Segment::Index(0), |path| value(path, val))?])
+            }
+            Some(val) => value(path, val)?,
+        },
+        Val::Result(result) => match result.value() {
+            Ok(val) => case("ok", payload(path, "ok", val)?),
+            Err(val) => case("err", payload(path, "err", val)?),
+        },
+        Val::Flags(flags) => Value::Array(flags.flags().map(|flag| flag.into()).collect()),
+        Val::Resource(_) => bail!("resources cannot be converted to JSON at `{path}`"),
+    };
+    Ok(json)
+}
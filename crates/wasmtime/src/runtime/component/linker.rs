@@ -9,12 +9,13 @@ use crate::{AsContextMut, Engine, Module, StoreContextMut};
 use anyhow::{anyhow, bail, Context, Result};
 use indexmap::IndexMap;
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::HashSet;
 use std::future::Future;
 use std::marker;
 use std::ops::Deref;
 use std::pin::Pin;
 use std::sync::Arc;
-use wasmtime_environ::component::TypeDef;
+use wasmtime_environ::component::{ResourceIndex, TypeDef};
 use wasmtime_environ::{EntityRef, PrimaryMap};
 
 /// A type used to instantiate [`Component`]s.
@@ -328,6 +329,194 @@ impl<T> Linker<T> {
             .instantiate_async(store)
             .await
     }
+
+    /// Implement any function imports of the [`Component`] that are not
+    /// defined in this linker with a function that traps when called.
+    ///
+    /// Imported instances are walked recursively, defining any missing
+    /// functions within them, and imported resources which aren't defined are
+    /// defined as host resources that are never created by the stubs.
+    ///
+    /// This method can be used to allow unknown imports from command
+    /// components, which may never call them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmtime::{Config, Engine, Store};
+    /// # use wasmtime::component::{Component, Linker};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut config = Config::new();
+    /// # config.wasm_component_model(true);
+    /// # let engine = Engine::new(&config)?;
+    /// # let component = Component::new(&engine, r#"
+    /// #     (component (import "unknown" (instance (export "f" (func)))))
+    /// # "#)?;
+    /// # let mut store = Store::new(&engine, ());
+    /// let mut linker = Linker::new(&engine);
+    /// linker.define_unknown_imports_as_traps(&component)?;
+    /// linker.instantiate(&mut store, &component)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn define_unknown_imports_as_traps(&mut self, component: &Component) -> Result<()>
+    where
+        T: 'static,
+    {
+        self.define_unknown_imports(component, &mut |name, _ty| {
+            let name = name.to_string();
+            Ok(Box::new(move |_, _, _| {
+                bail!("unknown import: `{name}` has not been defined")
+            }))
+        })
+    }
+
+    /// Implement any function imports of the [`Component`] that are not
+    /// defined in this linker with a function that ignores its arguments and
+    /// returns default values.
+    ///
+    /// Default values are `false`, zero, empty strings, lists and flags,
+    /// `none`, `ok` and the first case of variants and enums, with default
+    /// payloads and fields. Resources have no default value, so this returns
+    /// an error if an unknown import returns one. Imported instances and
+    /// resources are handled as in [`Linker::define_unknown_imports_as_traps`].
+    ///
+    /// This method can be used to allow unknown imports from command
+    /// components.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmtime::{Config, Engine, Store};
+    /// # use wasmtime::component::{Component, Linker};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut config = Config::new();
+    /// # config.wasm_component_model(true);
+    /// # let engine = Engine::new(&config)?;
+    /// # let component = Component::new(&engine, r#"
+    /// #     (component (import "unknown" (instance (export "f" (func (result u32))))))
+    /// # "#)?;
+    /// # let mut store = Store::new(&engine, ());
+    /// let mut linker = Linker::new(&engine);
+    /// linker.define_unknown_imports_as_default_values(&component)?;
+    /// linker.instantiate(&mut store, &component)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn define_unknown_imports_as_default_values(&mut self, component: &Component) -> Result<()>
+    where
+        T: 'static,
+    {
+        self.define_unknown_imports(component, &mut |name, ty| {
+            let results = ty
+                .results()
+                .map(|ty| default_value(&ty))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("failed to define unknown import `{name}`"))?;
+            Ok(Box::new(move |_, _, dst| {
+                dst.clone_from_slice(&results);
+                Ok(())
+            }))
+        })
+    }
+
+    fn define_unknown_imports(
+        &mut self,
+        component: &Component,
+        stub: &mut dyn FnMut(&str, &types::ComponentFunc) -> Result<UnknownImportFunc<T>>,
+    ) -> Result<()>
+    where
+        T: 'static,
+    {
+        let env_component = component.env_component();
+        let imports = env_component
+            .import_types
+            .values()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect::<IndexMap<_, _>>();
+
+        // Resource types in function signatures are never instantiated by
+        // the stubs, so a placeholder suffices to describe them.
+        let resources = Arc::new(
+            (0..env_component.num_resources)
+                .map(|_| ResourceType::host::<UnknownResource>())
+                .collect(),
+        );
+        let mut cx = UnknownImports {
+            component,
+            ty: InstanceType {
+                types: component.types(),
+                resources: &resources,
+            },
+            seen_resources: HashSet::new(),
+            stub,
+        };
+        self.root().define_unknown_imports(&imports, "", &mut cx)
+    }
+}
+
+/// Host function implementing an unknown import.
+type UnknownImportFunc<T> =
+    Box<dyn Fn(StoreContextMut<'_, T>, &[Val], &mut [Val]) -> Result<()> + Send + Sync>;
+
+/// State of [`Linker::define_unknown_imports`].
+struct UnknownImports<'a, T> {
+    component: &'a Component,
+    ty: InstanceType<'a>,
+    /// Resources already imported, where further imports of them are `(eq
+    /// ...)` bounds, which must not be defined.
+    seen_resources: HashSet<ResourceIndex>,
+    stub: &'a mut dyn FnMut(&str, &types::ComponentFunc) -> Result<UnknownImportFunc<T>>,
+}
+
+/// Host resource type of unknown resource imports.
+struct UnknownResource;
+
+/// Returns the default value of `ty` for
+/// [`Linker::define_unknown_imports_as_default_values`].
+fn default_value(ty: &types::Type) -> Result<Val> {
+    use types::Type;
+
+    Ok(match ty {
+        Type::Bool => Val::Bool(false),
+        Type::S8 => Val::S8(0),
+        Type::U8 => Val::U8(0),
+        Type::S16 => Val::S16(0),
+        Type::U16 => Val::U16(0),
+        Type::S32 => Val::S32(0),
+        Type::U32 => Val::U32(0),
+        Type::S64 => Val::S64(0),
+        Type::U64 => Val::U64(0),
+        Type::Float32 => Val::Float32(0.0),
+        Type::Float64 => Val::Float64(0.0),
+        Type::Char => Val::Char('\0'),
+        Type::String => Val::String("".into()),
+        Type::List(list) => list.new_val(Box::new([]))?,
+        Type::Record(record) => record.new_val(
+            record
+                .fields()
+                .map(|field| Ok((field.name, default_value(&field.ty)?)))
+                .collect::<Result<Vec<_>>>()?,
+        )?,
+        Type::Tuple(tuple) => tuple.new_val(
+            tuple
+                .types()
+                .map(|ty| default_value(&ty))
+                .collect::<Result<_>>()?,
+        )?,
+        Type::Variant(variant) => {
+            let case = variant.cases().next().unwrap();
+            let payload = case.ty.as_ref().map(default_value).transpose()?;
+            variant.new_val(case.name, payload)?
+        }
+        Type::Enum(enum_) => enum_.new_val(enum_.names().next().unwrap())?,
+        Type::Option(option) => option.new_val(None)?,
+        Type::Result(result) => {
+            result.new_val(Ok(result.ok().as_ref().map(default_value).transpose()?))?
+        }
+        Type::Flags(flags) => flags.new_val(&[])?,
+        Type::Own(_) | Type::Borrow(_) => bail!("no default value exists for resources"),
+    })
 }
 
 impl<T> LinkerInstance<'_, T> {
@@ -566,6 +755,76 @@ impl<T> LinkerInstance<'_, T> {
         Ok(self)
     }
 
+    /// Defines the items of `imports` which aren't defined in this instance
+    /// yet, where `prefix` is the path to this instance used in error
+    /// messages.
+    fn define_unknown_imports(
+        &mut self,
+        imports: &IndexMap<String, TypeDef>,
+        prefix: &str,
+        cx: &mut UnknownImports<'_, T>,
+    ) -> Result<()>
+    where
+        T: 'static,
+    {
+        let types = cx.component.types();
+        for (name, ty) in imports {
+            let defined = self
+                .strings
+                .lookup(name)
+                .and_then(|name| self.map.get(&name));
+            match ty {
+                // Items defined with the wrong type are left to be reported
+                // by type-checking.
+                TypeDef::ComponentInstance(index) => {
+                    let mut instance = match defined {
+                        None => self.instance(name)?,
+                        Some(Definition::Instance(_)) => {
+                            let name = self.strings.lookup(name).unwrap();
+                            self.as_mut().into_defined_instance(name)
+                        }
+                        Some(_) => continue,
+                    };
+                    let exports = &types[*index].exports;
+                    instance.define_unknown_imports(exports, &format!("{prefix}{name}#"), cx)?;
+                }
+                TypeDef::ComponentFunc(index) if defined.is_none() => {
+                    let ty = types::ComponentFunc::from(*index, &cx.ty);
+                    let func = (cx.stub)(&format!("{prefix}{name}"), &ty)?;
+                    let name = self.strings.intern(name);
+                    self.insert(
+                        name,
+                        Definition::Func(HostFunc::new_dynamic(func, *index, types)),
+                    )?;
+                }
+                TypeDef::Resource(index) => {
+                    if cx.seen_resources.insert(types[*index].ty) && defined.is_none() {
+                        self.resource(
+                            name,
+                            ResourceType::host::<UnknownResource>(),
+                            |_, _| Ok(()),
+                        )?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as [`LinkerInstance::into_instance`] except that it enters the
+    /// instance already defined as `name`.
+    fn into_defined_instance(mut self, name: usize) -> Self {
+        self.map = match self.map.get_mut(&name) {
+            Some(Definition::Instance(map)) => map,
+            _ => unreachable!(),
+        };
+        self.path.truncate(self.path_len);
+        self.path.push(name);
+        self.path_len += 1;
+        self
+    }
+
     fn insert(&mut self, key: usize, item: Definition) -> Result<()> {
         match self.map.entry(key) {
             Entry::Occupied(_) if !self.allow_shadowing => {
@@ -44,14 +44,17 @@
 mod component;
 mod func;
 mod instance;
+pub mod json;
 mod linker;
 mod matching;
 mod resource_table;
 mod resources;
 mod storage;
 mod store;
+pub mod text;
 pub mod types;
 mod values;
+pub mod wit;
 pub use self::component::Component;
 pub use self::func::{
     ComponentNamedList, ComponentType, Func, Lift, Lower, TypedFunc, WasmList, WasmStr,
@@ -0,0 +1,614 @@
+//! Textual syntax for component model values.
+//!
+//! This module converts between [`Val`]s and a human-readable text format,
+//! directed by the component model [`Type`] of the value. The syntax is
+//! intended for ad-hoc use, for example passing arguments to component
+//! exports on the command line, and looks like:
+//!
+//! | Type                    | Syntax                                    |
+//! |-------------------------|-------------------------------------------|
+//! | `bool`                  | `true`, `false`                           |
+//! | integers                | `42`, `-7`                                |
+//! | `float32`, `float64`    | `3.5`, `-1e10`, `nan`, `inf`, `-inf`      |
+//! | `char`                  | `'x'`, `'\n'`, `'\u{1f980}'`              |
+//! | `string`                | `"hello\tworld"`                          |
+//! | `list<T>`               | `[1, 2, 3]`                               |
+//! | `tuple<...>`            | `(1, "two")`                              |
+//! | `record`                | `{name: "x", size: 3}`                    |
+//! | `variant`               | `case`, `case(payload)`                   |
+//! | `enum`                  | `case`                                    |
+//! | `option<T>`             | `none`, `some(value)`                     |
+//! | `result<T, E>`          | `ok`, `ok(value)`, `err`, `err(value)`    |
+//! | `flags`                 | `{read, write}`                           |
+//! | `own<T>`, `borrow<T>`   | `#0`, an index into [`Handles`]           |
+//!
+//! Names of cases, fields and flags are written in kebab-case and may be
+//! prefixed with `%` to distinguish them from keywords, e.g. `%none`. Record
+//! fields of `option` type may be omitted, in which case they are `none`.
+//! Trailing commas are allowed in all comma-separated sequences.
+
+use crate::component::types::{self, Type};
+use crate::component::{ResourceAny, Val};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use std::fmt::Write;
+
+/// Keywords, which have to be prefixed with `%` to be used as names.
+const KEYWORDS: &[&str] = &["true", "false", "some", "none", "ok", "err", "inf", "nan"];
+
+/// Table of resources referenced by handle in the textual syntax.
+///
+/// Resources can't be written down by value, so instead they are written as
+/// `#n` where `n` is the index of the resource within this table.
+#[derive(Clone, Default)]
+pub struct Handles {
+    resources: Vec<ResourceAny>,
+}
+
+impl Handles {
+    /// Adds `resource` to this table, returning its handle.
+    pub fn insert(&mut self, resource: ResourceAny) -> u32 {
+        let handle = u32::try_from(self.resources.len()).unwrap();
+        self.resources.push(resource);
+        handle
+    }
+
+    /// Returns the resource with `handle`, if any.
+    pub fn get(&self, handle: u32) -> Option<ResourceAny> {
+        self.resources.get(usize::try_from(handle).ok()?).copied()
+    }
+}
+
+/// Parses `s` as a value of type `ty`.
+pub fn parse(ty: &Type, s: &str, handles: &Handles) -> Result<Val> {
+    let mut parser = Parser::new(s, handles);
+    let val = parser.value(ty)?;
+    parser.finish()?;
+    Ok(val)
+}
+
+/// Parses `s` as a comma-separated sequence of values of types `tys`, for
+/// example the arguments of a function call.
+pub fn parse_args(tys: &[Type], s: &str, handles: &Handles) -> Result<Vec<Val>> {
+    let mut parser = Parser::new(s, handles);
+    let vals = parser.sequence(None, tys.len(), |parser, i| parser.value(&tys[i]))?;
+    parser.finish()?;
+    Ok(vals)
+}
+
+/// Splits a function call expression `name(args...)` into the function name
+/// and the unparsed arguments.
+///
+/// A bare `name` is treated as a call without arguments.
+pub fn parse_call(s: &str) -> Result<(&str, &str)> {
+    let s = s.trim();
+    let Some(open) = s.find('(') else {
+        return Ok((s, ""));
+    };
+    let Some(args) = s[open + 1..].strip_suffix(')') else {
+        bail!("expected `)` at the end of function call `{s}`");
+    };
+    let name = s[..open].trim_end();
+    ensure!(!name.is_empty(), "missing function name in `{s}`");
+    Ok((name, args))
+}
+
+/// Formats `val` in the textual syntax, inserting any resources it contains
+/// into `handles`.
+pub fn to_string(val: &Val, handles: &mut Handles) -> String {
+    let mut out = String::new();
+    write_val(&mut out, val, handles);
+    out
+}
+
+fn write_name(out: &mut String, name: &str) {
+    if KEYWORDS.contains(&name) {
+        out.push('%');
+    }
+    out.push_str(name);
+}
+
+fn write_seq<'a>(
+    out: &mut String,
+    open: char,
+    close: char,
+    items: impl IntoIterator<Item = &'a Val>,
+    handles: &mut Handles,
+) {
+    out.push(open);
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_val(out, item, handles);
+    }
+    out.push(close);
+}
+
+fn write_payload(out: &mut String, payload: Option<&Val>, handles: &mut Handles) {
+    if let Some(payload) = payload {
+        write_seq(out, '(', ')', [payload], handles);
+    }
+}
+
+fn write_float(out: &mut String, f: impl std::fmt::Display, is_nan: bool) {
+    if is_nan {
+        out.push_str("nan");
+    } else {
+        write!(out, "{f}").unwrap();
+    }
+}
+
+fn write_char(out: &mut String, c: char, quote: char) {
+    match c {
+        '\t' => out.push_str("\\t"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\\' => out.push_str("\\\\"),
+        c if c == quote => {
+            out.push('\\');
+            out.push(c);
+        }
+        c if c.is_control() => write!(out, "\\u{{{:x}}}", u32::from(c)).unwrap(),
+        c => out.push(c),
+    }
+}
+
+fn write_val(out: &mut String, val: &Val, handles: &mut Handles) {
+    match val {
+        Val::Bool(b) => write!(out, "{b}").unwrap(),
+        Val::S8(n) => write!(out, "{n}").unwrap(),
+        Val::U8(n) => write!(out, "{n}").unwrap(),
+        Val::S16(n) => write!(out, "{n}").unwrap(),
+        Val::U16(n) => write!(out, "{n}").unwrap(),
+        Val::S32(n) => write!(out, "{n}").unwrap(),
+        Val::U32(n) => write!(out, "{n}").unwrap(),
+        Val::S64(n) => write!(out, "{n}").unwrap(),
+        Val::U64(n) => write!(out, "{n}").unwrap(),
+        Val::Float32(f) => write_float(out, f, f.is_nan()),
+        Val::Float64(f) => write_float(out, f, f.is_nan()),
+        Val::Char(c) => {
+            out.push('\'');
+            write_char(out, *c, '\'');
+            out.push('\'');
+        }
+        Val::String(s) => {
+            out.push('"');
+            for c in s.chars() {
+                write_char(out, c, '"');
+            }
+            out.push('"');
+        }
+        Val::List(list) => write_seq(out, '[', ']', list.iter(), handles),
+        Val::Tuple(tuple) => write_seq(out, '(', ')', tuple.values(), handles),
+        Val::Record(record) => {
+            out.push('{');
+            for (i, (name, val)) in record.fields().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_name(out, name);
+                out.push_str(": ");
+                write_val(out, val, handles);
+            }
+            out.push('}');
+        }
+        Val::Variant(variant) => {
+            write_name(out, variant.discriminant());
+            write_payload(out, variant.payload(), handles);
+        }
+        Val::Enum(enum_) => write_name(out, enum_.discriminant()),
+        Val::Option(option) => match option.value() {
+            Some(val) => {
+                out.push_str("some");
+                write_payload(out, Some(val), handles);
+            }
+            None => out.push_str("none"),
+        },
+        Val::Result(result) => {
+            let (case, payload) = match result.value() {
+                Ok(payload) => ("ok", payload),
+                Err(payload) => ("err", payload),
+            };
+            out.push_str(case);
+            write_payload(out, payload, handles);
+        }
+        Val::Flags(flags) => {
+            out.push('{');
+            for (i, flag) in flags.flags().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_name(out, flag);
+            }
+            out.push('}');
+        }
+        Val::Resource(resource) => write!(out, "#{}", handles.insert(*resource)).unwrap(),
+    }
+}
+
+/// Recursive descent parser of the textual syntax.
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+    handles: &'a Handles,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str, handles: &'a Handles) -> Self {
+        Self { s, pos: 0, handles }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    fn error(&self, msg: impl std::fmt::Display) -> anyhow::Error {
+        anyhow!("{msg} at offset {}", self.pos)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("unexpected trailing input")),
+        }
+    }
+
+    /// Consumes characters up to the next delimiter, returning them.
+    fn token(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || ",:()[]{}'\"#".contains(c))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Parses a kebab-case name optionally prefixed with `%`.
+    fn name(&mut self) -> Result<&'a str> {
+        self.eat('%');
+        let start = self.pos;
+        let name = self.token();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-') {
+            self.pos = start;
+            return Err(self.error("expected a name"));
+        }
+        Ok(name)
+    }
+
+    /// Parses a comma-separated sequence of exactly `len` items, delimited by
+    /// `close` if set, which is consumed.
+    fn sequence<T>(
+        &mut self,
+        close: Option<char>,
+        len: usize,
+        mut item: impl FnMut(&mut Self, usize) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::with_capacity(len);
+        for i in 0..len {
+            if i > 0 {
+                self.expect(',')?;
+            }
+            items.push(item(self, i)?);
+        }
+        if len > 0 {
+            self.eat(',');
+        }
+        if let Some(close) = close {
+            self.expect(close)?;
+        }
+        Ok(items)
+    }
+
+    /// Parses a comma-separated sequence of any length up to `close`, which
+    /// is consumed.
+    fn delimited<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            items.push(item(self)?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Parses an optional parenthesized payload of type `ty`, which is required
+    /// if `ty` is set.
+    fn payload(&mut self, ty: Option<Type>, name: &str) -> Result<Option<Val>> {
+        match ty {
+            Some(ty) => {
+                self.expect('(')
+                    .with_context(|| format!("`{name}` requires a payload"))?;
+                let val = self.value(&ty)?;
+                self.eat(',');
+                self.expect(')')?;
+                Ok(Some(val))
+            }
+            None if self.peek() == Some('(') => {
+                Err(self.error(format!("`{name}` does not have a payload")))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, ty: &str) -> Result<T> {
+        let start = self.pos;
+        let token = self.token();
+        token.parse().map_err(|_| {
+            self.pos = start;
+            self.error(format!("invalid {ty} `{token}`"))
+        })
+    }
+
+    fn float(&mut self, ty: &str) -> Result<f64> {
+        let start = self.pos;
+        let token = self.token();
+        match token {
+            "nan" => Ok(f64::NAN),
+            "inf" => Ok(f64::INFINITY),
+            "-inf" => Ok(f64::NEG_INFINITY),
+            // Reject the alternative spellings accepted by `f64::from_str`.
+            _ if token
+                .chars()
+                .any(|c| c.is_alphabetic() && c != 'e' && c != 'E') =>
+            {
+                self.pos = start;
+                Err(self.error(format!("invalid {ty} `{token}`")))
+            }
+            _ => {
+                self.pos = start;
+                self.number(ty)
+            }
+        }
+    }
+
+    /// Parses a quoted `char` or `string` literal delimited by `quote`.
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        self.expect(quote)?;
+        let mut s = String::new();
+        let mut chars = self.rest().char_indices();
+        loop {
+            let Some((i, c)) = chars.next() else {
+                self.pos = self.s.len();
+                return Err(self.error(format!("missing closing `{quote}`")));
+            };
+            match c {
+                c if c == quote => {
+                    self.pos += i + c.len_utf8();
+                    return Ok(s);
+                }
+                '\\' => {
+                    let escaped = match chars.next() {
+                        Some((_, 't')) => '\t',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, c @ ('\\' | '\'' | '"'))) => c,
+                        Some((_, 'u')) => {
+                            let rest = &self.rest()[i + 2..];
+                            let code = rest
+                                .strip_prefix('{')
+                                .and_then(|rest| rest.split_once('}'))
+                                .map(|(code, _)| code);
+                            let c = code
+                                .and_then(|code| u32::from_str_radix(code, 16).ok())
+                                .and_then(char::from_u32);
+                            let (Some(code), Some(c)) = (code, c) else {
+                                self.pos += i;
+                                return Err(self.error("invalid unicode escape"));
+                            };
+                            // Skip over `{code}`.
+                            for _ in 0..code.len() + 2 {
+                                chars.next();
+                            }
+                            c
+                        }
+                        _ => {
+                            self.pos += i;
+                            return Err(self.error("invalid escape"));
+                        }
+                    };
+                    s.push(escaped);
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn value(&mut self, ty: &Type) -> Result<Val> {
+        let val = match ty {
+            Type::Bool => {
+                let start = self.pos;
+                match self.token() {
+                    "true" => Val::Bool(true),
+                    "false" => Val::Bool(false),
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("expected `true` or `false`"));
+                    }
+                }
+            }
+            Type::S8 => Val::S8(self.number("s8")?),
+            Type::U8 => Val::U8(self.number("u8")?),
+            Type::S16 => Val::S16(self.number("s16")?),
+            Type::U16 => Val::U16(self.number("u16")?),
+            Type::S32 => Val::S32(self.number("s32")?),
+            Type::U32 => Val::U32(self.number("u32")?),
+            Type::S64 => Val::S64(self.number("s64")?),
+            Type::U64 => Val::U64(self.number("u64")?),
+            Type::Float32 => Val::Float32(self.float("float32")? as f32),
+            Type::Float64 => Val::Float64(self.float("float64")?),
+            Type::Char => {
+                self.skip_whitespace();
+                let start = self.pos;
+                let s = self.quoted('\'')?;
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Val::Char(c),
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("expected a single character"));
+                    }
+                }
+            }
+            Type::String => {
+                self.skip_whitespace();
+                Val::String(self.quoted('"')?.into())
+            }
+            Type::List(list) => {
+                self.expect('[')?;
+                let element = list.ty();
+                let values = self.delimited(']', |parser| parser.value(&element))?;
+                list.new_val(values.into())?
+            }
+            Type::Tuple(tuple) => {
+                self.expect('(')?;
+                let types = tuple.types().collect::<Vec<_>>();
+                let values =
+                    self.sequence(Some(')'), types.len(), |parser, i| parser.value(&types[i]))?;
+                tuple.new_val(values.into())?
+            }
+            Type::Record(record) => self.record(record)?,
+            Type::Variant(variant) => {
+                let start = self.pos;
+                let name = self.name()?;
+                let Some(case) = variant.cases().find(|case| case.name == name) else {
+                    self.pos = start;
+                    return Err(self.error(format!("unknown variant case `{name}`")));
+                };
+                let payload = self.payload(case.ty, name)?;
+                variant.new_val(name, payload)?
+            }
+            Type::Enum(enum_) => {
+                let start = self.pos;
+                let name = self.name()?;
+                if !enum_.names().any(|case| case == name) {
+                    self.pos = start;
+                    return Err(self.error(format!("unknown enum case `{name}`")));
+                }
+                enum_.new_val(name)?
+            }
+            Type::Option(option) => {
+                let start = self.pos;
+                match self.token() {
+                    "none" => option.new_val(None)?,
+                    "some" => {
+                        let payload = self.payload(Some(option.ty()), "some")?;
+                        option.new_val(payload)?
+                    }
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("expected `some` or `none`"));
+                    }
+                }
+            }
+            Type::Result(result) => {
+                let start = self.pos;
+                match self.token() {
+                    "ok" => result.new_val(Ok(self.payload(result.ok(), "ok")?))?,
+                    "err" => result.new_val(Err(self.payload(result.err(), "err")?))?,
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("expected `ok` or `err`"));
+                    }
+                }
+            }
+            Type::Flags(flags) => {
+                self.expect('{')?;
+                let names = self.delimited('}', |parser| {
+                    let start = parser.pos;
+                    let name = parser.name()?;
+                    if !flags.names().any(|flag| flag == name) {
+                        parser.pos = start;
+                        return Err(parser.error(format!("unknown flag `{name}`")));
+                    }
+                    Ok(name)
+                })?;
+                flags.new_val(&names)?
+            }
+            Type::Own(ty) | Type::Borrow(ty) => {
+                self.expect('#')?;
+                let start = self.pos;
+                let handle = self.number::<u32>("resource handle")?;
+                let Some(resource) = self.handles.get(handle) else {
+                    self.pos = start;
+                    return Err(self.error(format!("unknown resource handle `#{handle}`")));
+                };
+                if resource.ty() != *ty {
+                    self.pos = start;
+                    return Err(self.error(format!("resource `#{handle}` has the wrong type")));
+                }
+                Val::Resource(resource)
+            }
+        };
+        Ok(val)
+    }
+
+    fn record(&mut self, record: &types::Record) -> Result<Val> {
+        self.expect('{')?;
+        let mut values = record
+            .fields()
+            .map(|field| (field.name, field.ty, None))
+            .collect::<Vec<_>>();
+        self.delimited('}', |parser| {
+            let start = parser.pos;
+            let name = parser.name()?;
+            let Some((_, ty, value)) = values.iter_mut().find(|(field, ..)| *field == name) else {
+                parser.pos = start;
+                return Err(parser.error(format!("unknown field `{name}`")));
+            };
+            if value.is_some() {
+                parser.pos = start;
+                return Err(parser.error(format!("duplicate field `{name}`")));
+            }
+            parser.expect(':')?;
+            *value = Some(parser.value(ty)?);
+            Ok(())
+        })?;
+        let values = values
+            .into_iter()
+            .map(|(name, ty, value)| {
+                let value = match (value, ty) {
+                    (Some(value), _) => value,
+                    (None, Type::Option(option)) => option.new_val(None)?,
+                    (None, _) => bail!("missing field `{name}`"),
+                };
+                Ok((name, value))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map_err(|err| self.error(err))?;
+        record.new_val(values)
+    }
+}
@@ -770,6 +770,14 @@ impl ComponentFunc {
         Self(Handle::new(index, ty))
     }
 
+    /// Iterates over names of function parameters
+    pub fn param_names(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.0.types[self.0.index]
+            .param_names
+            .iter()
+            .map(|name| name.deref())
+    }
+
     /// Iterates over types of function parameters
     pub fn params(&self) -> impl ExactSizeIterator<Item = Type> + '_ {
         let params = self.0.types[self.0.index].params;
@@ -779,6 +787,15 @@ impl ComponentFunc {
             .map(|ty| Type::from(ty, &self.0.instance()))
     }
 
+    /// Iterates over names of function results, which are empty if the results
+    /// are unnamed
+    pub fn result_names(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.0.types[self.0.index]
+            .result_names
+            .iter()
+            .map(|name| name.deref())
+    }
+
     /// Iterates over types of function results
     pub fn results(&self) -> impl ExactSizeIterator<Item = Type> + '_ {
         let results = self.0.types[self.0.index].results;
@@ -944,7 +961,7 @@ impl ComponentItem {
                 engine,
                 ty.types[*idx].clone(),
             )),
-            TypeDef::Resource(idx) => Self::Resource(ty.resources[ty.types[*idx].ty]),
+            TypeDef::Resource(idx) => Self::Resource(ty.resource_type(*idx)),
         }
     }
 }
@@ -0,0 +1,623 @@
+//! Printing of component types as WIT.
+//!
+//! [`print_world`] renders the imports and exports of a [`types::Component`],
+//! for example one returned by [`Component::component_type`], as a WIT world.
+//! Imported and exported interfaces with names such as
+//! `wasi:cli/environment@0.2.0` are referenced by name from the world, while
+//! other instances are printed inline. As a WIT document defines a single
+//! package, [`print_packages`] renders the definitions of the named
+//! interfaces as one document per package, which can be placed in the `deps`
+//! directory next to the world.
+//!
+//! The names of types are recovered from the type imports and exports of the
+//! component, so types which aren't named by the component, which can't
+//! happen for components generated from WIT, are printed structurally and
+//! don't form valid WIT. Imported and exported core modules and components
+//! can't be represented in WIT and are printed as comments. Names, which are
+//! WIT keywords, are prefixed with `%`.
+//!
+//! [`Component::component_type`]: crate::component::Component::component_type
+
+use crate::component::types::{self, ComponentFunc, ComponentItem, ResourceType, Type};
+use crate::Engine;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+/// Name of the package and the world printed by [`print_world`].
+const ROOT: &str = "root";
+
+/// Keywords, which have to be prefixed with `%` to be used as names.
+const KEYWORDS: &[&str] = &[
+    "as",
+    "bool",
+    "borrow",
+    "char",
+    "constructor",
+    "enum",
+    "export",
+    "f32",
+    "f64",
+    "flags",
+    "float32",
+    "float64",
+    "from",
+    "func",
+    "future",
+    "import",
+    "include",
+    "interface",
+    "list",
+    "option",
+    "own",
+    "package",
+    "record",
+    "resource",
+    "result",
+    "s16",
+    "s32",
+    "s64",
+    "s8",
+    "static",
+    "stream",
+    "string",
+    "tuple",
+    "type",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "use",
+    "variant",
+    "with",
+    "world",
+];
+
+/// Returns `name` prefixed with `%` if it's a keyword.
+fn ident(name: &str) -> Cow<'_, str> {
+    if KEYWORDS.contains(&name) {
+        Cow::Owned(format!("%{name}"))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Returns a reference to the instance `name`, which is an interface name if
+/// it parses as one.
+fn instance_ref(name: &str) -> String {
+    match InterfaceName::parse(name) {
+        Some(interface) => interface.to_string(),
+        None => ident(name).into_owned(),
+    }
+}
+
+/// Renders the imports and exports of `component` as a WIT world.
+///
+/// Interfaces with names such as `wasi:cli/environment@0.2.0` are referenced
+/// by name, see [`print_packages`] for their definitions.
+pub fn print_world(engine: &Engine, component: &types::Component) -> String {
+    print(engine, component).0
+}
+
+/// Renders the interfaces with names such as `wasi:cli/environment@0.2.0`,
+/// which are imported or exported by `component`, as one WIT document per
+/// package, keyed by the name of the package.
+pub fn print_packages(engine: &Engine, component: &types::Component) -> BTreeMap<String, String> {
+    print(engine, component).1
+}
+
+/// Renders the world of `component` and the packages of its interfaces.
+fn print(engine: &Engine, component: &types::Component) -> (String, BTreeMap<String, String>) {
+    let imports = component.imports(engine).collect::<Vec<_>>();
+    let exports = component.exports(engine).collect::<Vec<_>>();
+
+    let mut printer = Printer::default();
+    for (name, item) in imports.iter().chain(&exports) {
+        printer.register(engine, "", name, item);
+    }
+
+    // Interfaces by package, printed in a document per package.
+    let mut packages = BTreeMap::<String, BTreeMap<String, String>>::new();
+    let mut scope = Scope::new("");
+    let mut items = String::new();
+    for (direction, items_of_direction) in [("import", &imports), ("export", &exports)] {
+        for (name, item) in items_of_direction.iter() {
+            let name = *name;
+            match item {
+                ComponentItem::ComponentInstance(instance) => {
+                    let exports = instance.exports(engine).collect::<Vec<_>>();
+                    match InterfaceName::parse(name) {
+                        Some(interface) => {
+                            writeln!(items, "  {direction} {interface};").unwrap();
+                            let package = packages.entry(interface.package()).or_default();
+                            if !package.contains_key(interface.name) {
+                                let body = printer.interface(name, &exports, "  ");
+                                package.insert(interface.name.to_string(), body);
+                            }
+                        }
+                        None => {
+                            let body = printer.interface(name, &exports, "    ");
+                            let name = ident(name);
+                            writeln!(items, "  {direction} {name}: interface {{").unwrap();
+                            items.push_str(&body);
+                            items.push_str("  }\n");
+                        }
+                    }
+                }
+                ComponentItem::ComponentFunc(func) => {
+                    let func = printer.func(&mut scope, name, func);
+                    writeln!(items, "  {direction} {func};").unwrap();
+                }
+                ComponentItem::Module(_) => {
+                    writeln!(items, "  // {direction} {name}: core module").unwrap();
+                }
+                ComponentItem::Component(_) => {
+                    writeln!(items, "  // {direction} {name}: component").unwrap();
+                }
+                ComponentItem::CoreFunc(_) => {
+                    writeln!(items, "  // {direction} {name}: core func").unwrap();
+                }
+                ComponentItem::Type(_) | ComponentItem::Resource(_) => {}
+            }
+        }
+    }
+
+    let mut types = String::new();
+    for (name, item) in imports.iter().chain(&exports) {
+        printer.typedef(&mut types, &mut scope, &[], name, item, "  ");
+    }
+
+    let mut world = format!("package {ROOT}:component;\n\nworld {ROOT} {{\n");
+    scope.write_uses(&mut world, "  ");
+    world.push_str(&types);
+    world.push_str(&items);
+    world.push_str("}\n");
+
+    let packages = packages
+        .into_iter()
+        .map(|(package, interfaces)| {
+            let mut out = format!("package {package};\n");
+            for (name, body) in interfaces {
+                let name = ident(&name);
+                write!(out, "\ninterface {name} {{\n{body}}}\n").unwrap();
+            }
+            (package, out)
+        })
+        .collect();
+    (world, packages)
+}
+
+/// An interface name of the form `namespace:package/interface@version`.
+struct InterfaceName<'a> {
+    namespace: &'a str,
+    package: &'a str,
+    name: &'a str,
+    version: Option<&'a str>,
+}
+
+impl<'a> InterfaceName<'a> {
+    fn parse(s: &'a str) -> Option<Self> {
+        let (namespace, rest) = s.split_once(':')?;
+        let (package, rest) = rest.split_once('/')?;
+        let (name, version) = match rest.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (rest, None),
+        };
+        Some(Self {
+            namespace,
+            package,
+            name,
+            version,
+        })
+    }
+
+    fn package(&self) -> String {
+        let (namespace, package) = (ident(self.namespace), ident(self.package));
+        match self.version {
+            Some(version) => format!("{namespace}:{package}@{version}"),
+            None => format!("{namespace}:{package}"),
+        }
+    }
+}
+
+impl fmt::Display for InterfaceName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (namespace, package) = (ident(self.namespace), ident(self.package));
+        write!(f, "{namespace}:{package}/{}", ident(self.name))?;
+        if let Some(version) = self.version {
+            write!(f, "@{version}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A type named by an import or export of the component.
+enum Named {
+    Type(Type),
+    Resource(ResourceType),
+}
+
+/// A named type along with the instance it's defined in.
+struct Definition {
+    /// Name of the instance, which is empty for the world.
+    owner: String,
+    name: String,
+    ty: Named,
+}
+
+impl Definition {
+    fn is(&self, ty: &Named) -> bool {
+        match (&self.ty, ty) {
+            (Named::Type(a), Named::Type(b)) => a == b,
+            (Named::Resource(a), Named::Resource(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Interface or world being printed, collecting the types it has to `use`
+/// from other interfaces.
+struct Scope {
+    owner: String,
+    /// Items used by interface, as `(name, alias)`.
+    uses: BTreeMap<String, BTreeSet<(String, String)>>,
+}
+
+impl Scope {
+    fn new(owner: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            uses: BTreeMap::new(),
+        }
+    }
+
+    fn write_uses(&self, out: &mut String, indent: &str) {
+        for (interface, items) in &self.uses {
+            let items = items
+                .iter()
+                .map(|(name, alias)| {
+                    if name == alias {
+                        ident(name).into_owned()
+                    } else {
+                        format!("{} as {}", ident(name), ident(alias))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let interface = instance_ref(interface);
+            writeln!(out, "{indent}use {interface}.{{{items}}};").unwrap();
+        }
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    definitions: Vec<Definition>,
+}
+
+impl Printer {
+    /// Records the types named by `item`, recursing into instances.
+    fn register(&mut self, engine: &Engine, owner: &str, name: &str, item: &ComponentItem) {
+        let ty = match item {
+            ComponentItem::ComponentInstance(instance) if owner.is_empty() => {
+                for (export, item) in instance.exports(engine) {
+                    self.register(engine, name, export, &item);
+                }
+                return;
+            }
+            ComponentItem::Type(ty) => Named::Type(ty.clone()),
+            ComponentItem::Resource(ty) => Named::Resource(*ty),
+            _ => return,
+        };
+        self.definitions.push(Definition {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            ty,
+        });
+    }
+
+    /// Returns the name of `ty` within `scope`, adding a `use` of it if it's
+    /// defined elsewhere.
+    fn name_of(&self, scope: &mut Scope, ty: &Named) -> Option<String> {
+        if let Some(def) = self
+            .definitions
+            .iter()
+            .find(|def| def.owner == scope.owner && def.is(ty))
+        {
+            return Some(ident(&def.name).into_owned());
+        }
+        let def = self.definitions.iter().find(|def| def.is(ty))?;
+        scope
+            .uses
+            .entry(def.owner.clone())
+            .or_default()
+            .insert((def.name.clone(), def.name.clone()));
+        Some(ident(&def.name).into_owned())
+    }
+
+    /// Renders the body of the interface `owner` with `exports`.
+    fn interface(&self, owner: &str, exports: &[(&str, ComponentItem)], indent: &str) -> String {
+        let mut scope = Scope::new(owner);
+
+        // Functions of resources are printed within the resource.
+        let mut methods = BTreeMap::<&str, Vec<(&str, &ComponentFunc)>>::new();
+        let mut funcs = Vec::new();
+        for (name, item) in exports {
+            if let ComponentItem::ComponentFunc(func) = item {
+                match resource_of(name) {
+                    Some(resource) => methods.entry(resource).or_default().push((name, func)),
+                    None => funcs.push((*name, func)),
+                }
+            }
+        }
+
+        let mut body = String::new();
+        for (name, item) in exports {
+            let methods = methods.get(name).map(|m| m.as_slice()).unwrap_or(&[]);
+            self.typedef(&mut body, &mut scope, methods, name, item, indent);
+        }
+        for (name, func) in funcs {
+            let func = self.func(&mut scope, name, func);
+            writeln!(body, "{indent}{func};").unwrap();
+        }
+
+        let mut out = String::new();
+        scope.write_uses(&mut out, indent);
+        out.push_str(&body);
+        out
+    }
+
+    /// Renders the definition of the type `item` named `name`, if it's a
+    /// type, with the functions of a resource given by `methods`.
+    fn typedef(
+        &self,
+        out: &mut String,
+        scope: &mut Scope,
+        methods: &[(&str, &ComponentFunc)],
+        name: &str,
+        item: &ComponentItem,
+        indent: &str,
+    ) {
+        let ty = match item {
+            ComponentItem::Type(ty) => Named::Type(ty.clone()),
+            ComponentItem::Resource(ty) => Named::Resource(*ty),
+            _ => return,
+        };
+
+        // Types defined elsewhere, either in another interface or under
+        // another name, are used or aliased. Resources are compared by
+        // identity, but other types structurally, so for those a definition
+        // of the same name is preferred.
+        let same_name = match ty {
+            Named::Type(_) => self
+                .definitions
+                .iter()
+                .find(|def| def.is(&ty) && def.name == name),
+            Named::Resource(_) => None,
+        };
+        let def = same_name
+            .or_else(|| self.definitions.iter().find(|def| def.is(&ty)))
+            .unwrap();
+        if def.owner != scope.owner {
+            scope
+                .uses
+                .entry(def.owner.clone())
+                .or_default()
+                .insert((def.name.clone(), name.to_string()));
+            return;
+        }
+        if def.name != name {
+            let (name, def_name) = (ident(name), ident(&def.name));
+            writeln!(out, "{indent}type {name} = {def_name};").unwrap();
+            return;
+        }
+        let name = ident(name);
+
+        let ty = match ty {
+            Named::Type(ty) => ty,
+            Named::Resource(_) if methods.is_empty() => {
+                writeln!(out, "{indent}resource {name};").unwrap();
+                return;
+            }
+            Named::Resource(_) => {
+                writeln!(out, "{indent}resource {name} {{").unwrap();
+                for (func_name, func) in methods {
+                    let func = self.func(scope, func_name, func);
+                    writeln!(out, "{indent}  {func};").unwrap();
+                }
+                writeln!(out, "{indent}}}").unwrap();
+                return;
+            }
+        };
+        match &ty {
+            Type::Record(record) => {
+                writeln!(out, "{indent}record {name} {{").unwrap();
+                for field in record.fields() {
+                    let ty = self.ty(scope, &field.ty);
+                    writeln!(out, "{indent}  {}: {ty},", ident(field.name)).unwrap();
+                }
+                writeln!(out, "{indent}}}").unwrap();
+            }
+            Type::Variant(variant) => {
+                writeln!(out, "{indent}variant {name} {{").unwrap();
+                for case in variant.cases() {
+                    match &case.ty {
+                        Some(ty) => {
+                            let ty = self.ty(scope, ty);
+                            writeln!(out, "{indent}  {}({ty}),", ident(case.name)).unwrap();
+                        }
+                        None => writeln!(out, "{indent}  {},", ident(case.name)).unwrap(),
+                    }
+                }
+                writeln!(out, "{indent}}}").unwrap();
+            }
+            Type::Enum(enum_) => {
+                writeln!(out, "{indent}enum {name} {{").unwrap();
+                for case in enum_.names() {
+                    writeln!(out, "{indent}  {},", ident(case)).unwrap();
+                }
+                writeln!(out, "{indent}}}").unwrap();
+            }
+            Type::Flags(flags) => {
+                writeln!(out, "{indent}flags {name} {{").unwrap();
+                for flag in flags.names() {
+                    writeln!(out, "{indent}  {},", ident(flag)).unwrap();
+                }
+                writeln!(out, "{indent}}}").unwrap();
+            }
+            ty => {
+                let ty = self.structural(scope, ty);
+                writeln!(out, "{indent}type {name} = {ty};").unwrap();
+            }
+        }
+    }
+
+    /// Renders the function `func` named `name`.
+    fn func(&self, scope: &mut Scope, name: &str, func: &ComponentFunc) -> String {
+        let (name, kind, skip) = if name.starts_with("[constructor]") {
+            (Cow::Borrowed("constructor"), "", 0)
+        } else if let Some(method) = name.strip_prefix("[method]") {
+            let (_, method) = method.split_once('.').unwrap_or(("", method));
+            (ident(method), ": func", 1)
+        } else if let Some(method) = name.strip_prefix("[static]") {
+            let (_, method) = method.split_once('.').unwrap_or(("", method));
+            (ident(method), ": static func", 0)
+        } else {
+            (ident(name), ": func", 0)
+        };
+
+        let params = func
+            .param_names()
+            .zip(func.params())
+            .skip(skip)
+            .map(|(name, ty)| format!("{}: {}", ident(name), self.ty(scope, &ty)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut out = format!("{name}{kind}({params})");
+        if kind.is_empty() {
+            // The result of constructors is implicit.
+            return out;
+        }
+
+        let results = func.results().collect::<Vec<_>>();
+        let names = func.result_names().collect::<Vec<_>>();
+        match (&results[..], names.is_empty()) {
+            ([], _) => {}
+            ([ty], true) => write!(out, " -> {}", self.ty(scope, ty)).unwrap(),
+            (results, _) => {
+                let results = names
+                    .iter()
+                    .zip(results)
+                    .map(|(name, ty)| format!("{}: {}", ident(name), self.ty(scope, ty)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(out, " -> ({results})").unwrap();
+            }
+        }
+        out
+    }
+
+    /// Renders a reference to `ty`, by name if it's named.
+    fn ty(&self, scope: &mut Scope, ty: &Type) -> String {
+        match ty {
+            Type::Own(resource) => {
+                return self
+                    .name_of(scope, &Named::Resource(*resource))
+                    .unwrap_or_else(|| "resource".to_string())
+            }
+            Type::Borrow(resource) => {
+                let name = self
+                    .name_of(scope, &Named::Resource(*resource))
+                    .unwrap_or_else(|| "resource".to_string());
+                return format!("borrow<{name}>");
+            }
+            _ => {}
+        }
+        // Only nominal types are referred to by name, as e.g. a
+        // `type bytes = list<u8>` may not be meant wherever a `list<u8>`
+        // appears.
+        let nominal = matches!(
+            ty,
+            Type::Record(_) | Type::Variant(_) | Type::Enum(_) | Type::Flags(_)
+        );
+        if nominal {
+            if let Some(name) = self.name_of(scope, &Named::Type(ty.clone())) {
+                return name;
+            }
+        }
+        self.structural(scope, ty)
+    }
+
+    /// Renders `ty` structurally.
+    fn structural(&self, scope: &mut Scope, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "bool".into(),
+            Type::S8 => "s8".into(),
+            Type::U8 => "u8".into(),
+            Type::S16 => "s16".into(),
+            Type::U16 => "u16".into(),
+            Type::S32 => "s32".into(),
+            Type::U32 => "u32".into(),
+            Type::S64 => "s64".into(),
+            Type::U64 => "u64".into(),
+            Type::Float32 => "float32".into(),
+            Type::Float64 => "float64".into(),
+            Type::Char => "char".into(),
+            Type::String => "string".into(),
+            Type::List(list) => format!("list<{}>", self.ty(scope, &list.ty())),
+            Type::Tuple(tuple) => {
+                let types = tuple
+                    .types()
+                    .map(|ty| self.ty(scope, &ty))
+                    .collect::<Vec<_>>();
+                format!("tuple<{}>", types.join(", "))
+            }
+            Type::Option(option) => format!("option<{}>", self.ty(scope, &option.ty())),
+            Type::Result(result) => match (result.ok(), result.err()) {
+                (None, None) => "result".into(),
+                (Some(ok), None) => format!("result<{}>", self.ty(scope, &ok)),
+                (None, Some(err)) => format!("result<_, {}>", self.ty(scope, &err)),
+                (Some(ok), Some(err)) => {
+                    format!("result<{}, {}>", self.ty(scope, &ok), self.ty(scope, &err))
+                }
+            },
+            Type::Record(record) => {
+                let fields = record
+                    .fields()
+                    .map(|field| format!("{}: {}", ident(field.name), self.ty(scope, &field.ty)))
+                    .collect::<Vec<_>>();
+                format!("record {{ {} }}", fields.join(", "))
+            }
+            Type::Variant(variant) => {
+                let cases = variant
+                    .cases()
+                    .map(|case| match &case.ty {
+                        Some(ty) => format!("{}({})", ident(case.name), self.ty(scope, ty)),
+                        None => ident(case.name).into_owned(),
+                    })
+                    .collect::<Vec<_>>();
+                format!("variant {{ {} }}", cases.join(", "))
+            }
+            Type::Enum(enum_) => {
+                let names = enum_.names().map(ident).collect::<Vec<_>>();
+                format!("enum {{ {} }}", names.join(", "))
+            }
+            Type::Flags(flags) => {
+                let names = flags.names().map(ident).collect::<Vec<_>>();
+                format!("flags {{ {} }}", names.join(", "))
+            }
+            Type::Own(_) | Type::Borrow(_) => self.ty(scope, ty),
+        }
+    }
+}
+
+/// Returns the name of the resource the function `name` belongs to, if any.
+fn resource_of(name: &str) -> Option<&str> {
+    if let Some(resource) = name.strip_prefix("[constructor]") {
+        return Some(resource);
+    }
+    let method = name
+        .strip_prefix("[method]")
+        .or_else(|| name.strip_prefix("[static]"))?;
+    Some(method.split_once('.')?.0)
+}
@@ -124,7 +124,7 @@ impl Table {
         TableType::from_wasmtime_table(store.engine(), ty)
     }
 
-    fn wasmtime_table(
+    pub(crate) fn wasmtime_table(
         &self,
         store: &mut StoreOpaque,
         lazy_init_range: impl Iterator<Item = u32>,
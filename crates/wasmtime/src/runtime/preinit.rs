@@ -0,0 +1,417 @@
+//! Pre-initialization of modules by snapshotting their state after running an
+//! initialization function, similar to [Wizer].
+//!
+//! [Wizer]: https://github.com/bytecodealliance/wizer
+
+use crate::checkpoint::{FuncRef, InstanceState, Value};
+use crate::{Engine, Instance, Module, Store, UpdateDeadline};
+use anyhow::{bail, ensure, Context, Result};
+use std::ops::Range;
+use wasm_encoder::{ConstExpr, Encode, HeapType, RawSection, RefType};
+use wasmparser::{DataKind, ElementItems, ElementKind, ExternalKind, Parser, Payload, ValType};
+use wasmtime_environ::WASM_PAGE_SIZE;
+
+/// Runs of zero bytes shorter than this are included in the surrounding data
+/// segment rather than splitting it, as each segment has its own overhead.
+const MIN_ZERO_RUN: usize = 16;
+
+/// Maximum number of data segments written for the contents of all memories,
+/// the same as Wizer's, well below the limit of 100,000 data segments per
+/// module.
+const MAX_DATA_SEGMENTS: usize = 10_000;
+
+#[cfg_attr(docsrs, doc(cfg(feature = "preinit")))]
+impl Engine {
+    /// Pre-initializes a WebAssembly module by running its initialization
+    /// function ahead of time.
+    ///
+    /// The module in `bytes`, which may be in the text format when the `wat`
+    /// feature is enabled, is instantiated within this engine and its exported
+    /// function `init_func`, which must take no parameters and return no
+    /// results, is called. The state of the instance afterwards is then
+    /// written back into a copy of the original module which is returned in
+    /// its binary format:
+    ///
+    /// * Each defined memory's data segments are replaced with the memory's
+    ///   contents, and its minimum size becomes its current size.
+    /// * Each defined table's active element segments are replaced with the
+    ///   table's contents, and its minimum size becomes its current size.
+    /// * Each mutable global's initializer is replaced with its current value.
+    /// * The start function, if any, is removed since it has already run, as
+    ///   is the export of `init_func`.
+    ///
+    /// Instantiating the returned module is then equivalent to instantiating
+    /// the original module and calling `init_func`, but without doing the
+    /// work at runtime. The result can be compiled as usual, for example with
+    /// [`Engine::precompile_module`], to move that work to build time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module fails to compile or instantiate, or if
+    /// `init_func` doesn't exist or traps. The module also must not have any
+    /// imports, and its state must be representable in a module, so the same
+    /// restrictions as [`Store::checkpoint`](crate::Store::checkpoint) apply.
+    /// Whether passive segments were dropped by `init_func` is not preserved.
+    ///
+    /// This engine must be able to execute code on the host, so it can't be
+    /// configured with [`Config::target`](crate::Config::target) for another
+    /// platform, and it must not have
+    /// [`Config::async_support`](crate::Config::async_support) enabled.
+    pub fn preinitialize(&self, bytes: &[u8], init_func: &str) -> Result<Vec<u8>> {
+        ensure!(
+            !self.config().async_support,
+            "pre-initialization is not supported with async support enabled"
+        );
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes)?;
+        let module = Module::new(self, &bytes)?;
+        ensure!(
+            module.imports().len() == 0,
+            "cannot pre-initialize a module with imports"
+        );
+
+        let mut store = Store::new(self, ());
+        if self.tunables().consume_fuel {
+            store.set_fuel(u64::MAX)?;
+        }
+        if self.tunables().epoch_interruption {
+            store.epoch_deadline_callback(|_| Ok(UpdateDeadline::Continue(1)));
+        }
+        let instance = Instance::new(&mut store, &module, &[])?;
+        instance
+            .get_typed_func::<(), ()>(&mut store, init_func)?
+            .call(&mut store, ())
+            .with_context(|| format!("failed to run initialization function `{init_func}`"))?;
+
+        let mut checkpoint = store.checkpoint()?;
+        let state = checkpoint.instances.pop().unwrap();
+        rewrite(&bytes, init_func, &state)
+    }
+}
+
+/// Copies the module in `wasm`, replacing its initial state with `state`.
+fn rewrite(wasm: &[u8], init_func: &str, state: &InstanceState) -> Result<Vec<u8>> {
+    let page_size = usize::try_from(WASM_PAGE_SIZE).unwrap();
+    let mut module = wasm_encoder::Module::new();
+    let mut memory64 = Vec::new();
+    let data_ranges = data_ranges(state);
+    let mut wrote_elements = false;
+    let mut wrote_data = false;
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+
+        // The snapshot's element segments need to be written even if the
+        // original module didn't have an element section, so do so once the
+        // first section which follows it is reached.
+        let follows_elements = matches!(
+            payload,
+            Payload::DataCountSection { .. }
+                | Payload::CodeSectionStart { .. }
+                | Payload::DataSection(_)
+                | Payload::End(_)
+        );
+        if follows_elements && !wrote_elements {
+            let mut section = wasm_encoder::ElementSection::new();
+            snapshot_elements(&mut section, state)?;
+            if !section.is_empty() {
+                module.section(&section);
+            }
+            wrote_elements = true;
+        }
+
+        match payload {
+            Payload::Version { .. } => continue,
+            Payload::End(_) => {
+                if !wrote_data {
+                    let mut section = wasm_encoder::DataSection::new();
+                    snapshot_data(&mut section, state, &data_ranges, &memory64);
+                    if !section.is_empty() {
+                        module.section(&section);
+                    }
+                }
+                break;
+            }
+
+            Payload::TableSection(reader) => {
+                let mut section = wasm_encoder::TableSection::new();
+                for (table, elements) in reader.into_iter().zip(&state.tables) {
+                    let table = table?;
+                    // Any initialization expression is subsumed by the
+                    // element segments written for the table's contents.
+                    section.table(wasm_encoder::TableType {
+                        element_type: ref_type(table.ty.element_type)?,
+                        minimum: u32::try_from(elements.len()).unwrap(),
+                        maximum: table.ty.maximum,
+                    });
+                }
+                module.section(&section);
+            }
+
+            Payload::MemorySection(reader) => {
+                let mut section = wasm_encoder::MemorySection::new();
+                for (memory, state) in reader.into_iter().zip(&state.memories) {
+                    let memory = memory?;
+                    memory64.push(memory.memory64);
+                    section.memory(wasm_encoder::MemoryType {
+                        minimum: u64::try_from(state.data.len() / page_size).unwrap(),
+                        maximum: memory.maximum,
+                        memory64: memory.memory64,
+                        shared: memory.shared,
+                    });
+                }
+                module.section(&section);
+            }
+
+            Payload::GlobalSection(reader) => {
+                let mut section = wasm_encoder::GlobalSection::new();
+                for (global, value) in reader.into_iter_with_offsets().zip(&state.globals) {
+                    let (offset, global) = global?;
+                    let init = global.init_expr.get_binary_reader().range();
+                    let mut bytes = wasm[offset..init.start].to_vec();
+                    match value {
+                        Some(value) => {
+                            const_expr(global.ty.content_type, value)?.encode(&mut bytes);
+                        }
+                        None => bytes.extend_from_slice(&wasm[init]),
+                    }
+                    section.raw(&bytes);
+                }
+                module.section(&section);
+            }
+
+            Payload::ExportSection(reader) => {
+                let mut section = wasm_encoder::ExportSection::new();
+                for export in reader {
+                    let export = export?;
+                    let kind = match export.kind {
+                        ExternalKind::Func if export.name == init_func => continue,
+                        ExternalKind::Func => wasm_encoder::ExportKind::Func,
+                        ExternalKind::Table => wasm_encoder::ExportKind::Table,
+                        ExternalKind::Memory => wasm_encoder::ExportKind::Memory,
+                        ExternalKind::Global => wasm_encoder::ExportKind::Global,
+                        ExternalKind::Tag => wasm_encoder::ExportKind::Tag,
+                    };
+                    section.export(export.name, kind, export.index);
+                }
+                module.section(&section);
+            }
+
+            // The start function has already run as part of instantiation.
+            Payload::StartSection { .. } => {}
+
+            // Active segments are replaced with empty passive segments, rather
+            // than removed, so that the indices of all other segments are
+            // unchanged. Active segments are dropped after instantiation so
+            // this doesn't change the behavior of any bulk memory operations.
+            Payload::ElementSection(reader) => {
+                let mut section = wasm_encoder::ElementSection::new();
+                for element in reader {
+                    let element = element?;
+                    match (element.kind, element.items) {
+                        (ElementKind::Active { .. }, ElementItems::Functions(_)) => {
+                            section.passive(wasm_encoder::Elements::Functions(&[]));
+                        }
+                        (ElementKind::Active { .. }, ElementItems::Expressions(ty, _)) => {
+                            section
+                                .passive(wasm_encoder::Elements::Expressions(ref_type(ty)?, &[]));
+                        }
+                        _ => {
+                            section.raw(&wasm[element.range]);
+                        }
+                    }
+                }
+                snapshot_elements(&mut section, state)?;
+                module.section(&section);
+                wrote_elements = true;
+            }
+            Payload::DataCountSection { count, .. } => {
+                let snapshot = data_ranges.iter().map(Vec::len).sum::<usize>();
+                module.section(&wasm_encoder::DataCountSection {
+                    count: count + u32::try_from(snapshot).unwrap(),
+                });
+            }
+            Payload::DataSection(reader) => {
+                let mut section = wasm_encoder::DataSection::new();
+                for data in reader {
+                    let data = data?;
+                    match data.kind {
+                        DataKind::Active { .. } => {
+                            section.passive([]);
+                        }
+                        DataKind::Passive => {
+                            section.raw(&wasm[data.range]);
+                        }
+                    }
+                }
+                snapshot_data(&mut section, state, &data_ranges, &memory64);
+                module.section(&section);
+                wrote_data = true;
+            }
+
+            // The code section is copied as a whole along with all other
+            // sections below.
+            Payload::CodeSectionEntry(_) => {}
+
+            other => match other.as_section() {
+                Some((id, range)) => {
+                    module.section(&RawSection {
+                        id,
+                        data: &wasm[range],
+                    });
+                }
+                None => bail!("unexpected payload in a core module"),
+            },
+        }
+    }
+
+    Ok(module.finish())
+}
+
+/// Appends active element segments with the contents of the tables in
+/// `state` to `section`.
+fn snapshot_elements(
+    section: &mut wasm_encoder::ElementSection,
+    state: &InstanceState,
+) -> Result<()> {
+    for (index, elements) in state.tables.iter().enumerate() {
+        let Some(start) = elements.iter().position(|e| e.is_some()) else {
+            continue;
+        };
+        let end = elements.iter().rposition(|e| e.is_some()).unwrap() + 1;
+        let exprs = elements[start..end]
+            .iter()
+            .map(|e| match e {
+                Some(func) => func_ref(func),
+                None => ConstExpr::ref_null(HeapType::Func),
+            })
+            .collect::<Vec<_>>();
+        section.active(
+            Some(u32::try_from(index).unwrap()),
+            &ConstExpr::i32_const(start as i32),
+            wasm_encoder::Elements::Expressions(RefType::FUNCREF, &exprs),
+        );
+    }
+    Ok(())
+}
+
+/// Appends active data segments with the contents of the memories in `state`
+/// within `data_ranges`, as returned by [`data_ranges`], to `section`.
+fn snapshot_data(
+    section: &mut wasm_encoder::DataSection,
+    state: &InstanceState,
+    data_ranges: &[Vec<Range<usize>>],
+    memory64: &[bool],
+) {
+    for (index, (memory, ranges)) in state.memories.iter().zip(data_ranges).enumerate() {
+        for range in ranges.iter().cloned() {
+            let offset = if memory64[index] {
+                ConstExpr::i64_const(range.start as i64)
+            } else {
+                ConstExpr::i32_const(range.start as i32)
+            };
+            section.active(
+                u32::try_from(index).unwrap(),
+                &offset,
+                memory.data[range].iter().copied(),
+            );
+        }
+    }
+}
+
+/// Returns the ranges of each memory's data in `state` which need to be
+/// written as data segments.
+///
+/// If there would be more than [`MAX_DATA_SEGMENTS`] ranges, those separated
+/// by the shortest runs of zeros are merged, like Wizer does.
+fn data_ranges(state: &InstanceState) -> Vec<Vec<Range<usize>>> {
+    let mut ranges = state
+        .memories
+        .iter()
+        .map(|memory| nonzero_ranges(&memory.data))
+        .collect::<Vec<_>>();
+    let count = ranges.iter().map(Vec::len).sum::<usize>();
+    if count <= MAX_DATA_SEGMENTS {
+        return ranges;
+    }
+
+    // Gaps between the ranges of each memory as `(len, memory, range)`, where
+    // `range` is the index of the range following the gap.
+    let mut gaps = ranges
+        .iter()
+        .enumerate()
+        .flat_map(|(memory, ranges)| {
+            ranges
+                .windows(2)
+                .enumerate()
+                .map(move |(i, pair)| (pair[1].start - pair[0].end, memory, i + 1))
+        })
+        .collect::<Vec<_>>();
+    gaps.sort_unstable();
+    let mut merge = ranges
+        .iter()
+        .map(|ranges| vec![false; ranges.len()])
+        .collect::<Vec<_>>();
+    for (_, memory, range) in gaps.into_iter().take(count - MAX_DATA_SEGMENTS) {
+        merge[memory][range] = true;
+    }
+    for (ranges, merge) in ranges.iter_mut().zip(merge) {
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for (range, merge) in ranges.drain(..).zip(merge) {
+            match merged.last_mut() {
+                Some(prev) if merge => prev.end = range.end,
+                _ => merged.push(range),
+            }
+        }
+        *ranges = merged;
+    }
+    ranges
+}
+
+/// Returns the ranges of `data` which need to be written as data segments,
+/// skipping long runs of zeros.
+fn nonzero_ranges(data: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut i = 0;
+    while let Some(start) = data[i..].iter().position(|b| *b != 0) {
+        let start = i + start;
+        let end = data[start..]
+            .iter()
+            .position(|b| *b == 0)
+            .map_or(data.len(), |n| start + n);
+        match ranges.last_mut() {
+            Some(prev) if start - prev.end < MIN_ZERO_RUN => prev.end = end,
+            _ => ranges.push(start..end),
+        }
+        i = end;
+    }
+    ranges
+}
+
+fn const_expr(ty: ValType, value: &Value) -> Result<ConstExpr> {
+    Ok(match (ty, value) {
+        (_, Value::I32(v)) => ConstExpr::i32_const(*v),
+        (_, Value::I64(v)) => ConstExpr::i64_const(*v),
+        (_, Value::F32(v)) => ConstExpr::f32_const(f32::from_bits(*v)),
+        (_, Value::F64(v)) => ConstExpr::f64_const(f64::from_bits(*v)),
+        (_, Value::V128(v)) => ConstExpr::v128_const(*v as i128),
+        (_, Value::Ref(Some(func))) => func_ref(func),
+        (ValType::Ref(ty), Value::Ref(None)) => ConstExpr::ref_null(ref_type(ty)?.heap_type),
+        (_, Value::Ref(None)) => unreachable!(),
+    })
+}
+
+fn func_ref(func: &FuncRef) -> ConstExpr {
+    debug_assert_eq!(func.instance, 0);
+    ConstExpr::ref_func(func.func)
+}
+
+fn ref_type(ty: wasmparser::RefType) -> Result<RefType> {
+    if ty == wasmparser::RefType::FUNCREF {
+        Ok(RefType::FUNCREF)
+    } else if ty == wasmparser::RefType::EXTERNREF {
+        Ok(RefType::EXTERNREF)
+    } else {
+        bail!("typed function references are not supported")
+    }
+}
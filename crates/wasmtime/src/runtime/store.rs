@@ -81,7 +81,7 @@ use crate::linker::Definition;
 use crate::module::{BareModuleInfo, RegisteredModuleId};
 use crate::trampoline::VMHostGlobalContext;
 use crate::{module::ModuleRegistry, Engine, Module, Trap, Val, ValRaw};
-use crate::{Global, Instance, Memory};
+use crate::{Checkpoint, Global, Instance, Memory};
 use anyhow::{anyhow, bail, Result};
 use std::cell::UnsafeCell;
 use std::fmt;
@@ -801,6 +801,74 @@ impl<T> Store<T> {
         self.inner.gc()
     }
 
+    /// Captures the mutable state of all instances in this store, which can
+    /// later be applied to another store with [`Store::restore`].
+    ///
+    /// See [`Checkpoint`] for what is and isn't captured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an instance has state which can't be captured, such
+    /// as a shared memory or a table containing a host-defined function.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let engine = Engine::default();
+    /// let module = Module::new(
+    ///     &engine,
+    ///     r#"
+    ///         (module
+    ///             (global $count (export "count") (mut i32) (i32.const 0))
+    ///             (func (export "bump") (global.set $count (i32.add (global.get $count) (i32.const 1))))
+    ///         )
+    ///     "#,
+    /// )?;
+    ///
+    /// let mut store = Store::new(&engine, ());
+    /// let instance = Instance::new(&mut store, &module, &[])?;
+    /// instance.get_typed_func::<(), ()>(&mut store, "bump")?.call(&mut store, ())?;
+    /// let checkpoint = store.checkpoint()?;
+    ///
+    /// // Instantiate the same module in a new store and resume from the
+    /// // checkpoint.
+    /// let mut store = Store::new(&engine, ());
+    /// let instance = Instance::new(&mut store, &module, &[])?;
+    /// store.restore(&checkpoint)?;
+    /// let count = instance.get_global(&mut store, "count").unwrap();
+    /// assert_eq!(count.get(&mut store).i32(), Some(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checkpoint(&mut self) -> Result<Checkpoint> {
+        crate::checkpoint::checkpoint(self.as_context_mut())
+    }
+
+    /// Restores the state captured in `checkpoint` into the instances of this
+    /// store.
+    ///
+    /// The same modules that were instantiated in the store that `checkpoint`
+    /// was created from must have been instantiated in this store, in the same
+    /// order. Memories and tables are grown as necessary, going through this
+    /// store's [`ResourceLimiter`](crate::ResourceLimiter), so like
+    /// [`Memory::grow`] this method will panic when used with a
+    /// [`ResourceLimiterAsync`](crate::ResourceLimiterAsync).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this store's instances don't match those in
+    /// `checkpoint`, in which case the store is left unchanged. The store is
+    /// validated against the whole checkpoint before any state is restored,
+    /// so only two failures can leave it partially restored: a memory or
+    /// table that can't be grown to its size in the checkpoint, in which case
+    /// some memories and tables may have been grown but nothing has been
+    /// written, and failing to map a memory's contents copy-on-write.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        crate::checkpoint::restore(self.as_context_mut(), checkpoint)
+    }
+
     /// Returns the amount fuel in this [`Store`]. When fuel is enabled, it must
     /// be configured via [`Store::set_fuel`].
     ///
@@ -1046,6 +1114,20 @@ impl<'a, T> StoreContextMut<'a, T> {
         self.0.gc()
     }
 
+    /// Captures the mutable state of all instances in this store.
+    ///
+    /// For more information see [`Store::checkpoint`].
+    pub fn checkpoint(&mut self) -> Result<Checkpoint> {
+        crate::checkpoint::checkpoint(self.as_context_mut())
+    }
+
+    /// Restores the state captured in `checkpoint` into this store.
+    ///
+    /// For more information see [`Store::restore`].
+    pub fn restore(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        crate::checkpoint::restore(self.as_context_mut(), checkpoint)
+    }
+
     /// Returns remaining fuel in this store.
     ///
     /// For more information see [`Store::get_fuel`]
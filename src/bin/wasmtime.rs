@@ -80,6 +80,10 @@ enum Subcommand {
     /// Runs a WebAssembly test script file
     #[cfg(feature = "wast")]
     Wast(wasmtime_cli::commands::WastCommand),
+
+    /// Prints the WIT world of a WebAssembly component.
+    #[cfg(feature = "wit")]
+    Wit(wasmtime_cli::commands::WitCommand),
 }
 
 impl Wasmtime {
@@ -111,6 +115,9 @@ impl Wasmtime {
 
             #[cfg(feature = "wast")]
             Subcommand::Wast(c) => c.execute(),
+
+            #[cfg(feature = "wit")]
+            Subcommand::Wit(c) => c.execute(),
         }
     }
 }
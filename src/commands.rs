@@ -30,6 +30,11 @@ mod compile;
 #[cfg(feature = "compile")]
 pub use self::compile::*;
 
+#[cfg(feature = "wit")]
+mod wit;
+#[cfg(feature = "wit")]
+pub use self::wit::*;
+
 #[cfg(feature = "cranelift")]
 mod settings;
 #[cfg(feature = "cranelift")]
@@ -24,7 +24,11 @@ static AFTER_HELP: Lazy<String> = Lazy::new(|| {
         \n\
         Compiling for a specific platform (Linux) and CPU preset (Skylake):\n\
         \n  \
-        wasmtime compile --target x86_64-unknown-linux -Ccranelift-skylake foo.wasm\n",
+        wasmtime compile --target x86_64-unknown-linux -Ccranelift-skylake foo.wasm\n\
+        \n\
+        Running the `init` export ahead of time and compiling its resulting state:\n\
+        \n  \
+        wasmtime compile --init-func init foo.wasm\n",
     )
 });
 
@@ -51,6 +55,14 @@ pub struct CompileCommand {
     #[arg(long = "emit-clif", value_name = "PATH")]
     pub emit_clif: Option<PathBuf>,
 
+    /// The name of an exported function to run ahead of time, compiling the
+    /// module with the resulting memory, table and global state.
+    ///
+    /// The module must not have any imports, and the function must take no
+    /// parameters and return no results.
+    #[arg(long = "init-func", value_name = "FUNCTION")]
+    pub init_func: Option<String>,
+
     /// The path of the WebAssembly to compile
     #[arg(index = 1, value_name = "MODULE")]
     pub module: PathBuf,
@@ -99,6 +111,27 @@ impl CompileCommand {
             output
         });
 
+        #[cfg(feature = "preinit")]
+        let input = match &self.init_func {
+            Some(init_func) => {
+                if wasmparser::Parser::is_component(&input) {
+                    bail!("'--init-func' is not supported for components");
+                }
+                // The initialization function runs on the host, so when
+                // cross-compiling use a separate engine for the host.
+                let host = match self.target {
+                    Some(_) => Engine::new(&self.common.config(None)?)?,
+                    None => engine.clone(),
+                };
+                host.preinitialize(&input, init_func)?
+            }
+            None => input,
+        };
+        #[cfg(not(feature = "preinit"))]
+        if self.init_func.is_some() {
+            bail!("support for pre-initialization was disabled at compile time");
+        }
+
         let output_bytes = if wasmparser::Parser::is_component(&input) {
             #[cfg(feature = "component-model")]
             {
@@ -156,6 +189,46 @@ mod test {
         Ok(())
     }
 
+    #[cfg(feature = "preinit")]
+    #[test]
+    fn test_init_func_compile() -> Result<()> {
+        let (mut input, input_path) = NamedTempFile::new()?.into_parts();
+        input.write_all(
+            "(module
+                (global $g (mut i32) (i32.const 0))
+                (func (export \"init\") (global.set $g (i32.const 42)))
+                (func (export \"f\") (result i32) global.get $g)
+            )"
+            .as_bytes(),
+        )?;
+        drop(input);
+
+        let output_path = NamedTempFile::new()?.into_temp_path();
+
+        let command = CompileCommand::try_parse_from(vec![
+            "compile",
+            "-Dlogging=n",
+            "--init-func",
+            "init",
+            "-o",
+            output_path.to_str().unwrap(),
+            input_path.to_str().unwrap(),
+        ])?;
+
+        command.execute()?;
+
+        let engine = Engine::default();
+        let contents = std::fs::read(output_path)?;
+        let module = unsafe { Module::deserialize(&engine, contents)? };
+        assert!(module.get_export("init").is_none());
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[])?;
+        let f = instance.get_typed_func::<(), i32>(&mut store, "f")?;
+        assert_eq!(f.call(&mut store, ()).unwrap(), 42);
+
+        Ok(())
+    }
+
     #[cfg(target_arch = "x86_64")]
     #[test]
     fn test_x64_flags_compile() -> Result<()> {
@@ -11,7 +11,9 @@ use anyhow::{anyhow, bail, Context as _, Error, Result};
 use clap::Parser;
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+#[cfg(not(feature = "wasi-cloud-core"))]
+use std::sync::Mutex;
 use std::thread;
 use wasi_common::sync::{ambient_authority, Dir, TcpListener, WasiCtxBuilder};
 use wasmtime::{Engine, Func, Module, Store, StoreLimits, Val, ValType};
@@ -81,6 +83,11 @@ pub struct RunCommand {
     pub vars: Vec<(String, Option<String>)>,
 
     /// The name of the function to run
+    ///
+    /// For components this is a function call such as `add(1, 2)` with the
+    /// arguments written in the textual value syntax of
+    /// `wasmtime::component::text`. Functions exported from an instance are
+    /// named `interface#function`.
     #[arg(long, value_name = "FUNCTION")]
     pub invoke: Option<String>,
 
@@ -133,6 +140,17 @@ impl RunCommand {
         let engine = Engine::new(&config)?;
 
         // Read the wasm module binary either as `*.wat` or a raw binary.
+        #[cfg(all(feature = "wasi-cloud-core", feature = "component-model"))]
+        let (main, claims) =
+            self.run
+                .load_module_and(&engine, self.module_and_args[0].as_ref(), |bytes| {
+                    if self.run.common.wasi.cloud_core == Some(true) {
+                        self.run.cloud_core_claims(&engine, bytes)
+                    } else {
+                        Ok(None)
+                    }
+                })?;
+        #[cfg(not(all(feature = "wasi-cloud-core", feature = "component-model")))]
         let main = self
             .run
             .load_module(&engine, self.module_and_args[0].as_ref())?;
@@ -163,9 +181,22 @@ impl RunCommand {
             }
         }
 
-        let host = Host::default().await;
+        #[cfg(feature = "wasi-cloud-core")]
+        let host = {
+            let mut handler = self.run.cloud_core_handler().await?;
+            handler.set_component_id(self.module_and_args[0].to_string_lossy());
+            Host::new(handler)
+        };
+        #[cfg(not(feature = "wasi-cloud-core"))]
+        let host = Host::default();
         let mut store = Store::new(&engine, host);
-        self.populate_with_wasi(&mut linker, &mut store, &main)?;
+        self.populate_with_wasi(
+            &mut linker,
+            &mut store,
+            &main,
+            #[cfg(all(feature = "wasi-cloud-core", feature = "component-model"))]
+            claims,
+        )?;
 
         store.data_mut().limits = self.run.store_limits();
         store.limiter(|t| &mut t.limits);
@@ -410,28 +441,38 @@ impl RunCommand {
         // The main module might be allowed to have unknown imports, which
         // should be defined as traps:
         if self.run.common.wasm.unknown_imports_trap == Some(true) {
-            #[cfg(feature = "cranelift")]
             match linker {
+                #[cfg(feature = "cranelift")]
                 CliLinker::Core(linker) => {
                     linker.define_unknown_imports_as_traps(module.unwrap_core())?;
                 }
-                _ => bail!("cannot use `--trap-unknown-imports` with components"),
+                #[cfg(not(feature = "cranelift"))]
+                CliLinker::Core(_) => {
+                    bail!("support for `unknown-imports-trap` disabled at compile time");
+                }
+                #[cfg(feature = "component-model")]
+                CliLinker::Component(linker) => {
+                    linker.define_unknown_imports_as_traps(module.unwrap_component())?;
+                }
             }
-            #[cfg(not(feature = "cranelift"))]
-            bail!("support for `unknown-imports-trap` disabled at compile time");
         }
 
         // ...or as default values.
         if self.run.common.wasm.unknown_imports_default == Some(true) {
-            #[cfg(feature = "cranelift")]
             match linker {
+                #[cfg(feature = "cranelift")]
                 CliLinker::Core(linker) => {
                     linker.define_unknown_imports_as_default_values(module.unwrap_core())?;
                 }
-                _ => bail!("cannot use `--default-values-unknown-imports` with components"),
+                #[cfg(not(feature = "cranelift"))]
+                CliLinker::Core(_) => {
+                    bail!("support for `unknown-imports-trap` disabled at compile time");
+                }
+                #[cfg(feature = "component-model")]
+                CliLinker::Component(linker) => {
+                    linker.define_unknown_imports_as_default_values(module.unwrap_component())?;
+                }
             }
-            #[cfg(not(feature = "cranelift"))]
-            bail!("support for `unknown-imports-trap` disabled at compile time");
         }
 
         let finish_epoch_handler = self.setup_epoch_handler(store, modules)?;
@@ -471,29 +512,30 @@ impl RunCommand {
             }
             #[cfg(feature = "component-model")]
             CliLinker::Component(linker) => {
-                if self.invoke.is_some() {
-                    bail!("using `--invoke` with components is not supported");
-                }
-
                 let component = module.unwrap_component();
 
-                let (command, _instance) = wasmtime_wasi::command::Command::instantiate_async(
-                    &mut *store,
-                    component,
-                    linker,
-                ).await?;
-                let result = command
-                    .wasi_cli_run()
-                    .call_run(&mut *store).await
-                    .context("failed to invoke `run` function")
-                    .map_err(|e| self.handle_core_dump(&mut *store, e));
-
-                // Translate the `Result<(),()>` produced by wasm into a feigned
-                // explicit exit here with status 1 if `Err(())` is returned.
-                result.and_then(|wasm_result| match wasm_result {
-                    Ok(()) => Ok(()),
-                    Err(()) => Err(wasmtime_wasi::I32Exit(1).into()),
-                })
+                if let Some(invoke) = &self.invoke {
+                    self.invoke_component(store, component, linker, invoke)
+                        .await
+                } else {
+                    let (command, _instance) = wasmtime_wasi::command::Command::instantiate_async(
+                        &mut *store,
+                        component,
+                        linker,
+                    ).await?;
+                    let result = command
+                        .wasi_cli_run()
+                        .call_run(&mut *store).await
+                        .context("failed to invoke `run` function")
+                        .map_err(|e| self.handle_core_dump(&mut *store, e));
+
+                    // Translate the `Result<(),()>` produced by wasm into a feigned
+                    // explicit exit here with status 1 if `Err(())` is returned.
+                    result.and_then(|wasm_result| match wasm_result {
+                        Ok(()) => Ok(()),
+                        Err(()) => Err(wasmtime_wasi::I32Exit(1).into()),
+                    })
+                }
             }
         };
         finish_epoch_handler(store);
@@ -578,6 +620,59 @@ impl RunCommand {
         Ok(())
     }
 
+    #[cfg(feature = "component-model")]
+    async fn invoke_component(
+        &self,
+        store: &mut Store<Host>,
+        component: &wasmtime::component::Component,
+        linker: &wasmtime::component::Linker<Host>,
+        invoke: &str,
+    ) -> Result<()> {
+        use wasmtime::component::text;
+
+        let (name, args) = text::parse_call(invoke)?;
+        if self.module_and_args.len() > 1 {
+            bail!("arguments to `{name}` must be passed in the `--invoke` function call");
+        }
+        let instance = linker
+            .instantiate_async(&mut *store, component)
+            .await
+            .context(format!(
+                "failed to instantiate {:?}",
+                self.module_and_args[0]
+            ))?;
+        let func = match name.rsplit_once('#') {
+            Some((interface, func)) => instance
+                .exports(&mut *store)
+                .instance(interface)
+                .and_then(|mut instance| instance.func(func)),
+            None => instance.get_func(&mut *store, name),
+        }
+        .ok_or_else(|| anyhow!("no func export named `{}` found", name))?;
+
+        // No resources exist before the call, so arguments can't refer to any
+        // and handles only appear in the printed results.
+        let mut handles = text::Handles::default();
+        let params = text::parse_args(&func.params(&store), args, &handles)
+            .with_context(|| format!("invalid arguments for `{}`", name))?;
+        let mut results =
+            vec![wasmtime::component::Val::Bool(false); func.results(&store).len()];
+        let invoke_res = func
+            .call_async(&mut *store, &params, &mut results)
+            .await
+            .with_context(|| format!("failed to invoke `{}`", name));
+        if let Err(err) = invoke_res {
+            return Err(self.handle_core_dump(&mut *store, err));
+        }
+        func.post_return_async(&mut *store).await?;
+
+        for result in &results {
+            println!("{}", text::to_string(result, &mut handles));
+        }
+
+        Ok(())
+    }
+
     #[cfg(feature = "coredump")]
     fn handle_core_dump(&self, store: &mut Store<Host>, err: Error) -> Error {
         let coredump_path = match &self.run.common.debug.coredump {
@@ -610,6 +705,8 @@ impl RunCommand {
         linker: &mut CliLinker,
         store: &mut Store<Host>,
         module: &RunTarget,
+        #[cfg(all(feature = "wasi-cloud-core", feature = "component-model"))]
+        claims: Option<wasmtime_wasi_cloud_core::claims::Claims>,
     ) -> Result<()> {
         if self.run.common.wasi.common != Some(false) {
             match linker {
@@ -733,7 +830,14 @@ impl RunCommand {
                     }
                 }
 
-                store.data_mut().wasi_http = Some(Arc::new(WasiHttpCtx {}));
+                #[cfg(not(feature = "wasi-cloud-core"))]
+                {
+                    store.data_mut().wasi_http = Some(Arc::new(WasiHttpCtx {}));
+                }
+                #[cfg(feature = "wasi-cloud-core")]
+                {
+                    store.data_mut().wasi_http = Some(WasiHttpCtx {});
+                }
             }
         }
 
@@ -749,8 +853,21 @@ impl RunCommand {
                         bail!("Cannot enable wasi-cloud-core for core wasm modules");
                     }
                     CliLinker::Component(linker) => {
-                        wasmtime_wasi_cloud_core::capability::Interfaces::add_to_linker(linker, |ctx| ctx)
-                            .context("failed to link `wasmcloud:host/interfaces` interface")?;
+                        if let Some(claims) = claims {
+                            let interfaces = claims
+                                .interfaces()
+                                .context("invalid capability interface in component claims")?;
+                            wasmtime_wasi_cloud_core::capability::add_interfaces_to_linker(
+                                linker,
+                                |host| host,
+                                &interfaces,
+                            )
+                            .context("failed to link claimed `wasmcloud:host/interfaces` interfaces")?;
+                            store.data_mut().handler.set_component_id(claims.subject);
+                        } else {
+                            wasmtime_wasi_cloud_core::capability::Interfaces::add_to_linker(linker, |ctx| ctx)
+                                .context("failed to link `wasmcloud:host/interfaces` interface")?;
+                        }
                     }
                 }
             }
@@ -792,16 +909,16 @@ impl RunCommand {
     }
 
     fn set_preview2_ctx(&self, store: &mut Store<Host>) -> Result<()> {
-        let mut builder = wasmtime_wasi::WasiCtxBuilder::new();
-        builder.inherit_stdio().args(&self.compute_argv()?);
+        let argv = self.compute_argv()?;
 
+        let mut env = Vec::new();
         for (key, value) in self.vars.iter() {
             let value = match value {
                 Some(value) => value.clone(),
                 None => std::env::var(key)
                     .map_err(|_| anyhow!("environment variable `{key}` not found"))?,
             };
-            builder.env(key, &value);
+            env.push((key.clone(), value));
         }
 
         if self.run.common.wasi.listenfd == Some(true) {
@@ -811,30 +928,48 @@ impl RunCommand {
             bail!("components do not support --tcplisten");
         }
 
-        for (name, dir) in self.compute_preopen_dirs()? {
-            builder.preopened_dir(
-                dir,
-                wasmtime_wasi::DirPerms::all(),
-                wasmtime_wasi::FilePerms::all(),
-                name,
-            );
-        }
+        let dirs = self.compute_preopen_dirs()?;
+        let wasi = &self.run.common.wasi;
+        let (inherit_network, allow_ip_name_lookup, tcp, udp) =
+            (wasi.inherit_network, wasi.allow_ip_name_lookup, wasi.tcp, wasi.udp);
+
+        // Contexts are constructed once per store, and with wasi-cloud-core,
+        // once per `wasi-threads` thread as well.
+        let new_ctx = move || -> Result<wasmtime_wasi::WasiCtx> {
+            let mut builder = wasmtime_wasi::WasiCtxBuilder::new();
+            builder.inherit_stdio().args(&argv);
+            for (key, value) in &env {
+                builder.env(key, value);
+            }
+            for (name, dir) in &dirs {
+                builder.preopened_dir(
+                    dir.try_clone()?,
+                    wasmtime_wasi::DirPerms::all(),
+                    wasmtime_wasi::FilePerms::all(),
+                    name,
+                );
+            }
+            if inherit_network == Some(true) {
+                builder.inherit_network();
+            }
+            if let Some(enable) = allow_ip_name_lookup {
+                builder.allow_ip_name_lookup(enable);
+            }
+            if let Some(enable) = tcp {
+                builder.allow_tcp(enable);
+            }
+            if let Some(enable) = udp {
+                builder.allow_udp(enable);
+            }
+            Ok(builder.build())
+        };
 
-        if self.run.common.wasi.inherit_network == Some(true) {
-            builder.inherit_network();
-        }
-        if let Some(enable) = self.run.common.wasi.allow_ip_name_lookup {
-            builder.allow_ip_name_lookup(enable);
+        #[cfg(not(feature = "wasi-cloud-core"))]
+        {
+            store.data_mut().preview2_ctx = Some(Arc::new(Mutex::new(new_ctx()?)));
         }
-        if let Some(enable) = self.run.common.wasi.tcp {
-            builder.allow_tcp(enable);
-        }
-        if let Some(enable) = self.run.common.wasi.udp {
-            builder.allow_udp(enable);
-        }
-
-        let ctx = builder.build();
-        store.data_mut().preview2_ctx = Some(Arc::new(Mutex::new(ctx)));
+        #[cfg(feature = "wasi-cloud-core")]
+        store.data_mut().set_preview2_ctx(Arc::new(new_ctx))?;
         Ok(())
     }
 }
@@ -1,5 +1,5 @@
 use crate::common::{Profile, RunCommon, RunTarget};
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context as _, Result};
 use clap::Parser;
 use std::net::SocketAddr;
 use std::{
@@ -30,12 +30,34 @@ struct Host {
 
     #[cfg(feature = "wasi-nn")]
     nn: Option<WasiNnCtx>,
+
+    /// wasi-cloud-core host sharing the capability providers of all requests,
+    /// its resource table is used instead of `table` if set
+    #[cfg(feature = "wasi-cloud-core")]
+    cloud_core: Option<wasmtime_wasi_cloud_core::component::Host>,
 }
 
-impl WasiView for Host {
+impl Host {
     fn table(&mut self) -> &mut wasmtime::component::ResourceTable {
+        #[cfg(feature = "wasi-cloud-core")]
+        if let Some(cloud_core) = &mut self.cloud_core {
+            return &mut cloud_core.preview2_table;
+        }
         &mut self.table
     }
+}
+
+#[cfg(feature = "wasi-cloud-core")]
+fn cloud_core(host: &mut Host) -> &mut wasmtime_wasi_cloud_core::component::Host {
+    host.cloud_core
+        .as_mut()
+        .expect("wasi-cloud-core host is set for each request")
+}
+
+impl WasiView for Host {
+    fn table(&mut self) -> &mut wasmtime::component::ResourceTable {
+        Host::table(self)
+    }
 
     fn ctx(&mut self) -> &mut WasiCtx {
         &mut self.ctx
@@ -44,7 +66,7 @@ impl WasiView for Host {
 
 impl WasiHttpView for Host {
     fn table(&mut self) -> &mut wasmtime::component::ResourceTable {
-        &mut self.table
+        Host::table(self)
     }
 
     fn ctx(&mut self) -> &mut WasiHttpCtx {
@@ -101,6 +123,13 @@ impl ServeCommand {
             bail!("wasi-threads does not support components yet")
         }
 
+        if self.run.common.wasi.cloud_core == Some(true) {
+            #[cfg(not(feature = "wasi-cloud-core"))]
+            {
+                bail!("Cannot enable wasi-cloud-core when the binary is not compiled with this feature.");
+            }
+        }
+
         // The serve command requires both wasi-http and the component model, so we enable those by
         // default here.
         if self.run.common.wasi.http.replace(true) == Some(false) {
@@ -154,6 +183,9 @@ impl ServeCommand {
 
             #[cfg(feature = "wasi-nn")]
             nn: None,
+
+            #[cfg(feature = "wasi-cloud-core")]
+            cloud_core: None,
         };
 
         if self.run.common.wasi.nn == Some(true) {
@@ -256,11 +288,53 @@ impl ServeCommand {
 
         self.add_to_linker(&mut linker)?;
 
-        let component = match self.run.load_module(&engine, &self.component)? {
+        #[cfg(feature = "wasi-cloud-core")]
+        let (component, claims) = self
+            .run
+            .load_module_and(&engine, &self.component, |bytes| {
+                if self.run.common.wasi.cloud_core == Some(true) {
+                    self.run.cloud_core_claims(&engine, bytes)
+                } else {
+                    Ok(None)
+                }
+            })?;
+        #[cfg(not(feature = "wasi-cloud-core"))]
+        let component = self.run.load_module(&engine, &self.component)?;
+        let component = match component {
             RunTarget::Core(_) => bail!("The serve command currently requires a component"),
             RunTarget::Component(c) => c,
         };
 
+        #[cfg(feature = "wasi-cloud-core")]
+        let cloud_core = if self.run.common.wasi.cloud_core == Some(true) {
+            let mut component_id = self.component.to_string_lossy().into_owned();
+            if let Some(claims) = claims {
+                let interfaces = claims
+                    .interfaces()
+                    .context("invalid capability interface in component claims")?;
+                wasmtime_wasi_cloud_core::capability::add_interfaces_to_linker(
+                    &mut linker,
+                    cloud_core,
+                    &interfaces,
+                )
+                .context("failed to link claimed `wasmcloud:host/interfaces` interfaces")?;
+                component_id = claims.subject;
+            } else {
+                wasmtime_wasi_cloud_core::capability::Interfaces::add_to_linker(
+                    &mut linker,
+                    cloud_core,
+                )
+                .context("failed to link `wasmcloud:host/interfaces` interface")?;
+            }
+            // Providers are shared by all requests, so that handlers can
+            // persist data between requests
+            let mut handler = self.run.cloud_core_handler().await?;
+            handler.set_component_id(component_id);
+            Some(handler)
+        } else {
+            None
+        };
+
         let instance = linker.instantiate_pre(&component)?;
 
         // Tokio by default sets `SO_REUSEADDR` for listeners but that makes it
@@ -288,7 +362,13 @@ impl ServeCommand {
 
         log::info!("Listening on {}", self.addr);
 
-        let handler = ProxyHandler::new(self, engine, instance);
+        let handler = ProxyHandler::new(
+            self,
+            engine,
+            instance,
+            #[cfg(feature = "wasi-cloud-core")]
+            cloud_core,
+        );
 
         loop {
             let (stream, _) = listener.accept().await?;
@@ -353,6 +433,10 @@ struct ProxyHandlerInner {
     engine: Engine,
     instance_pre: InstancePre<Host>,
     next_id: AtomicU64,
+
+    /// wasi-cloud-core capability handler
+    #[cfg(feature = "wasi-cloud-core")]
+    cloud_core: Option<wasmtime_wasi_cloud_core::capability::Handler>,
 }
 
 impl ProxyHandlerInner {
@@ -365,12 +449,21 @@ impl ProxyHandlerInner {
 struct ProxyHandler(Arc<ProxyHandlerInner>);
 
 impl ProxyHandler {
-    fn new(cmd: ServeCommand, engine: Engine, instance_pre: InstancePre<Host>) -> Self {
+    fn new(
+        cmd: ServeCommand,
+        engine: Engine,
+        instance_pre: InstancePre<Host>,
+        #[cfg(feature = "wasi-cloud-core")] cloud_core: Option<
+            wasmtime_wasi_cloud_core::capability::Handler,
+        >,
+    ) -> Self {
         Self(Arc::new(ProxyHandlerInner {
             cmd,
             engine,
             instance_pre,
             next_id: AtomicU64::from(0),
+            #[cfg(feature = "wasi-cloud-core")]
+            cloud_core,
         }))
     }
 }
@@ -428,6 +521,13 @@ async fn handle_request(
 
         let mut store = inner.cmd.new_store(&inner.engine, req_id)?;
 
+        #[cfg(feature = "wasi-cloud-core")]
+        if let Some(handler) = &inner.cloud_core {
+            store.data_mut().cloud_core = Some(wasmtime_wasi_cloud_core::component::Host::new(
+                handler.clone(),
+            ));
+        }
+
         let req = store.data_mut().new_incoming_request(req)?;
         let out = store.data_mut().new_response_outparam(sender)?;
 
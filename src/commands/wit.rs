@@ -0,0 +1,105 @@
+//! The module that implements the `wasmtime wit` command.
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use std::path::PathBuf;
+use wasmtime::component::{wit, Component};
+use wasmtime::{Engine, Precompiled};
+use wasmtime_cli_flags::CommonOptions;
+
+/// Prints the WIT world of a WebAssembly component.
+#[derive(Parser, PartialEq)]
+pub struct WitCommand {
+    #[command(flatten)]
+    common: CommonOptions,
+
+    /// Allow reading precompiled components as `*.cwasm` files.
+    ///
+    /// Note that this option is not safe to pass if the component being passed
+    /// in is arbitrary user input. Only `wasmtime`-precompiled components
+    /// generated via the `wasmtime compile` command or equivalent should be
+    /// passed as an argument with this option specified.
+    #[arg(long = "allow-precompiled")]
+    allow_precompiled: bool,
+
+    /// Write the world to `DIR/world.wit` and the packages of the interfaces
+    /// it uses to `DIR/deps`, instead of printing them.
+    ///
+    /// The directory can then be parsed as a WIT package by WIT tooling.
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+
+    /// The path of the WebAssembly component to print
+    #[arg(required = true, value_name = "COMPONENT")]
+    component: PathBuf,
+}
+
+impl WitCommand {
+    /// Executes the command.
+    pub fn execute(mut self) -> Result<()> {
+        self.common.init_logging()?;
+
+        let engine = Engine::new(&self.common.config(None)?)?;
+        let bytes = std::fs::read(&self.component)
+            .with_context(|| format!("failed to read file: {}", self.component.display()))?;
+
+        let component = match engine.detect_precompiled(&bytes) {
+            Some(Precompiled::Component) => {
+                if !self.allow_precompiled {
+                    bail!(
+                        "reading a precompiled component requires the `--allow-precompiled` flag"
+                    );
+                }
+                unsafe { Component::deserialize(&engine, &bytes)? }
+            }
+            Some(Precompiled::Module) => {
+                bail!(
+                    "`{}` is a core module, not a component",
+                    self.component.display()
+                );
+            }
+            #[cfg(any(feature = "cranelift", feature = "winch"))]
+            None => {
+                #[cfg(feature = "wat")]
+                let bytes = wat::parse_bytes(&bytes).map_err(|mut e| {
+                    e.set_path(&self.component);
+                    e
+                })?;
+                if !wasmparser::Parser::is_component(&bytes) {
+                    bail!(
+                        "`{}` is a core module, not a component",
+                        self.component.display()
+                    );
+                }
+                Component::new(&engine, &bytes)?
+            }
+            #[cfg(not(any(feature = "cranelift", feature = "winch")))]
+            None => bail!("support for compiling components was disabled at compile time"),
+        };
+
+        let ty = component.component_type();
+        let world = wit::print_world(&engine, &ty);
+        let packages = wit::print_packages(&engine, &ty);
+        let Some(dir) = &self.out_dir else {
+            // Each package is a separate WIT document.
+            print!("{world}");
+            for wit in packages.values() {
+                print!("\n{wit}");
+            }
+            return Ok(());
+        };
+
+        let deps = dir.join("deps");
+        std::fs::create_dir_all(&deps)
+            .with_context(|| format!("failed to create directory: {}", deps.display()))?;
+        let files = packages
+            .iter()
+            .map(|(name, wit)| (deps.join(format!("{}.wit", name.replace(':', "-"))), wit))
+            .chain([(dir.join("world.wit"), &world)]);
+        for (path, wit) in files {
+            std::fs::write(&path, wit)
+                .with_context(|| format!("failed to write file: {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
@@ -114,7 +114,87 @@ impl RunCommon {
         Ok(())
     }
 
+    /// Construct the wasi-cloud-core capability handler using the providers
+    /// selected by `-S cloud-core-config` and serve its metrics if
+    /// `-S cloud-core-metrics-addr` is set
+    #[cfg(feature = "wasi-cloud-core")]
+    pub async fn cloud_core_handler(
+        &self,
+    ) -> Result<wasmtime_wasi_cloud_core::capability::Handler> {
+        let mut handler = match &self.common.wasi.cloud_core_config {
+            Some(path) => wasmtime_wasi_cloud_core::config::Config::from_file(path)?
+                .handler()
+                .await
+                .context("failed to construct wasi-cloud-core providers")?,
+            None => {
+                wasmtime_wasi_cloud_core::component::Host::default()
+                    .await
+                    .handler
+            }
+        };
+        if let Some(addr) = &self.common.wasi.cloud_core_metrics_addr {
+            let metrics = std::sync::Arc::new(
+                wasmtime_wasi_cloud_core::capability::PrometheusMetrics::default(),
+            );
+            metrics
+                .spawn_server(addr.as_str())
+                .await
+                .with_context(|| format!("failed to serve wasi-cloud-core metrics on `{addr}`"))?;
+            handler.replace_metrics(metrics);
+        }
+        Ok(handler)
+    }
+
+    /// Verify the signed capability claims embedded in the component `bytes`
+    /// according to `-S cloud-core-strict-claims` and
+    /// `-S cloud-core-trusted-issuer`
+    ///
+    /// Claims can only be embedded in binary components, so precompiled and
+    /// text format components are treated as unsigned, which is an error if
+    /// claims are strictly enforced.
+    #[cfg(feature = "wasi-cloud-core")]
+    pub fn cloud_core_claims(
+        &self,
+        engine: &Engine,
+        bytes: &[u8],
+    ) -> Result<Option<wasmtime_wasi_cloud_core::claims::Claims>> {
+        let policy = wasmtime_wasi_cloud_core::claims::Policy {
+            strict: self.common.wasi.cloud_core_strict_claims == Some(true),
+            trusted_issuers: self.common.wasi.cloud_core_trusted_issuer.clone(),
+        };
+        if policy.strict && policy.trusted_issuers.is_empty() {
+            bail!("`-S cloud-core-strict-claims` requires at least one `-S cloud-core-trusted-issuer`");
+        }
+        if engine.detect_precompiled(bytes).is_some() {
+            if policy.strict {
+                bail!("cannot verify the claims of a precompiled component, sign and run the component binary instead");
+            }
+            return Ok(None);
+        }
+        if !bytes.starts_with(b"\0asm") {
+            if policy.strict {
+                bail!("cannot verify the claims of a component in the text format, sign and run the component binary instead");
+            }
+            return Ok(None);
+        }
+        wasmtime_wasi_cloud_core::claims::verify(bytes, &policy)
+            .context("failed to verify component claims")
+    }
+
     pub fn load_module(&self, engine: &Engine, path: &Path) -> Result<RunTarget> {
+        let (target, ()) = self.load_module_and(engine, path, |_| Ok(()))?;
+        Ok(target)
+    }
+
+    /// Like [`RunCommon::load_module`], but additionally passes the contents
+    /// of the file to `inspect`, for example to verify metadata embedded in
+    /// them without reading the file again
+    pub fn load_module_and<T>(
+        &self,
+        engine: &Engine,
+        path: &Path,
+        inspect: impl FnOnce(&[u8]) -> Result<T>,
+    ) -> Result<(RunTarget, T)> {
         let path = match path.to_str() {
             #[cfg(unix)]
             Some("-") => "/dev/stdin".as_ref(),
@@ -140,25 +220,29 @@ impl RunCommon {
         // happen at this time). It's hoped though that opening a file twice
         // isn't too bad in the grand scheme of things with respect to the CLI.
         match wasmtime_runtime::MmapVec::from_file(path) {
-            Ok(map) => self.load_module_contents(
-                engine,
-                path,
-                &map,
-                || unsafe { Module::deserialize_file(engine, path) },
-                #[cfg(feature = "component-model")]
-                || unsafe { Component::deserialize_file(engine, path) },
-            ),
+            Ok(map) => {
+                let target = self.load_module_contents(
+                    engine,
+                    path,
+                    &map,
+                    || unsafe { Module::deserialize_file(engine, path) },
+                    #[cfg(feature = "component-model")]
+                    || unsafe { Component::deserialize_file(engine, path) },
+                )?;
+                Ok((target, inspect(&map)?))
+            }
             Err(_) => {
                 let bytes = std::fs::read(path)
                     .with_context(|| format!("failed to read file: {}", path.display()))?;
-                self.load_module_contents(
+                let target = self.load_module_contents(
                     engine,
                     path,
                     &bytes,
                     || unsafe { Module::deserialize(engine, &bytes) },
                     #[cfg(feature = "component-model")]
                     || unsafe { Component::deserialize(engine, &bytes) },
-                )
+                )?;
+                Ok((target, inspect(&bytes)?))
             }
         }
     }
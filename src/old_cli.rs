@@ -235,7 +235,7 @@ fn parse_module(s: OsString) -> anyhow::Result<PathBuf> {
     // Do not accept wasmtime subcommand names as the module name
     match s.to_str() {
         Some("help") | Some("run") | Some("compile") | Some("serve") | Some("explore")
-        | Some("settings") | Some("wast") | Some("config") => {
+        | Some("settings") | Some("wast") | Some("config") | Some("wit") => {
             bail!("module name cannot be the same as a subcommand")
         }
         _ => Ok(s.into()),
@@ -706,6 +706,7 @@ impl CompileCommand {
             target,
             output,
             emit_clif,
+            init_func: None,
             module,
         }
     }
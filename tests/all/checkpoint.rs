@@ -0,0 +1,268 @@
+use anyhow::Result;
+use wasmtime::*;
+
+const WAT: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (data (i32.const 100) "hello")
+
+        (global $counter (export "counter") (mut i64) (i64.const 0))
+        (global $callback (export "callback") (mut funcref) (ref.null func))
+        (global $seed i32 (i32.const 7))
+
+        (table $t 2 funcref)
+        (elem (i32.const 0) $one $two)
+
+        (func $one (result i32) i32.const 1)
+        (func $two (result i32) i32.const 2)
+
+        ;; Mutates every kind of state: grows memory and writes to the new
+        ;; page, bumps the counter, and swaps the table entries while
+        ;; remembering one of them in a global.
+        (func (export "mutate")
+            (drop (memory.grow (i32.const 1)))
+            (i32.store (i32.const 70000) (i32.const 42))
+            (i32.store8 (i32.const 100) (i32.const 0x4a))
+            (global.set $counter (i64.add (global.get $counter) (i64.const 1)))
+            (global.set $callback (table.get $t (i32.const 1)))
+            (table.set $t (i32.const 1) (table.get $t (i32.const 0)))
+            (table.set $t (i32.const 0) (global.get $callback))
+            (drop (table.grow $t (ref.null func) (i32.const 1)))
+        )
+
+        (func (export "call") (param i32) (result i32)
+            (call_indirect (result i32) (local.get 0))
+        )
+    )
+"#;
+
+fn configs() -> Vec<Config> {
+    let mut configs = Vec::new();
+    configs.push(Config::new());
+
+    let mut config = Config::new();
+    config.memory_init_cow(false);
+    configs.push(config);
+
+    let mut pool = crate::small_pool_config();
+    pool.memory_pages(2).total_memories(2).total_tables(2);
+    let mut config = Config::new();
+    config.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
+    configs.push(config);
+
+    let mut config = Config::new();
+    config.static_memory_maximum_size(0);
+    configs.push(config);
+
+    configs
+}
+
+fn assert_mutated(store: &mut Store<()>, instance: &Instance) -> Result<()> {
+    let memory = instance.get_memory(&mut *store, "memory").unwrap();
+    assert_eq!(memory.size(&*store), 2);
+    assert_eq!(&memory.data(&*store)[100..105], b"Jello");
+    assert_eq!(memory.data(&*store)[70000], 42);
+
+    let counter = instance.get_global(&mut *store, "counter").unwrap();
+    assert_eq!(counter.get(&mut *store).i64(), Some(1));
+
+    let call = instance.get_typed_func::<u32, u32>(&mut *store, "call")?;
+    assert_eq!(call.call(&mut *store, 0)?, 2);
+    assert_eq!(call.call(&mut *store, 1)?, 1);
+    assert!(call.call(&mut *store, 2).is_err());
+    assert!(call.call(&mut *store, 3).is_err());
+
+    let callback = instance.get_global(&mut *store, "callback").unwrap();
+    let callback = callback.get(&mut *store).unwrap_funcref().copied().unwrap();
+    assert_eq!(
+        callback.typed::<(), u32>(&*store)?.call(&mut *store, ())?,
+        2
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn restore_into_new_store() -> Result<()> {
+    for config in configs() {
+        let engine = Engine::new(&config)?;
+        let module = Module::new(&engine, WAT)?;
+
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[])?;
+        instance
+            .get_typed_func::<(), ()>(&mut store, "mutate")?
+            .call(&mut store, ())?;
+        let checkpoint = store.checkpoint()?;
+        let checkpoint = Checkpoint::deserialize(&checkpoint.serialize()?)?;
+
+        // Restoring the same checkpoint multiple times, into multiple stores,
+        // yields the same state each time.
+        for _ in 0..3 {
+            let mut store = Store::new(&engine, ());
+            let instance = Instance::new(&mut store, &module, &[])?;
+            store.restore(&checkpoint)?;
+            assert_mutated(&mut store, &instance)?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn restore_discards_later_changes() -> Result<()> {
+    for config in configs() {
+        let engine = Engine::new(&config)?;
+        let module = Module::new(&engine, WAT)?;
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[])?;
+        let checkpoint = store.checkpoint()?;
+
+        instance
+            .get_typed_func::<(), ()>(&mut store, "mutate")?
+            .call(&mut store, ())?;
+        store.restore(&checkpoint).unwrap_err();
+
+        // Memory can't shrink, so restoring a checkpoint taken before the
+        // memory grew is an error, but restoring one taken afterwards
+        // replaces everything written since.
+        let checkpoint = store.checkpoint()?;
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        memory.data_mut(&mut store)[100..105].copy_from_slice(b"world");
+        memory.data_mut(&mut store)[200] = 1;
+        memory.data_mut(&mut store)[70000] = 0;
+        instance
+            .get_global(&mut store, "counter")
+            .unwrap()
+            .set(&mut store, Val::I64(10))?;
+        store.restore(&checkpoint)?;
+        assert_eq!(memory.data(&store)[200], 0);
+        assert_mutated(&mut store, &instance)?;
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn restore_multiple_instances() -> Result<()> {
+    let engine = Engine::default();
+    let exporter = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "f") (result i32) i32.const 100)
+            )
+        "#,
+    )?;
+    let importer = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "" "memory" (memory 1))
+                (import "" "f" (func $f (result i32)))
+                (table (export "table") 1 funcref)
+                (func (export "store") (i32.store (i32.const 0) (i32.const 5)))
+                (func (export "set") (table.set (i32.const 0) (ref.func $f)))
+                (elem declare func $f)
+            )
+        "#,
+    )?;
+    let instantiate = |store: &mut Store<()>| -> Result<(Memory, Instance)> {
+        let exporter = Instance::new(&mut *store, &exporter, &[])?;
+        let memory = exporter.get_memory(&mut *store, "memory").unwrap();
+        let f = exporter.get_func(&mut *store, "f").unwrap();
+        let instance = Instance::new(&mut *store, &importer, &[memory.into(), f.into()])?;
+        Ok((memory, instance))
+    };
+
+    let mut store = Store::new(&engine, ());
+    let (_, instance) = instantiate(&mut store)?;
+    for name in ["store", "set"] {
+        instance
+            .get_typed_func::<(), ()>(&mut store, name)?
+            .call(&mut store, ())?;
+    }
+    let checkpoint = store.checkpoint()?;
+
+    let mut store = Store::new(&engine, ());
+    let (memory, instance) = instantiate(&mut store)?;
+    store.restore(&checkpoint)?;
+    assert_eq!(memory.data(&store)[0], 5);
+    let table = instance.get_table(&mut store, "table").unwrap();
+    let f = table.get(&mut store, 0).unwrap();
+    let f = f.unwrap_func().unwrap().typed::<(), i32>(&store)?;
+    assert_eq!(f.call(&mut store, ())?, 100);
+
+    // Restoring into a store with different instances is an error.
+    let mut store = Store::new(&engine, ());
+    Instance::new(&mut store, &exporter, &[])?;
+    let err = store.restore(&checkpoint).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "checkpoint contains 2 instances but the store contains 1"
+    );
+    Instance::new(&mut store, &exporter, &[])?;
+    let err = store.restore(&checkpoint).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "instance 1 in the store was not created from the same module as in the checkpoint"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn restore_validates_before_writing() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (global (export "g") (mut i32) (i32.const 0))
+                (table (export "table") 1 2 funcref)
+            )
+        "#,
+    )?;
+    let mut store = Store::new(&engine, ());
+    let first = Instance::new(&mut store, &module, &[])?;
+    let second = Instance::new(&mut store, &module, &[])?;
+    let checkpoint = store.checkpoint()?;
+
+    // The second instance's table can't shrink back, which is detected
+    // before the first instance is restored.
+    let g = first.get_global(&mut store, "g").unwrap();
+    g.set(&mut store, Val::I32(5))?;
+    let table = second.get_table(&mut store, "table").unwrap();
+    table.grow(&mut store, 1, Ref::Func(None))?;
+    let err = store.restore(&checkpoint).unwrap_err();
+    assert_eq!(
+        format!("{err:#}"),
+        "cannot restore table 0 of instance 1: \
+         cannot restore 1 elements into a table of 2 elements"
+    );
+    assert_eq!(g.get(&mut store).i32(), Some(5));
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn unsupported_state() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, r#"(module (table (export "table") 1 funcref))"#)?;
+    let mut store = Store::new(&engine, ());
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let table = instance.get_table(&mut store, "table").unwrap();
+    let host = Func::wrap(&mut store, || {});
+    table.set(&mut store, 0, Ref::Func(Some(host)))?;
+    let err = store.checkpoint().unwrap_err();
+    assert_eq!(
+        format!("{err:#}"),
+        "cannot checkpoint table 0 of instance 0: \
+         references to host-defined functions are not supported"
+    );
+
+    assert!(Checkpoint::deserialize(&[]).is_err());
+    assert!(Checkpoint::deserialize(&[1, 2, 3]).is_err());
+    Ok(())
+}
@@ -865,6 +865,35 @@ fn run_precompiled_component() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg_attr(not(feature = "component-model"), ignore)]
+fn invoke_component() -> Result<()> {
+    let path = "tests/all/cli_tests/component-invoke.wat";
+    let stdout = run_wasmtime(&[
+        "run",
+        "-Ccache=n",
+        "--invoke",
+        "local:demo/math#add(1, -3)",
+        path,
+    ])?;
+    assert_eq!(stdout, "-2\n");
+    let stdout = run_wasmtime(&["run", "-Ccache=n", "--invoke", "parity-of(7)", path])?;
+    assert_eq!(stdout, "odd\n");
+
+    // Arguments must be passed in the function call
+    let output = get_wasmtime_command()?
+        .args(&["run", "-Ccache=n", "--invoke", "parity-of", path, "7"])
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("must be passed in the `--invoke` function call"),
+        "bad stderr: {stderr}"
+    );
+
+    Ok(())
+}
+
 #[test]
 fn memory_growth_failure() -> Result<()> {
     let output = get_wasmtime_command()?
@@ -1252,7 +1281,8 @@ fn mpk_without_pooling() -> Result<()> {
 mod test_programs {
     use super::{get_wasmtime_command, run_wasmtime};
     use anyhow::Result;
-    use std::io::{Read, Write};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
     use std::process::Stdio;
     use test_programs_artifacts::*;
 
@@ -1587,4 +1617,51 @@ mod test_programs {
         assert!(output.status.success());
         Ok(())
     }
+
+    #[test]
+    fn api_proxy_keyvalue_counter() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("cloud-core.toml");
+        std::fs::write(
+            &config,
+            "[keyvalue]\nprovider = \"memory\"\nbuckets = { counters = {} }\n",
+        )?;
+        let mut child = get_wasmtime_command()?
+            .arg("serve")
+            .arg("-Scloud-core")
+            .arg(format!("-Scloud-core-config={}", config.display()))
+            .arg("--addr=127.0.0.1:0")
+            .arg(API_PROXY_KEYVALUE_COUNTER_COMPONENT)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut stderr = BufReader::new(child.stderr.take().unwrap());
+        let mut line = String::new();
+        stderr.read_line(&mut line)?;
+        let addr = line
+            .trim()
+            .strip_prefix("Serving HTTP on http://")
+            .and_then(|addr| addr.strip_suffix('/'))
+            .unwrap_or_else(|| panic!("unexpected output: {line}"))
+            .to_string();
+
+        // The keyvalue provider is shared between requests, so the counter
+        // keeps counting
+        let result = ["1", "2"]
+            .into_iter()
+            .try_for_each(|expected| -> Result<()> {
+                let mut stream = TcpStream::connect(&addr)?;
+                stream.write_all(b"GET / HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+                let mut response = String::new();
+                stream.read_to_string(&mut response)?;
+                let (head, body) = response.split_once("\r\n\r\n").unwrap();
+                assert!(head.starts_with("HTTP/1.0 200"), "{response}");
+                assert_eq!(body, expected);
+                Ok(())
+            });
+        child.kill()?;
+        child.wait()?;
+        result
+    }
 }
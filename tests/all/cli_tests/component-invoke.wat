@@ -0,0 +1,19 @@
+(component
+  (core module $m
+    (func (export "add") (param i32 i32) (result i32)
+      (i32.add (local.get 0) (local.get 1)))
+    (func (export "parity") (param i32) (result i32)
+      (i32.and (local.get 0) (i32.const 1)))
+  )
+  (core instance $i (instantiate $m))
+
+  (func $add (param "a" s32) (param "b" s32) (result s32)
+    (canon lift (core func $i "add")))
+  (instance (export "local:demo/math")
+    (export "add" (func $add)))
+
+  (type $parity (enum "even" "odd"))
+  (export $parity' "parity" (type $parity))
+  (func (export "parity-of") (param "n" u32) (result $parity')
+    (canon lift (core func $i "parity")))
+)
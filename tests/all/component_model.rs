@@ -12,11 +12,14 @@ mod dynamic;
 mod func;
 mod import;
 mod instance;
+mod json;
 mod macros;
 mod nested;
 mod post_return;
 mod resources;
 mod strings;
+mod text;
+mod wit;
 
 #[test]
 #[cfg_attr(miri, ignore)]
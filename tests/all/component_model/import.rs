@@ -985,3 +985,155 @@ fn use_types_across_component_boundaries() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn trapping_unknown_imports() -> Result<()> {
+    let engine = super::engine();
+    let component = Component::new(
+        &engine,
+        r#"(component
+            (import "host" (instance $host
+                (export "known" (func (result u32)))
+                (export "nested" (instance
+                    (export "r" (type (sub resource)))
+                    (export "f" (func))
+                ))
+            ))
+            (import "unused" (func))
+
+            (alias export $host "nested" (instance $nested))
+            (core func $known (canon lower (func $host "known")))
+            (core func $f (canon lower (func $nested "f")))
+            (core module $m
+                (import "" "known" (func $known (result i32)))
+                (import "" "f" (func $f))
+                (func (export "known") (result i32) call $known)
+                (func (export "f") call $f)
+            )
+            (core instance $i (instantiate $m
+                (with "" (instance
+                    (export "known" (func $known))
+                    (export "f" (func $f))
+                ))
+            ))
+            (func (export "known") (result u32) (canon lift (core func $i "known")))
+            (func (export "f") (canon lift (core func $i "f")))
+        )"#,
+    )?;
+
+    let mut store = Store::new(&engine, ());
+    let mut linker = Linker::new(&engine);
+    linker
+        .instance("host")?
+        .func_wrap("known", |_, ()| Ok((7u32,)))?;
+    linker.define_unknown_imports_as_traps(&component)?;
+    let instance = linker.instantiate(&mut store, &component)?;
+
+    // Definitions of the linker are kept.
+    let known = instance.get_typed_func::<(), (u32,)>(&mut store, "known")?;
+    assert_eq!(known.call(&mut store, ())?, (7,));
+    known.post_return(&mut store)?;
+
+    let f = instance.get_typed_func::<(), ()>(&mut store, "f")?;
+    let err = f.call(&mut store, ()).unwrap_err();
+    assert!(
+        format!("{err:?}").contains("unknown import: `host#nested#f` has not been defined"),
+        "bad error: {err:?}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn default_value_unknown_imports() -> Result<()> {
+    let engine = super::engine();
+    let component = Component::new(
+        &engine,
+        format!(
+            r#"(component
+            (type $r (record
+                (field "a" u32)
+                (field "b" string)
+                (field "c" (option u8))
+                (field "d" (list u8))
+                (field "e" (result (error string)))
+            ))
+            (import "r" (type $r' (eq $r)))
+            (import "f" (func $f (result $r')))
+
+            (core module $libc
+                (memory (export "memory") 1)
+                {REALLOC_AND_FREE}
+            )
+            (core instance $libc (instantiate $libc))
+            (core func $f (canon lower (func $f)
+                (memory $libc "memory")
+                (realloc (func $libc "realloc"))
+            ))
+            (core module $m
+                (import "" "f" (func $f (param i32)))
+                (func (export "run") (result i32)
+                    i32.const 8
+                    call $f
+                    i32.const 8)
+            )
+            (core instance $i (instantiate $m
+                (with "" (instance (export "f" (func $f))))
+            ))
+            (func (export "run") (result $r')
+                (canon lift (core func $i "run") (memory $libc "memory"))
+            )
+        )"#
+        ),
+    )?;
+
+    let mut store = Store::new(&engine, ());
+    let mut linker = Linker::new(&engine);
+    linker.define_unknown_imports_as_default_values(&component)?;
+    let instance = linker.instantiate(&mut store, &component)?;
+
+    let run = instance.get_func(&mut store, "run").unwrap();
+    let mut results = [Val::Bool(false)];
+    run.call(&mut store, &[], &mut results)?;
+    let Val::Record(record) = &results[0] else {
+        panic!("expected a record, found {:?}", results[0]);
+    };
+    let fields = record.fields().collect::<Vec<_>>();
+    assert_eq!(fields[0], ("a", &Val::U32(0)));
+    assert_eq!(fields[1], ("b", &Val::String("".into())));
+    assert!(matches!(fields[2].1, Val::Option(o) if o.value().is_none()));
+    assert!(matches!(fields[3].1, Val::List(l) if l.is_empty()));
+    assert!(matches!(fields[4].1, Val::Result(r) if matches!(r.value(), Ok(None))));
+
+    Ok(())
+}
+
+#[test]
+fn unknown_resource_imports() -> Result<()> {
+    let engine = super::engine();
+    let component = Component::new(
+        &engine,
+        r#"(component
+            (import "r" (type $r (sub resource)))
+            (import "f" (func (result (own $r))))
+        )"#,
+    )?;
+
+    // Resources have no default value...
+    let mut linker = Linker::<()>::new(&engine);
+    let err = linker
+        .define_unknown_imports_as_default_values(&component)
+        .unwrap_err();
+    assert!(
+        format!("{err:#}").contains("no default value exists for resources"),
+        "bad error: {err:#}"
+    );
+
+    // ...but functions returning them can trap.
+    let mut store = Store::new(&engine, ());
+    let mut linker = Linker::new(&engine);
+    linker.define_unknown_imports_as_traps(&component)?;
+    linker.instantiate(&mut store, &component)?;
+
+    Ok(())
+}
@@ -0,0 +1,255 @@
+#![cfg(not(miri))]
+
+use super::{make_echo_component, make_echo_component_with_params, Param, Type};
+use anyhow::Result;
+use component_test_util::FuncExt;
+use serde_json::json;
+use wasmtime::component::json::{from_json, from_json_args, to_json, to_json_args};
+use wasmtime::component::{Component, Func, Linker, Val};
+use wasmtime::Store;
+
+const RECORD: &str = r#"
+    (type $shape' (variant
+        (case "circle" u32)
+        (case "square" (tuple u32 u32))
+        (case "empty")
+    ))
+    (export $shape "shape" (type $shape'))
+    (type $mode' (enum "ok" "broken"))
+    (export $mode "mode" (type $mode'))
+    (type $perms' (flags "read" "write"))
+    (export $perms "perms" (type $perms'))
+    (type $Foo' (record
+        (field "name" string)
+        (field "tags" (list string))
+        (field "size" (option u32))
+        (field "shape" $shape)
+        (field "mode" $mode)
+        (field "res" (result u8 (error string)))
+        (field "perms" $perms)
+    ))
+"#;
+
+fn echo_func(store: &mut Store<()>, component: &str) -> Result<Func> {
+    let component = Component::new(store.engine(), component)?;
+    let instance = Linker::new(store.engine()).instantiate(&mut *store, &component)?;
+    Ok(instance.get_func(&mut *store, "echo").unwrap())
+}
+
+/// Converts `input` to the parameter of the echo component `component`,
+/// calls it and converts the result back to JSON.
+fn echo(component: &str, input: serde_json::Value) -> Result<serde_json::Value> {
+    let mut store = Store::new(&super::engine(), ());
+    let func = echo_func(&mut store, component)?;
+    let input = from_json(&func.params(&store)[0], &input)?;
+    let mut output = [Val::Bool(false)];
+    func.call_and_post_return(&mut store, &[input], &mut output)?;
+    to_json(&output[0])
+}
+
+#[test]
+fn primitives() -> Result<()> {
+    for (input, ty, param) in [
+        (json!(true), "bool", Param(Type::U8, Some(0))),
+        (json!(-42), "s8", Param(Type::S8, Some(0))),
+        (json!(4242), "u16", Param(Type::U16, Some(0))),
+        (json!(-314159265), "s32", Param(Type::I32, Some(0))),
+        (json!(u64::MAX), "u64", Param(Type::I64, Some(0))),
+        (json!(0.1), "float32", Param(Type::F32, Some(0))),
+        (json!(-2.5e100), "float64", Param(Type::F64, Some(0))),
+        (json!("-inf"), "float64", Param(Type::F64, Some(0))),
+        (json!("nan"), "float32", Param(Type::F32, Some(0))),
+        (json!("🦀"), "char", Param(Type::I32, Some(0))),
+    ] {
+        let component = make_echo_component_with_params(ty, &[param]);
+        assert_eq!(echo(&component, input.clone())?, input);
+    }
+
+    let component = make_echo_component("string", 8);
+    assert_eq!(echo(&component, json!("héllo\n"))?, json!("héllo\n"));
+
+    Ok(())
+}
+
+#[test]
+fn compound() -> Result<()> {
+    let component = make_echo_component(RECORD, 56);
+
+    for input in [
+        json!({
+            "name": "x",
+            "tags": [],
+            "size": null,
+            "shape": "empty",
+            "mode": "ok",
+            "res": {"ok": 7},
+            "perms": [],
+        }),
+        json!({
+            "name": "café",
+            "tags": ["a", "b"],
+            "size": 42,
+            "shape": {"square": [1, 2]},
+            "mode": "broken",
+            "res": {"err": "nope"},
+            "perms": ["read", "write"],
+        }),
+    ] {
+        assert_eq!(echo(&component, input.clone())?, input);
+    }
+
+    // Omitted `option` fields are `none`.
+    let input = json!({
+        "name": "n",
+        "tags": ["t"],
+        "shape": {"circle": 3},
+        "mode": "ok",
+        "res": {"ok": 0},
+        "perms": ["write"],
+    });
+    let mut expected = input.clone();
+    expected["size"] = json!(null);
+    assert_eq!(echo(&component, input)?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn nested_options() -> Result<()> {
+    let component = make_echo_component_with_params(
+        "(option (option u32))",
+        &[
+            Param(Type::U8, Some(0)),
+            Param(Type::U8, Some(4)),
+            Param(Type::I32, Some(8)),
+        ],
+    );
+    for input in [json!(null), json!([null]), json!([7])] {
+        assert_eq!(echo(&component, input.clone())?, input);
+    }
+
+    let mut store = Store::new(&super::engine(), ());
+    let func = echo_func(&mut store, &component)?;
+    let err = from_json(&func.params(&store)[0], &json!(7)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected null or a single element array, found a number at `$`"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> Result<()> {
+    let mut store = Store::new(&super::engine(), ());
+    let func = echo_func(&mut store, &make_echo_component(RECORD, 56))?;
+    let ty = &func.params(&store)[0];
+    let valid = json!({
+        "name": "x",
+        "tags": [],
+        "size": null,
+        "shape": "empty",
+        "mode": "ok",
+        "res": {"ok": 1},
+        "perms": [],
+    });
+
+    for (pointer, value, expected) in [
+        (
+            "/name",
+            json!(1),
+            "expected a string, found a number at `$.name`",
+        ),
+        (
+            "/tags",
+            json!(["a", 1]),
+            "expected a string, found a number at `$.tags[1]`",
+        ),
+        ("/size", json!(-1), "invalid u32 `-1` at `$.size`"),
+        ("/size", json!(1.5), "invalid u32 `1.5` at `$.size`"),
+        (
+            "/shape",
+            json!("circle"),
+            "`circle` requires a payload at `$.shape`",
+        ),
+        (
+            "/shape",
+            json!({"empty": 1}),
+            "expected null, found a number at `$.shape.empty`",
+        ),
+        (
+            "/shape",
+            json!({"square": [1]}),
+            "expected 2 elements, found 1 at `$.shape.square`",
+        ),
+        (
+            "/shape",
+            json!({"round": 1}),
+            "unknown variant case `round` at `$.shape`",
+        ),
+        (
+            "/mode",
+            json!("fixed"),
+            "unknown enum case `fixed` at `$.mode`",
+        ),
+        (
+            "/res",
+            json!({"ok": 1, "err": ""}),
+            "expected an object with a single entry at `$.res`",
+        ),
+        (
+            "/res",
+            json!({"maybe": 1}),
+            "expected `ok` or `err` at `$.res`",
+        ),
+        ("/res", json!({"ok": 256}), "invalid u8 `256` at `$.res.ok`"),
+        (
+            "/perms",
+            json!(["read", "exec"]),
+            "unknown flag `exec` at `$.perms[1]`",
+        ),
+    ] {
+        let mut input = valid.clone();
+        *input.pointer_mut(pointer).unwrap() = value;
+        let err = from_json(ty, &input).unwrap_err();
+        assert_eq!(err.to_string(), expected, "for {input}");
+    }
+
+    let mut input = valid.clone();
+    input.as_object_mut().unwrap().remove("name");
+    let err = from_json(ty, &input).unwrap_err();
+    assert_eq!(err.to_string(), "missing field `name` at `$`");
+
+    let mut input = valid;
+    input["nam"] = json!("x");
+    let err = from_json(ty, &input).unwrap_err();
+    assert_eq!(err.to_string(), "unknown field `nam` at `$`");
+
+    Ok(())
+}
+
+#[test]
+fn args() -> Result<()> {
+    let mut store = Store::new(&super::engine(), ());
+    let u8_echo = make_echo_component_with_params("u8", &[Param(Type::U8, Some(0))]);
+    let string_echo = make_echo_component("string", 8);
+    let params = [
+        echo_func(&mut store, &u8_echo)?.params(&store)[0].clone(),
+        echo_func(&mut store, &string_echo)?.params(&store)[0].clone(),
+    ];
+
+    let args = from_json_args(&params, &json!([1, "a"]))?;
+    assert_eq!(to_json_args(&args)?, json!([1, "a"]));
+
+    let err = from_json_args(&params, &json!({})).unwrap_err();
+    assert_eq!(err.to_string(), "expected an array, found an object at `$`");
+    let err = from_json_args(&params, &json!([1])).unwrap_err();
+    assert_eq!(err.to_string(), "expected 2 elements, found 1 at `$`");
+    let err = from_json_args(&params, &json!([1, 2])).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a string, found a number at `$[1]`"
+    );
+
+    Ok(())
+}
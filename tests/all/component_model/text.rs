@@ -0,0 +1,157 @@
+#![cfg(not(miri))]
+
+use super::{make_echo_component, make_echo_component_with_params, Param, Type};
+use anyhow::Result;
+use component_test_util::FuncExt;
+use wasmtime::component::text::{self, Handles};
+use wasmtime::component::{Component, Linker, Val};
+use wasmtime::Store;
+
+const RECORD: &str = r#"
+    (type $shape' (variant
+        (case "circle" u32)
+        (case "square" (tuple u32 u32))
+        (case "empty")
+    ))
+    (export $shape "shape" (type $shape'))
+    (type $mode' (enum "ok" "broken"))
+    (export $mode "mode" (type $mode'))
+    (type $perms' (flags "read" "write"))
+    (export $perms "perms" (type $perms'))
+    (type $Foo' (record
+        (field "name" string)
+        (field "tags" (list string))
+        (field "size" (option u32))
+        (field "shape" $shape)
+        (field "mode" $mode)
+        (field "res" (result u8 (error string)))
+        (field "perms" $perms)
+    ))
+"#;
+
+/// Parses `input` as the parameter of the echo component `component`, calls
+/// it and formats the result.
+fn echo(component: &str, input: &str) -> Result<String> {
+    let engine = super::engine();
+    let mut store = Store::new(&engine, ());
+    let component = Component::new(&engine, component)?;
+    let instance = Linker::new(&engine).instantiate(&mut store, &component)?;
+    let func = instance.get_func(&mut store, "echo").unwrap();
+    let mut handles = Handles::default();
+    let input = text::parse(&func.params(&store)[0], input, &handles)?;
+    let mut output = [Val::Bool(false)];
+    func.call_and_post_return(&mut store, &[input], &mut output)?;
+    Ok(text::to_string(&output[0], &mut handles))
+}
+
+#[test]
+fn primitives() -> Result<()> {
+    for (input, ty, param) in [
+        ("true", "bool", Param(Type::U8, Some(0))),
+        ("-42", "s8", Param(Type::S8, Some(0))),
+        ("4242", "u16", Param(Type::U16, Some(0))),
+        ("-314159265", "s32", Param(Type::I32, Some(0))),
+        ("31415926535897", "u64", Param(Type::I64, Some(0))),
+        ("3.5", "float32", Param(Type::F32, Some(0))),
+        ("-inf", "float64", Param(Type::F64, Some(0))),
+        ("nan", "float64", Param(Type::F64, Some(0))),
+        ("'🦀'", "char", Param(Type::I32, Some(0))),
+        ("'\\''", "char", Param(Type::I32, Some(0))),
+    ] {
+        let component = make_echo_component_with_params(ty, &[param]);
+        assert_eq!(echo(&component, input)?, input);
+    }
+
+    let component = make_echo_component("string", 8);
+    for input in [r#""""#, r#""hello, \"component\"!\n""#, r#""\u{1b}[0m""#] {
+        assert_eq!(echo(&component, input)?, input);
+    }
+    assert_eq!(echo(&component, r#""\u{41}\t""#)?, r#""A\t""#);
+
+    Ok(())
+}
+
+#[test]
+fn compound() -> Result<()> {
+    let component = make_echo_component(RECORD, 56);
+
+    for input in [
+        r#"{name: "x", tags: [], size: none, shape: empty, mode: %ok, res: ok(7), perms: {}}"#,
+        r#"{name: "café", tags: ["a", "b"], size: some(42), shape: square((1, 2)), mode: broken, res: err("nope"), perms: {read, write}}"#,
+    ] {
+        assert_eq!(echo(&component, input)?, input);
+    }
+
+    // Whitespace, trailing commas, any field order, `%`-less keywords and
+    // omitted `option` fields are accepted.
+    assert_eq!(
+        echo(
+            &component,
+            r#"{
+                perms: {write,},
+                res: ok(0),
+                mode: ok,
+                shape: circle( 3 ),
+                tags: [ "t", ],
+                name: "n",
+            }"#,
+        )?,
+        r#"{name: "n", tags: ["t"], size: none, shape: circle(3), mode: %ok, res: ok(0), perms: {write}}"#,
+    );
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> Result<()> {
+    let engine = super::engine();
+    let mut store = Store::new(&engine, ());
+    let component = Component::new(&engine, make_echo_component(RECORD, 56))?;
+    let instance = Linker::new(&engine).instantiate(&mut store, &component)?;
+    let func = instance.get_func(&mut store, "echo").unwrap();
+    let ty = &func.params(&store)[0];
+    let handles = Handles::default();
+
+    for (input, expected) in [
+        (r#"{name: "x"}"#, "missing field `tags`"),
+        (
+            r#"{name: "x", name: "y"}"#,
+            "duplicate field `name` at offset 12",
+        ),
+        (r#"{nam: "x"}"#, "unknown field `nam` at offset 1"),
+        (r#"{name: x}"#, "expected `\"` at offset 7"),
+        (r#"{name: "x}"#, "missing closing `\"`"),
+        (r#"{size: some}"#, "`some` requires a payload"),
+        (r#"{size: some(-1)}"#, "invalid u32 `-1` at offset 12"),
+        (r#"{shape: empty(1)}"#, "`empty` does not have a payload"),
+        (r#"{shape: round}"#, "unknown variant case `round`"),
+        (r#"{perms: {exec}}"#, "unknown flag `exec`"),
+        (
+            r#"{name: "x", tags: [], shape: empty, mode: ok, res: ok(1), perms: {}} x"#,
+            "unexpected trailing input at offset 69",
+        ),
+    ] {
+        let err = text::parse(ty, input, &handles).unwrap_err();
+        let err = format!("{err:#}");
+        assert!(
+            err.contains(expected),
+            "unexpected error for {input}: {err}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn calls() -> Result<()> {
+    assert_eq!(text::parse_call("run")?, ("run", ""));
+    assert_eq!(text::parse_call(" add(1, 2) ")?, ("add", "1, 2"));
+    assert_eq!(
+        text::parse_call("wasi:cli/run#run()")?,
+        ("wasi:cli/run#run", "")
+    );
+    assert!(text::parse_call("add(1, 2").is_err());
+    assert!(text::parse_call("(1)").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+#![cfg(not(miri))]
+
+use anyhow::Result;
+use wasmtime::component::{wit, Component};
+
+const WORLD: &str = r#"package root:component;
+
+world root {
+  import wasi:io/streams@0.2.0;
+  import acme:app/types;
+  import log: func(msg: string);
+  export acme:app/run@1.0.0;
+  export run: func() -> u32;
+}
+"#;
+
+const PACKAGES: &[(&str, &str)] = &[
+    (
+        "acme:app",
+        r#"package acme:app;
+
+interface types {
+  use wasi:io/streams@0.2.0.{input-stream as %stream};
+  record point {
+    x: s32,
+    y: s32,
+  }
+  enum mode {
+    fast,
+    %static,
+  }
+  distance: func(a: point, b: point) -> (d: u32, m: mode);
+}
+"#,
+    ),
+    (
+        "acme:app@1.0.0",
+        r#"package acme:app@1.0.0;
+
+interface run {
+  run: func() -> u32;
+}
+"#,
+    ),
+    (
+        "wasi:io@0.2.0",
+        r#"package wasi:io@0.2.0;
+
+interface streams {
+  resource input-stream {
+    read: func(len: u64) -> list<u8>;
+    empty: static func() -> input-stream;
+  }
+}
+"#,
+    ),
+];
+
+const COMPONENT: &str = r#"
+    (component $C
+        (import "wasi:io/streams@0.2.0" (instance $streams
+            (export "input-stream" (type (sub resource)))
+            (export "[method]input-stream.read"
+                (func (param "self" (borrow 0)) (param "len" u64) (result (list u8))))
+            (export "[static]input-stream.empty" (func (result (own 0))))
+        ))
+        (alias export $streams "input-stream" (type $input-stream))
+        (import "acme:app/types" (instance $types
+            (type $point' (record (field "x" s32) (field "y" s32)))
+            (export "point" (type (eq $point')))
+            (type $mode' (enum "fast" "static"))
+            (export "mode" (type (eq $mode')))
+            (alias outer $C $input-stream (type $s))
+            (export "stream" (type (eq $s)))
+            (export "distance"
+                (func (param "a" 1) (param "b" 1) (result "d" u32) (result "m" 3)))
+        ))
+        (import "log" (func (param "msg" string)))
+
+        (core module $m
+            (func (export "run") (result i32) i32.const 0)
+        )
+        (core instance $i (instantiate $m))
+        (func $run (result u32) (canon lift (core func $i "run")))
+        (instance $app (export "run" (func $run)))
+        (export "acme:app/run@1.0.0" (instance $app))
+        (export "run" (func $run))
+    )
+"#;
+
+#[test]
+fn print_world() -> Result<()> {
+    let engine = super::engine();
+    let component = Component::new(&engine, COMPONENT)?;
+    let ty = component.component_type();
+    assert_eq!(wit::print_world(&engine, &ty), WORLD);
+    let packages = wit::print_packages(&engine, &ty);
+    assert_eq!(
+        packages
+            .iter()
+            .map(|(name, wit)| (name.as_str(), wit.as_str()))
+            .collect::<Vec<_>>(),
+        PACKAGES
+    );
+
+    // Precompiled components retain the same information.
+    let component = unsafe { Component::deserialize(&engine, component.serialize()?)? };
+    let ty = component.component_type();
+    assert_eq!(wit::print_world(&engine, &ty), WORLD);
+    assert_eq!(wit::print_packages(&engine, &ty), packages);
+
+    Ok(())
+}
+
+#[test]
+fn print_world_resolves() -> Result<()> {
+    let engine = super::engine();
+    let component = Component::new(&engine, COMPONENT)?;
+    let ty = component.component_type();
+
+    // The world and the packages form a WIT package with its dependencies.
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("world.wit"), wit::print_world(&engine, &ty))?;
+    std::fs::create_dir(dir.path().join("deps"))?;
+    for (i, (_, wit)) in wit::print_packages(&engine, &ty).iter().enumerate() {
+        std::fs::write(dir.path().join(format!("deps/{i}.wit")), wit)?;
+    }
+    let mut resolve = wit_parser::Resolve::default();
+    let (package, _) = resolve.push_dir(dir.path())?;
+    let world = resolve.select_world(package, Some("root"))?;
+    let world = &resolve.worlds[world];
+    assert_eq!(world.imports.len(), 3);
+    assert_eq!(world.exports.len(), 2);
+    Ok(())
+}
@@ -2,6 +2,7 @@
 
 mod async_functions;
 mod call_hook;
+mod checkpoint;
 mod cli_tests;
 mod code_too_large;
 mod component_model;
@@ -29,6 +30,7 @@ mod module_serialize;
 mod name;
 mod piped_tests;
 mod pooling_allocator;
+mod preinit;
 mod relocs;
 mod stack_creator;
 mod stack_overflow;
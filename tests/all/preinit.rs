@@ -0,0 +1,176 @@
+#![cfg(not(miri))]
+
+use anyhow::Result;
+use wasmtime::*;
+
+const WAT: &str = r#"
+    (module
+        (memory (export "memory") 1 10)
+        (data (i32.const 0) "abc")
+        (data $passive "xyz")
+
+        (global $starts (export "starts") (mut i32) (i32.const 0))
+        (global $inits (export "inits") (mut i32) (i32.const 0))
+        (global $wide (export "wide") (mut i64) (i64.const 0))
+        (global $float (export "float") (mut f64) (f64.const 0))
+        (global $vector (export "vector") (mut v128) (v128.const i64x2 0 0))
+        (global $callback (export "callback") (mut funcref) (ref.null func))
+        (global $offset (export "offset") i32 (i32.const 100))
+
+        (table $t (export "table") 1 funcref)
+        (elem (i32.const 0) $one)
+        (elem $declared declare func $two)
+
+        (func $one (result i32) i32.const 1)
+        (func $two (result i32) i32.const 2)
+
+        (func $start (global.set $starts (i32.add (global.get $starts) (i32.const 1))))
+        (start $start)
+
+        (func (export "init")
+            (global.set $inits (i32.add (global.get $inits) (i32.const 1)))
+            (drop (memory.grow (i32.const 1)))
+            (i32.store (i32.const 100) (i32.const 0x01020304))
+            (i32.store (i32.const 70000) (i32.const -1))
+            (i32.store8 (i32.const 70010) (i32.const 7))
+            (global.set $wide (i64.const -2))
+            (global.set $float (f64.const 1.5))
+            (global.set $vector (v128.const i64x2 3 4))
+            (global.set $callback (ref.func $two))
+            (drop (table.grow $t (ref.null func) (i32.const 2)))
+            (table.set $t (i32.const 2) (ref.func $two))
+        )
+
+        (func (export "load-passive") (result i32)
+            (memory.init $passive (i32.const 200) (i32.const 0) (i32.const 3))
+            (i32.load8_u (i32.const 202))
+        )
+
+        (func (export "call") (param i32) (result i32)
+            (call_indirect (result i32) (local.get 0))
+        )
+    )
+"#;
+
+#[test]
+fn preinitialize_snapshots_state() -> Result<()> {
+    let engine = Engine::default();
+    let wasm = engine.preinitialize(WAT.as_bytes(), "init")?;
+    wasmparser::validate(&wasm)?;
+
+    // Compare against the original module with `init` called at runtime.
+    let mut store = Store::new(&engine, ());
+    let original = Instance::new(&mut store, &Module::new(&engine, WAT)?, &[])?;
+    original
+        .get_typed_func::<(), ()>(&mut store, "init")?
+        .call(&mut store, ())?;
+
+    let module = Module::new(&engine, &wasm)?;
+    assert!(module.get_export("init").is_none());
+    let instance = Instance::new(&mut store, &module, &[])?;
+
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    let expected = original.get_memory(&mut store, "memory").unwrap();
+    assert_eq!(memory.size(&store), 2);
+    assert_eq!(memory.ty(&store).maximum(), Some(10));
+    assert_eq!(memory.data(&store), expected.data(&store));
+
+    // The start function ran once, before pre-initialization, and not again.
+    for (name, value) in [("starts", 1), ("inits", 1), ("offset", 100)] {
+        let global = instance.get_global(&mut store, name).unwrap();
+        assert_eq!(global.get(&mut store).i32(), Some(value));
+    }
+    let global = instance.get_global(&mut store, "wide").unwrap();
+    assert_eq!(global.get(&mut store).i64(), Some(-2));
+    let global = instance.get_global(&mut store, "float").unwrap();
+    assert_eq!(global.get(&mut store).f64(), Some(1.5));
+    let global = instance.get_global(&mut store, "vector").unwrap();
+    assert_eq!(global.get(&mut store).v128(), Some(V128::from(4 << 64 | 3)));
+    let global = instance.get_global(&mut store, "callback").unwrap();
+    let callback = global.get(&mut store).unwrap_funcref().copied().unwrap();
+    assert_eq!(callback.typed::<(), i32>(&store)?.call(&mut store, ())?, 2);
+
+    let table = instance.get_table(&mut store, "table").unwrap();
+    assert_eq!(table.size(&store), 3);
+    let call = instance.get_typed_func::<u32, i32>(&mut store, "call")?;
+    assert_eq!(call.call(&mut store, 0)?, 1);
+    assert!(call.call(&mut store, 1).is_err());
+    assert_eq!(call.call(&mut store, 2)?, 2);
+
+    // Passive segments keep their indices.
+    let load = instance.get_typed_func::<(), i32>(&mut store, "load-passive")?;
+    assert_eq!(load.call(&mut store, ())?, i32::from(b'z'));
+    Ok(())
+}
+
+#[test]
+fn preinitialize_sparse_memory() -> Result<()> {
+    // Writes a byte every 32 bytes of 20 pages, which would be written as
+    // 40,960 data segments if each run of zeros split the data.
+    const SPARSE: &str = r#"
+        (module
+            (memory (export "memory") 20)
+            (func (export "init")
+                (local $i i32)
+                (loop $loop
+                    (i32.store8 (local.get $i) (i32.const 1))
+                    (local.set $i (i32.add (local.get $i) (i32.const 32)))
+                    (br_if $loop (i32.lt_u (local.get $i) (i32.const 1310720)))
+                )
+            )
+        )
+    "#;
+    let engine = Engine::default();
+    let wasm = engine.preinitialize(SPARSE.as_bytes(), "init")?;
+    wasmparser::validate(&wasm)?;
+
+    let mut segments = 0;
+    for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+        if let wasmparser::Payload::DataSection(reader) = payload? {
+            segments += reader.count();
+        }
+    }
+    assert!(segments <= 10_000, "{segments} data segments");
+
+    let mut store = Store::new(&engine, ());
+    let original = Instance::new(&mut store, &Module::new(&engine, SPARSE)?, &[])?;
+    original
+        .get_typed_func::<(), ()>(&mut store, "init")?
+        .call(&mut store, ())?;
+    let instance = Instance::new(&mut store, &Module::new(&engine, &wasm)?, &[])?;
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+    let expected = original.get_memory(&mut store, "memory").unwrap();
+    assert!(memory.data(&store) == expected.data(&store));
+    Ok(())
+}
+
+#[test]
+fn preinitialize_errors() -> Result<()> {
+    let engine = Engine::default();
+
+    let err = engine
+        .preinitialize(b"(module (import \"\" \"\" (func)))", "init")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot pre-initialize a module with imports"
+    );
+
+    let err = engine
+        .preinitialize(b"(module (func (export \"init\") unreachable))", "init")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to run initialization function `init`"
+    );
+
+    let err = engine
+        .preinitialize(b"(module (func (export \"init\") (param i32)))", "init")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to convert function `init` to given type"
+    );
+    assert!(engine.preinitialize(b"(module)", "init").is_err());
+    Ok(())
+}